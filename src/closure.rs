@@ -0,0 +1,53 @@
+use crate::parser::Expression;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// An expression that is only evaluated when it is unwrapped, like `: (1 1 +)` or `{ 1 1 + }`.
+///
+/// Unwrapped in the call it was made in, a closure sees the variables of that call by
+/// reference, so it sees later changes to them. Once the call is done, it keeps a copy of the
+/// variables from when it was made, so they outlive their scope.
+///
+/// A closure with parameters is called like a function. It comes from a group that is missing
+/// arguments on its left, like `(2 *)`, from a builtin passed as a value like `add`, or from
+/// `f g compose`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Closure {
+    pub parameters: Vec<String>,
+    pub body: Expression,
+    pub captured: HashMap<String, Value>,
+
+    // The id of the call the closure was made in
+    pub frame: usize,
+
+    // How the closure was written when its body is not, like `(1 +) (2 *) compose`
+    pub source: Option<String>,
+}
+
+/// The names of the arguments missing from a partial application, like `_1` in `_1 2 *`. They
+/// start with `_` so they cannot be written in a program and never hide a variable.
+pub fn placeholders(count: usize) -> Vec<String> {
+    (1..=count).map(|i| format!("_{}", i)).collect()
+}
+
+/// The source of a closure, where a partial application is written the way it was made, like
+/// `(2 *)` rather than `_1 2 *`
+pub fn closure_source(parameters: &[String], body: &Expression) -> String {
+    let source = body.source();
+    if parameters.is_empty() {
+        return source;
+    }
+
+    let missing = format!("{} ", parameters.join(" "));
+    format!("({})", source.strip_prefix(&missing).unwrap_or(&source))
+}
+
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{}", source),
+            None => write!(f, "{}", closure_source(&self.parameters, &self.body)),
+        }
+    }
+}
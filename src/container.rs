@@ -0,0 +1,296 @@
+use crate::error::BasisError;
+use crate::number::Number;
+use crate::value::{unique, Value};
+use crate::vector::Vector;
+use std::cmp::Ordering;
+
+/// A value that holds other values, which is a vector, tuple, set, sample or population. They
+/// all have the `container` type, so `f (c container[int])` takes any of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Container {
+    Vector(Vector),
+    Tuple(Vec<Value>),
+    Set(Vec<Value>),
+    Sample(Vec<Value>),
+    Population(Vec<Value>),
+}
+
+/// Check that the values of a sample or population are all numbers
+fn numbers(kind: &str, values: Vec<Value>) -> Result<Vec<Value>, BasisError> {
+    match values
+        .iter()
+        .find(|v| !matches!(v, Value::Number(_) | Value::Estimate { .. }))
+    {
+        Some(other) => Err(BasisError::new(&format!(
+            "A {} can only hold numbers, but got {}",
+            kind,
+            other.type_name()
+        ))),
+        None => Ok(values),
+    }
+}
+
+/// The order of two values, which only numbers and strings have
+fn order(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.clone().compare(y.clone()),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+impl Container {
+    pub fn is_container(value: &Value) -> bool {
+        matches!(
+            value,
+            Value::Vector(_)
+                | Value::Tuple(_)
+                | Value::Set(_)
+                | Value::Sample(_)
+                | Value::Population(_)
+        )
+    }
+
+    pub fn from_value(value: Value) -> Result<Container, BasisError> {
+        match value {
+            Value::Vector(vector) => Ok(Container::Vector(vector)),
+            Value::Tuple(values) => Ok(Container::Tuple(values)),
+            Value::Set(values) => Ok(Container::Set(values)),
+            Value::Sample(values) => Ok(Container::Sample(values)),
+            Value::Population(values) => Ok(Container::Population(values)),
+            other => Err(BasisError::new(&format!(
+                "Expected a container but got {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    pub fn into_value(self) -> Value {
+        match self {
+            Container::Vector(vector) => Value::Vector(vector),
+            Container::Tuple(values) => Value::Tuple(values),
+            Container::Set(values) => Value::Set(values),
+            Container::Sample(values) => Value::Sample(values),
+            Container::Population(values) => Value::Population(values),
+        }
+    }
+
+    /// A sample of numbers like `(1 2 3) sample`, whose statistics are of a larger population
+    pub fn sample(values: Vec<Value>) -> Result<Container, BasisError> {
+        Ok(Container::Sample(numbers("sample", values)?))
+    }
+
+    /// A population of numbers like `(1 2 3) population`, whose statistics are of all of it
+    pub fn population(values: Vec<Value>) -> Result<Container, BasisError> {
+        Ok(Container::Population(numbers("population", values)?))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Container::Vector(vector) => vector.elements.len(),
+            Container::Tuple(values)
+            | Container::Set(values)
+            | Container::Sample(values)
+            | Container::Population(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values in order, where a vector with more than one row goes along each row in turn
+    pub fn values(&self) -> Vec<Value> {
+        match self {
+            Container::Vector(vector) => {
+                vector.elements.iter().cloned().map(Value::Number).collect()
+            }
+            Container::Tuple(values)
+            | Container::Set(values)
+            | Container::Sample(values)
+            | Container::Population(values) => values.clone(),
+        }
+    }
+
+    /// The value at an index counted from zero, like `(4 5 6) 1 at`, where a negative index
+    /// counts back from the end
+    pub fn get(&self, index: i64) -> Result<Value, BasisError> {
+        let len = self.len() as i64;
+        let position = if index < 0 { len + index } else { index };
+
+        if position < 0 || position >= len {
+            return Err(BasisError::new(&format!(
+                "The index {} is out of range for a container of size {}",
+                index, len
+            )));
+        }
+
+        Ok(self.values().swap_remove(position as usize))
+    }
+
+    /// A container of the same kind with new values. A vector keeps its shape when the number
+    /// of values stays the same and becomes a single row otherwise.
+    fn rebuild(&self, values: Vec<Value>) -> Result<Container, BasisError> {
+        match self {
+            Container::Vector(vector) => {
+                let mut elements = Vec::<Number>::new();
+                for value in values {
+                    match value.without_estimate() {
+                        Value::Number(number) => elements.push(number),
+                        other => {
+                            return Err(BasisError::new(&format!(
+                                "A vector can only hold numbers, but got {}",
+                                other.type_name()
+                            )))
+                        }
+                    }
+                }
+
+                let rows = match elements.len() == vector.elements.len() {
+                    true => elements
+                        .chunks(vector.columns.max(1))
+                        .map(<[Number]>::to_vec)
+                        .collect(),
+                    false => vec![elements],
+                };
+
+                Ok(Container::Vector(Vector::new(rows)?))
+            }
+            Container::Tuple(_) => Ok(Container::Tuple(values)),
+            Container::Set(_) => Ok(Container::Set(unique(values))),
+            Container::Sample(_) => Container::sample(values),
+            Container::Population(_) => Container::population(values),
+        }
+    }
+
+    /// Apply a function to each value, where a set drops the values that end up the same
+    pub fn map(
+        self,
+        mut function: impl FnMut(Value) -> Result<Value, BasisError>,
+    ) -> Result<Container, BasisError> {
+        let mut out = Vec::<Value>::new();
+        for value in self.values() {
+            out.push(function(value)?);
+        }
+
+        self.rebuild(out)
+    }
+
+    /// Keep the values that a function is true for
+    pub fn filter(
+        self,
+        mut keep: impl FnMut(&Value) -> Result<bool, BasisError>,
+    ) -> Result<Container, BasisError> {
+        let mut out = Vec::<Value>::new();
+        for value in self.values() {
+            if keep(&value)? {
+                out.push(value);
+            }
+        }
+
+        self.rebuild(out)
+    }
+
+    /// Combine the values into one, starting from `initial`, like the sum in `c 0 add fold`
+    pub fn fold(
+        self,
+        initial: Value,
+        mut function: impl FnMut(Value, Value) -> Result<Value, BasisError>,
+    ) -> Result<Value, BasisError> {
+        let mut total = initial;
+        for value in self.values() {
+            total = function(total, value)?;
+        }
+
+        Ok(total)
+    }
+
+    /// The values from smallest to largest, where only numbers and strings can be sorted
+    pub fn sort(self) -> Result<Container, BasisError> {
+        let mut values = self.values();
+        let mut unordered: Option<(&str, &str)> = None;
+
+        values.sort_by(|a, b| {
+            order(a, b).unwrap_or_else(|| {
+                unordered.get_or_insert((a.type_name(), b.type_name()));
+                Ordering::Equal
+            })
+        });
+
+        if let Some((a, b)) = unordered {
+            return Err(BasisError::new(&format!("Cannot order {} and {}", a, b)));
+        }
+
+        self.rebuild(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ints(values: &[i64]) -> Vec<Value> {
+        values.iter().map(|v| Value::int(*v)).collect()
+    }
+
+    #[test]
+    fn container_test() {
+        let tuple = Container::Tuple(ints(&[3, 1, 2]));
+        assert_eq!(tuple.len(), 3);
+        assert_eq!(tuple.get(0), Ok(Value::int(3)));
+        assert_eq!(tuple.get(-1), Ok(Value::int(2)));
+        assert!(tuple.get(3).is_err());
+
+        assert_eq!(tuple.clone().sort(), Ok(Container::Tuple(ints(&[1, 2, 3]))));
+        assert_eq!(
+            tuple.filter(|v| Ok(v != &Value::int(1))),
+            Ok(Container::Tuple(ints(&[3, 2])))
+        );
+
+        // A set drops the values that map to the same value
+        let set = Container::Set(ints(&[1, 2, 3]));
+        assert_eq!(
+            set.map(|_| Ok(Value::int(0))),
+            Ok(Container::Set(ints(&[0])))
+        );
+
+        // A vector keeps its shape unless the number of values changes
+        let vector = Vector::new(vec![
+            vec![Number::Int(1), Number::Int(2)],
+            vec![Number::Int(3), Number::Int(4)],
+        ])
+        .unwrap();
+        let doubled = Container::Vector(vector.clone())
+            .map(|v| Ok(Value::int(v.as_dec()? as i64 * 2)))
+            .unwrap();
+        assert_eq!(doubled.into_value().size().unwrap().dimensions, vec![2, 2]);
+
+        let odd = Container::Vector(vector)
+            .filter(|v| Ok(v.as_dec()? as i64 % 2 == 1))
+            .unwrap();
+        assert_eq!(
+            odd.into_value().to_string(),
+            "vector[int]: [1 3] -> [size: 1x2]"
+        );
+
+        let sum = Container::Tuple(ints(&[1, 2, 3])).fold(Value::int(10), |total, v| {
+            Ok(Value::int(total.as_dec()? as i64 + v.as_dec()? as i64))
+        });
+        assert_eq!(sum, Ok(Value::int(16)));
+
+        assert!(Container::Tuple(vec![Value::int(1), Value::Bool(true)])
+            .sort()
+            .is_err());
+        assert!(Container::from_value(Value::int(1)).is_err());
+
+        // A sample stays a sample of numbers
+        let sample = Container::sample(ints(&[1, 2])).unwrap();
+        assert_eq!(
+            sample
+                .clone()
+                .map(|v| Ok(Value::int(v.as_dec()? as i64 + 1))),
+            Ok(Container::Sample(ints(&[2, 3])))
+        );
+        assert!(sample.map(|_| Ok(Value::Bool(true))).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::error::BasisError;
+use crate::lexer::TokenType;
+use crate::parser::{Expression, Operation};
+use crate::simplify::simplify;
+
+/// Whether the variable appears anywhere in the expression
+pub fn depends_on(exp: &Expression, variable: &str) -> bool {
+    match exp {
+        Expression::TokenExpression(t) => {
+            t.token.token_type == TokenType::Identifier && t.token.value == variable
+        }
+        Expression::ExpExpOp(i) => {
+            depends_on(&i.expression_1, variable) || depends_on(&i.expression_2, variable)
+        }
+        Expression::Call(c) => c.arguments.iter().any(|a| depends_on(a, variable)),
+        _ => false,
+    }
+}
+
+fn add(a: Expression, b: Expression) -> Expression {
+    Expression::binary(a, b, TokenType::Addition)
+}
+
+fn sub(a: Expression, b: Expression) -> Expression {
+    Expression::binary(a, b, TokenType::Subtraction)
+}
+
+fn mul(a: Expression, b: Expression) -> Expression {
+    Expression::binary(a, b, TokenType::Multiplication)
+}
+
+fn div(a: Expression, b: Expression) -> Expression {
+    Expression::binary(a, b, TokenType::Division)
+}
+
+fn pow(a: Expression, b: Expression) -> Expression {
+    Expression::binary(a, b, TokenType::Power)
+}
+
+/// The derivative of a function applied to `u`, not including the `u'` from the chain rule
+fn derive_call(name: &str, u: &Expression) -> Result<Expression, BasisError> {
+    let u = u.clone();
+
+    match name {
+        "sin" => Ok(Expression::call("cos", vec![u])),
+        "cos" => Ok(mul(Expression::int(-1), Expression::call("sin", vec![u]))),
+        "tan" => Ok(pow(Expression::call("cos", vec![u]), Expression::int(-2))),
+        "exp" => Ok(Expression::call("exp", vec![u])),
+        "log" => Ok(pow(u, Expression::int(-1))),
+        "sqrt" => Ok(div(
+            Expression::int(1),
+            mul(Expression::int(2), Expression::call("sqrt", vec![u])),
+        )),
+        _ => Err(BasisError::new(&format!(
+            "Cannot differentiate the function `{}`",
+            name
+        ))),
+    }
+}
+
+fn derive_expression(exp: &Expression, variable: &str) -> Result<Expression, BasisError> {
+    if !depends_on(exp, variable) {
+        return Ok(Expression::int(0));
+    }
+
+    match exp {
+        // Only the variable itself is left since everything else is constant
+        Expression::TokenExpression(_) => Ok(Expression::int(1)),
+
+        Expression::ExpExpOp(i) => {
+            let a = i.expression_1.as_ref().clone();
+            let b = i.expression_2.as_ref().clone();
+            let da = derive_expression(&a, variable)?;
+            let db = derive_expression(&b, variable)?;
+
+            let Operation::TokenOperation(c) = i.operation.as_ref();
+            match c.token.token_type {
+                TokenType::Addition => Ok(add(da, db)),
+                TokenType::Subtraction => Ok(sub(da, db)),
+
+                // Product rule: (a b)' = a' b + a b'
+                TokenType::Multiplication => Ok(add(mul(da, b), mul(a, db))),
+
+                // Quotient rule: (a / b)' = (a' b - a b') / b^2
+                TokenType::Division => Ok(div(
+                    sub(mul(da, b.clone()), mul(a, db)),
+                    pow(b, Expression::int(2)),
+                )),
+
+                TokenType::Power => {
+                    if !depends_on(&b, variable) {
+                        // Power rule: (a^n)' = n a^(n - 1) a'
+                        Ok(mul(mul(b.clone(), pow(a, sub(b, Expression::int(1)))), da))
+                    } else if !depends_on(&a, variable) {
+                        // Exponential rule: (c^b)' = c^b log(c) b'
+                        Ok(mul(
+                            mul(pow(a.clone(), b), Expression::call("log", vec![a])),
+                            db,
+                        ))
+                    } else {
+                        // General rule: (a^b)' = a^b (b' log(a) + b a' / a)
+                        Ok(mul(
+                            pow(a.clone(), b.clone()),
+                            add(
+                                mul(db, Expression::call("log", vec![a.clone()])),
+                                div(mul(b, da), a),
+                            ),
+                        ))
+                    }
+                }
+
+                _ => Err(BasisError::new(&format!(
+                    "Cannot differentiate the operation `{}`",
+                    c.token.value
+                ))),
+            }
+        }
+
+        // Chain rule: f(u)' = f'(u) u'
+        Expression::Call(c) => match c.arguments.as_slice() {
+            [u] => Ok(mul(
+                derive_call(&c.function.value, u)?,
+                derive_expression(u, variable)?,
+            )),
+            _ => Err(BasisError::new(&format!(
+                "Cannot differentiate the function `{}`",
+                c.function.value
+            ))),
+        },
+
+        _ => Err(BasisError::new("Cannot differentiate this expression")),
+    }
+}
+
+/// Differentiate an expression with respect to a variable and simplify the result
+pub fn derive(exp: &Expression, variable: &str) -> Result<Expression, BasisError> {
+    Ok(simplify(&derive_expression(exp, variable)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::math_notation;
+
+    fn x() -> Expression {
+        Expression::identifier("x")
+    }
+
+    #[test]
+    fn derive_polynomial_test() {
+        // "x 3 ^ x 2 * +" is "3 x^2 + 2"
+        let exp = add(pow(x(), Expression::int(3)), mul(x(), Expression::int(2)));
+        assert_eq!(math_notation(&derive(&exp, "x").unwrap()), "3 x^2 + 2");
+
+        // Other names are constants
+        let exp = mul(Expression::identifier("pi"), x());
+        assert_eq!(derive(&exp, "x").unwrap(), Expression::identifier("pi"));
+    }
+
+    #[test]
+    fn derive_product_test() {
+        // "x 2 ^ x sin *" is "x^2 cos(x) + 2 x sin(x)"
+        let exp = mul(
+            pow(x(), Expression::int(2)),
+            Expression::call("sin", vec![x()]),
+        );
+        assert_eq!(
+            math_notation(&derive(&exp, "x").unwrap()),
+            "x^2 cos(x) + 2 x sin(x)"
+        );
+    }
+
+    #[test]
+    fn derive_quotient_test() {
+        // "1 x /" is "-1 / x^2"
+        let exp = div(Expression::int(1), x());
+        assert_eq!(math_notation(&derive(&exp, "x").unwrap()), "-1 / x^2");
+    }
+
+    #[test]
+    fn derive_chain_test() {
+        // "x 2 * exp" is "2 exp(2 x)"
+        let exp = Expression::call("exp", vec![mul(x(), Expression::int(2))]);
+        assert_eq!(math_notation(&derive(&exp, "x").unwrap()), "2 exp(2 x)");
+
+        // "x log" is "1 / x"
+        let exp = Expression::call("log", vec![x()]);
+        assert_eq!(math_notation(&derive(&exp, "x").unwrap()), "1 / x");
+
+        // "e x ^" is "e^x"
+        let exp = pow(Expression::identifier("e"), x());
+        assert_eq!(math_notation(&derive(&exp, "x").unwrap()), "e^x");
+    }
+}
@@ -0,0 +1,721 @@
+use crate::error::BasisError;
+use crate::number::{Complex, Number};
+use crate::vector::Vector;
+
+/// The most sweeps the Jacobi methods take before giving up
+const MAX_SWEEPS: usize = 100;
+
+/// The most QR steps spent on finding a single eigenvalue
+const MAX_ITERATIONS: usize = 200;
+
+// Relative to the size of the vector, entries smaller than this are treated as zero
+const TOLERANCE: f64 = 1e-13;
+
+type Matrix = Vec<Vec<f64>>;
+type ComplexMatrix = Vec<Vec<Complex>>;
+
+fn transpose(rows: &Matrix) -> Matrix {
+    let columns = rows.first().map_or(0, Vec::len);
+    (0..columns)
+        .map(|column| rows.iter().map(|row| row[column]).collect())
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Turn the pair of columns `p` and `q` by the rotation with cosine `c` and sine `s`
+fn rotate(columns: &mut [Vec<f64>], p: usize, q: usize, c: f64, s: f64) {
+    let (head, tail) = columns.split_at_mut(q);
+    for (x, y) in head[p].iter_mut().zip(tail[0].iter_mut()) {
+        let (a, b) = (*x, *y);
+        *x = c * a - s * b;
+        *y = s * a + c * b;
+    }
+}
+
+/// The tangent of the angle that makes the off diagonal entry of `[[a b] [b d]]` zero, taking
+/// the smaller angle so the rotation is stable
+fn rotation_tangent(a: f64, b: f64, d: f64) -> f64 {
+    let theta = (d - a) / (2.0 * b);
+    theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt())
+}
+
+/// The eigenvalues and eigenvectors of a symmetric matrix by the Jacobi method, where the
+/// eigenvectors are the columns of the second matrix
+fn jacobi(mut a: Matrix) -> Result<(Vec<f64>, Matrix), BasisError> {
+    let size = a.len();
+    let scale = frobenius(&a).max(f64::MIN_POSITIVE);
+
+    // The columns of the eigenvectors, which start as the identity
+    let mut vectors: Matrix = (0..size)
+        .map(|row| (0..size).map(|c| f64::from(u8::from(row == c))).collect())
+        .collect();
+
+    for _ in 0..MAX_SWEEPS {
+        let off: f64 = (0..size)
+            .flat_map(|row| (0..size).filter(move |c| *c != row).map(move |c| (row, c)))
+            .map(|(row, c)| a[row][c] * a[row][c])
+            .sum();
+
+        if off.sqrt() <= TOLERANCE * scale {
+            let values = (0..size).map(|index| a[index][index]).collect();
+            return Ok((values, transpose(&vectors)));
+        }
+
+        for p in 0..size {
+            for q in p + 1..size {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+
+                let (diagonal, off) = ((a[p][p], a[q][q]), a[p][q]);
+                let t = rotation_tangent(diagonal.0, off, diagonal.1);
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                // A is turned on both sides, so the rows and then the columns
+                rotate(&mut a, p, q, c, s);
+                let mut columns = transpose(&a);
+                rotate(&mut columns, p, q, c, s);
+                a = transpose(&columns);
+
+                // The entries the rotation is for are set directly, which rounds less
+                a[p][p] = diagonal.0 - t * off;
+                a[q][q] = diagonal.1 + t * off;
+                (a[p][q], a[q][p]) = (0.0, 0.0);
+
+                rotate(&mut vectors, p, q, c, s);
+            }
+        }
+    }
+
+    Err(BasisError::new("`eig` did not converge"))
+}
+
+fn frobenius(rows: &Matrix) -> f64 {
+    rows.iter().flatten().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+/// The Givens rotation `[[c̄ s̄] [-s c]]` that takes `[a b]` to `[r 0]`, which is nothing when
+/// `b` is already zero
+fn givens(a: Complex, b: Complex) -> Option<(Complex, Complex)> {
+    let r = a.abs().hypot(b.abs());
+    if b.abs() == 0.0 || r == 0.0 {
+        return None;
+    }
+
+    let scale = Complex::new(r, 0.0);
+    Some((a.div(&scale)?, b.div(&scale)?))
+}
+
+/// Apply a rotation to rows `i` and `j`
+fn rotate_rows(m: &mut ComplexMatrix, i: usize, j: usize, (c, s): (Complex, Complex)) {
+    let (head, tail) = m.split_at_mut(j);
+    for (x, y) in head[i].iter_mut().zip(tail[0].iter_mut()) {
+        let (a, b) = (*x, *y);
+        *x = c.conj().mul(&a).add(&s.conj().mul(&b));
+        *y = c.mul(&b).sub(&s.mul(&a));
+    }
+}
+
+/// Apply the conjugate transpose of a rotation to columns `i` and `j`
+fn rotate_columns(m: &mut ComplexMatrix, i: usize, j: usize, (c, s): (Complex, Complex)) {
+    for row in m.iter_mut() {
+        let (a, b) = (row[i], row[j]);
+        row[i] = a.mul(&c).add(&b.mul(&s));
+        row[j] = b.mul(&c.conj()).sub(&a.mul(&s.conj()));
+    }
+}
+
+/// The eigenvalue of the trailing `2x2` block of the first `m` rows that is closest to its
+/// last entry
+fn wilkinson_shift(t: &ComplexMatrix, m: usize) -> Complex {
+    let (a, b) = (t[m - 2][m - 2], t[m - 2][m - 1]);
+    let (c, d) = (t[m - 1][m - 2], t[m - 1][m - 1]);
+
+    let half = Complex::new(0.5, 0.0);
+    let mean = a.add(&d).mul(&half);
+    let gap = a.sub(&d).mul(&half);
+    let root = gap.mul(&gap).add(&b.mul(&c)).sqrt();
+
+    let (x, y) = (mean.add(&root), mean.sub(&root));
+    match x.sub(&d).abs() <= y.sub(&d).abs() {
+        true => x,
+        false => y,
+    }
+}
+
+/// The Schur decomposition `A = Z T Z*` by the shifted QR algorithm, where `T` is upper
+/// triangular with the eigenvalues along its diagonal
+fn schur(mut t: ComplexMatrix) -> Result<(ComplexMatrix, ComplexMatrix), BasisError> {
+    let size = t.len();
+    let scale = t
+        .iter()
+        .flatten()
+        .map(|z| z.abs() * z.abs())
+        .sum::<f64>()
+        .sqrt()
+        .max(f64::MIN_POSITIVE);
+
+    let zero = Complex::new(0.0, 0.0);
+    let mut z: ComplexMatrix = (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|c| Complex::new(f64::from(u8::from(row == c)), 0.0))
+                .collect()
+        })
+        .collect();
+
+    let mut active = size;
+    let mut iterations = 0;
+
+    while active > 1 {
+        let last = active - 1;
+
+        // Once the last row is zero left of the diagonal, its eigenvalue is found
+        if t[last][..last].iter().all(|x| x.abs() <= TOLERANCE * scale) {
+            for entry in t[last][..last].iter_mut() {
+                *entry = zero;
+            }
+
+            active -= 1;
+            iterations = 0;
+            continue;
+        }
+
+        iterations += 1;
+        if iterations > MAX_ITERATIONS {
+            return Err(BasisError::new("`eig` did not converge"));
+        }
+
+        // Now and then a different shift breaks a cycle that the usual shift can fall into
+        let shift = match iterations % 11 {
+            0 => t[last][last].add(&Complex::new(t[last][last - 1].abs(), 0.0)),
+            _ => wilkinson_shift(&t, active),
+        };
+
+        let mut shifted: ComplexMatrix = t[..active].iter().map(|r| r[..active].to_vec()).collect();
+        for (index, row) in shifted.iter_mut().enumerate() {
+            row[index] = row[index].sub(&shift);
+        }
+
+        // The rotations of the QR decomposition of the shifted block
+        let mut rotations = Vec::<(usize, usize, (Complex, Complex))>::new();
+        for column in 0..active {
+            for row in column + 1..active {
+                if let Some(rotation) = givens(shifted[column][column], shifted[row][column]) {
+                    rotate_rows(&mut shifted, column, row, rotation);
+                    rotations.push((column, row, rotation));
+                }
+            }
+        }
+
+        // T becomes Q* T Q, which is R Q + shift on the block
+        for (i, j, rotation) in &rotations {
+            rotate_rows(&mut t, *i, *j, *rotation);
+        }
+        for (i, j, rotation) in &rotations {
+            rotate_columns(&mut t, *i, *j, *rotation);
+            rotate_columns(&mut z, *i, *j, *rotation);
+        }
+    }
+
+    Ok((t, z))
+}
+
+/// The eigenvectors of `A = Z T Z*`, found by back substitution on `T` and turned back by `Z`
+fn schur_vectors(t: &ComplexMatrix, z: &ComplexMatrix, scale: f64) -> Vec<Vec<Complex>> {
+    let size = t.len();
+    let zero = Complex::new(0.0, 0.0);
+    let smallest = Complex::new(TOLERANCE * scale.max(f64::MIN_POSITIVE), 0.0);
+
+    let mut out = Vec::<Vec<Complex>>::new();
+    for k in 0..size {
+        let value = t[k][k];
+
+        let mut y = vec![zero; size];
+        y[k] = Complex::new(1.0, 0.0);
+        for i in (0..k).rev() {
+            let sum = (i + 1..=k).fold(zero, |sum, j| sum.add(&t[i][j].mul(&y[j])));
+
+            // A repeated eigenvalue would divide by zero, so it is nudged instead
+            let mut gap = t[i][i].sub(&value);
+            if gap.abs() < smallest.re {
+                gap = smallest;
+            }
+
+            y[i] = zero.sub(&sum).div(&gap).unwrap_or(zero);
+        }
+
+        let vector: Vec<Complex> = z
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&y)
+                    .fold(zero, |sum, (a, b)| sum.add(&a.mul(b)))
+            })
+            .collect();
+
+        out.push(normalize(vector));
+    }
+
+    out
+}
+
+/// An eigenvector with length one and its largest entry made real and positive, where the
+/// first of entries that are as large is used so the same vector always comes out the same
+fn normalize(vector: Vec<Complex>) -> Vec<Complex> {
+    let zero = Complex::new(0.0, 0.0);
+    let most = vector.iter().map(Complex::abs).fold(0.0, f64::max);
+    let largest = vector
+        .iter()
+        .find(|x| x.abs() >= most * (1.0 - 1e-9))
+        .copied()
+        .unwrap_or(zero);
+    let length = vector.iter().map(|x| x.abs() * x.abs()).sum::<f64>().sqrt();
+
+    // Dividing by the phase of the largest entry and by the length
+    let unit = largest
+        .div(&Complex::new(largest.abs(), 0.0))
+        .map(|phase| phase.mul(&Complex::new(length, 0.0)));
+
+    match unit {
+        Some(unit) => vector
+            .iter()
+            .map(|x| x.div(&unit).unwrap_or(zero))
+            .collect(),
+        None => vector,
+    }
+}
+
+/// A complex result as a dec when its imaginary part is only rounding error
+fn settle(value: Complex, scale: f64) -> Number {
+    let small = TOLERANCE * scale.max(1.0);
+    let re = if value.re.abs() <= small {
+        0.0
+    } else {
+        value.re
+    };
+
+    match value.im.abs() <= small {
+        true => Number::Dec(re),
+        false => Number::Complex(Complex::new(re, value.im)),
+    }
+}
+
+/// A unit vector orthogonal to each of `vectors`, which are orthonormal and fewer than `length`.
+/// It is the standard basis vector that is furthest from them, less its part along each of them.
+fn orthogonal_unit(vectors: &Matrix, length: usize) -> Vec<f64> {
+    let mut furthest = Vec::new();
+    for axis in 0..length {
+        let mut residual: Vec<f64> = (0..length)
+            .map(|index| f64::from(u8::from(index == axis)))
+            .collect();
+
+        for vector in vectors {
+            let projection = dot(vector, &residual);
+            for (r, v) in residual.iter_mut().zip(vector) {
+                *r -= projection * v;
+            }
+        }
+
+        if furthest.is_empty() || dot(&residual, &residual) > dot(&furthest, &furthest) {
+            furthest = residual;
+        }
+    }
+
+    let norm = dot(&furthest, &furthest).sqrt();
+    furthest.iter().map(|x| x / norm).collect()
+}
+
+/// The singular value decomposition `A = U S Vᵀ` of a matrix with at least as many rows as
+/// columns, by the one sided Jacobi method. The singular values are largest first, and the
+/// columns of `U` for zero singular values complete the others to an orthonormal set.
+fn jacobi_svd(a: &Matrix) -> Result<(Matrix, Vec<f64>, Matrix), BasisError> {
+    let columns = a.first().map_or(0, Vec::len);
+
+    // Turning pairs of columns until every pair is orthogonal
+    let mut u = transpose(a);
+    let mut v: Matrix = (0..columns)
+        .map(|row| {
+            (0..columns)
+                .map(|c| f64::from(u8::from(row == c)))
+                .collect()
+        })
+        .collect();
+
+    let mut converged = false;
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+        for p in 0..columns {
+            for q in p + 1..columns {
+                let (alpha, beta) = (dot(&u[p], &u[p]), dot(&u[q], &u[q]));
+                let gamma = dot(&u[p], &u[q]);
+                if gamma.abs() <= TOLERANCE * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+
+                rotated = true;
+                let t = rotation_tangent(alpha, gamma, beta);
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                rotate(&mut u, p, q, c, t * c);
+                rotate(&mut v, p, q, c, t * c);
+            }
+        }
+
+        if !rotated {
+            converged = true;
+            break;
+        }
+    }
+
+    if !converged {
+        return Err(BasisError::new("`svd` did not converge"));
+    }
+
+    let mut order: Vec<(f64, usize)> = u
+        .iter()
+        .enumerate()
+        .map(|(index, column)| (dot(column, column).sqrt(), index))
+        .collect();
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let largest = order.first().map_or(0.0, |(value, _)| *value);
+    let mut left = Matrix::new();
+    let mut right = Matrix::new();
+    for (value, index) in &order {
+        // The zero singular values come last, so every other column of `U` is there already
+        let column = match *value > TOLERANCE * largest {
+            true => u[*index].iter().map(|x| x / value).collect(),
+            false => orthogonal_unit(&left, u[*index].len()),
+        };
+
+        left.push(column);
+        right.push(v[*index].clone());
+    }
+
+    let values = order.iter().map(|(value, _)| *value).collect();
+    Ok((transpose(&left), values, transpose(&right)))
+}
+
+impl Vector {
+    fn expect_square_decs(&self, name: &str) -> Result<Matrix, BasisError> {
+        if self.rows != self.columns {
+            return Err(BasisError::new(&format!(
+                "`{}` needs a square vector but got {}x{}",
+                name, self.rows, self.columns
+            )));
+        }
+
+        self.to_decs(name)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        let Ok(rows) = self.expect_square_decs("eig") else {
+            return false;
+        };
+
+        let scale = frobenius(&rows).max(f64::MIN_POSITIVE);
+        (0..self.rows).all(|r| (0..r).all(|c| (rows[r][c] - rows[c][r]).abs() <= TOLERANCE * scale))
+    }
+
+    /// The eigenvalues as a row and the eigenvectors as the columns of a vector, where `A v = λ v`.
+    /// A symmetric vector has real eigenvalues from smallest to largest, and any other vector
+    /// can have complex ones, which are ordered by their real and then imaginary parts.
+    pub fn eigen(&self) -> Result<(Vector, Vector), BasisError> {
+        if self.rows != self.columns {
+            return Err(BasisError::new(&format!(
+                "`eig` needs a square vector but got {}x{}",
+                self.rows, self.columns
+            )));
+        }
+
+        if self.rows == 0 {
+            return Ok((Vector::row(Vec::new())?, Vector::new(Vec::new())?));
+        }
+
+        let mut pairs: Vec<(Complex, Vec<Complex>)> = match self.is_symmetric() {
+            true => {
+                let (values, vectors) = jacobi(self.to_decs("eig")?)?;
+                let columns = transpose(&vectors);
+                values
+                    .into_iter()
+                    .zip(columns)
+                    .map(|(value, column)| {
+                        let column = column.iter().map(|x| Complex::new(*x, 0.0));
+                        (Complex::new(value, 0.0), normalize(column.collect()))
+                    })
+                    .collect()
+            }
+            false => {
+                let rows: ComplexMatrix = self
+                    .to_rows()
+                    .iter()
+                    .map(|row| row.iter().map(Number::to_complex).collect())
+                    .collect();
+                let (t, z) = schur(rows)?;
+                let scale = t.iter().flatten().map(|x| x.abs()).fold(0.0, f64::max);
+                let vectors = schur_vectors(&t, &z, scale);
+                (0..self.rows).map(|k| t[k][k]).zip(vectors).collect()
+            }
+        };
+
+        pairs.sort_by(|(a, _), (b, _)| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+
+        let scale = pairs
+            .iter()
+            .map(|(value, _)| value.abs())
+            .fold(0.0, f64::max);
+        let values = pairs.iter().map(|(value, _)| settle(*value, scale));
+        let columns: Vec<Vec<Number>> = pairs
+            .iter()
+            .map(|(_, column)| column.iter().map(|x| settle(*x, 1.0)).collect())
+            .collect();
+
+        Ok((
+            Vector::row(values.collect())?,
+            Vector::new(columns)?.transpose(),
+        ))
+    }
+
+    /// The singular value decomposition, giving back `U`, the singular values as a row, and `V`
+    /// where `A = U S Vᵀ`. For an `mxn` vector, `U` is `mxk` and `V` is `nxk` where `k` is the
+    /// smaller of `m` and `n`.
+    pub fn svd(&self) -> Result<(Vector, Vector, Vector), BasisError> {
+        let rows = self.to_decs("svd")?;
+        if self.rows == 0 || self.columns == 0 {
+            return Err(BasisError::new("`svd` needs a vector with elements"));
+        }
+
+        let (u, values, v) = match self.rows >= self.columns {
+            true => jacobi_svd(&rows)?,
+            false => {
+                let (u, values, v) = jacobi_svd(&transpose(&rows))?;
+                (v, values, u)
+            }
+        };
+
+        Ok((
+            Vector::from_decs(u)?,
+            Vector::from_decs(vec![values])?,
+            Vector::from_decs(v)?,
+        ))
+    }
+
+    /// The `x` that makes `A x` closest to `b`, like `[1 1; 1 2; 1 3] [1 2 2] lstsq`, where `b`
+    /// can be a single row that is read as a column. When many `x` are as close, this is the
+    /// shortest one.
+    pub fn least_squares(&self, other: &Vector) -> Result<Vector, BasisError> {
+        let row = other.rows == 1 && other.columns == self.rows && self.rows != 1;
+        let right = match row {
+            true => other.transpose(),
+            false => other.clone(),
+        };
+
+        if right.rows != self.rows {
+            return Err(BasisError::new(&format!(
+                "Cannot fit a {}x{} system to a {}x{} vector",
+                self.rows, self.columns, other.rows, other.columns
+            )));
+        }
+
+        let (u, values, v) = self.svd()?;
+        let (u, v) = (transpose(&u.to_decs("lstsq")?), v.to_decs("lstsq")?);
+        let values = values.to_decs("lstsq")?.remove(0);
+        let b = transpose(&right.to_decs("lstsq")?);
+
+        // x = V S⁺ Uᵀ b, where singular values that are only rounding error are dropped
+        let largest = values.first().copied().unwrap_or(0.0);
+        let cutoff = TOLERANCE * largest * self.rows.max(self.columns) as f64;
+
+        let mut solution = Matrix::new();
+        for column in &b {
+            let scaled: Vec<f64> = u
+                .iter()
+                .zip(&values)
+                .map(|(u, value)| match *value > cutoff {
+                    true => dot(u, column) / value,
+                    false => 0.0,
+                })
+                .collect();
+
+            solution.push(v.iter().map(|row| dot(row, &scaled)).collect());
+        }
+
+        let solution = Vector::from_decs(solution)?;
+        Ok(match row {
+            true => solution,
+            false => solution.transpose(),
+        })
+    }
+
+    /// The length of a single row or column, which is the Frobenius norm of a bigger vector
+    pub fn norm(&self) -> f64 {
+        self.elements
+            .iter()
+            .map(|n| n.to_complex().abs().powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// The `p` norm, like `[3 4] 1 pnorm`. A bigger vector only has the `1` norm, which is the
+    /// largest column sum, and the `2` norm, which is the largest singular value.
+    pub fn p_norm(&self, p: f64) -> Result<f64, BasisError> {
+        if self.rows <= 1 || self.columns <= 1 {
+            if p.is_nan() || p < 1.0 {
+                return Err(BasisError::new(&format!(
+                    "`pnorm` needs p to be at least 1 but got {}",
+                    p
+                )));
+            }
+
+            let sum: f64 = self
+                .elements
+                .iter()
+                .map(|n| n.to_complex().abs().powf(p))
+                .sum();
+            return Ok(sum.powf(1.0 / p));
+        }
+
+        match p {
+            1.0 => Ok(transpose(&self.to_decs("pnorm")?)
+                .iter()
+                .map(|column| column.iter().map(|x| x.abs()).sum::<f64>())
+                .fold(0.0, f64::max)),
+            2.0 => {
+                let (_, values, _) = self.svd()?;
+                Ok(values.elements[0].to_dec())
+            }
+            _ => Err(BasisError::new(&format!(
+                "`pnorm` of a {}x{} vector needs p to be 1 or 2 but got {}",
+                self.rows, self.columns, p
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[f64]]) -> Vector {
+        Vector::from_decs(rows.iter().map(|row| row.to_vec()).collect()).unwrap()
+    }
+
+    fn close(a: &Vector, b: &Vector) -> bool {
+        (a.rows, a.columns) == (b.rows, b.columns)
+            && a.elements
+                .iter()
+                .zip(&b.elements)
+                .all(|(x, y)| x.to_complex().sub(&y.to_complex()).abs() < 1e-9)
+    }
+
+    #[test]
+    fn eigen_test() {
+        // A symmetric vector has real eigenvalues from smallest to largest
+        let a = matrix(&[&[2.0, 1.0], &[1.0, 2.0]]);
+        let (values, vectors) = a.eigen().unwrap();
+        assert!(close(&values, &matrix(&[&[1.0, 3.0]])));
+
+        let s = 0.5_f64.sqrt();
+        assert!(close(&vectors, &matrix(&[&[s, s], &[-s, s]])));
+
+        // A v = v λ for every pair
+        let b = matrix(&[&[4.0, 1.0, 2.0], &[0.0, 3.0, 1.0], &[1.0, 0.0, 2.0]]);
+        let (values, vectors) = b.eigen().unwrap();
+        let mut scaled = vectors.clone();
+        for (index, element) in scaled.elements.iter_mut().enumerate() {
+            let value = values.elements[index % 3].to_complex();
+            *element = Number::Complex(element.to_complex().mul(&value));
+        }
+        assert!(close(&b.multiply(&vectors).unwrap(), &scaled));
+
+        // A rotation turns every real vector, so its eigenvalues are complex
+        let (values, _) = matrix(&[&[0.0, -1.0], &[1.0, 0.0]]).eigen().unwrap();
+        assert_eq!(values.element_type(), "complex");
+        assert!(close(
+            &values,
+            &Vector::row(vec![
+                Number::Complex(Complex::new(0.0, -1.0)),
+                Number::Complex(Complex::new(0.0, 1.0))
+            ])
+            .unwrap()
+        ));
+
+        assert!(matrix(&[&[1.0, 2.0]]).eigen().is_err());
+    }
+
+    #[test]
+    fn svd_test() {
+        let a = matrix(&[&[3.0, 0.0], &[4.0, 5.0]]);
+        let (u, values, v) = a.svd().unwrap();
+        assert!(close(
+            &values,
+            &matrix(&[&[45.0_f64.sqrt(), 5.0_f64.sqrt()]])
+        ));
+
+        // U S Vᵀ gives back A
+        let mut scaled = u.clone();
+        for (index, element) in scaled.elements.iter_mut().enumerate() {
+            *element = Number::Dec(element.to_dec() * values.elements[index % 2].to_dec());
+        }
+        assert!(close(&scaled.multiply(&v.transpose()).unwrap(), &a));
+
+        // A wide vector has as many singular values as rows
+        let (u, values, v) = matrix(&[&[1.0, 0.0, 0.0]]).svd().unwrap();
+        assert_eq!((u.rows, u.columns, values.columns, v.rows), (1, 1, 1, 3));
+
+        // The columns of U are orthonormal even when some singular values are zero
+        for a in [
+            matrix(&[&[0.0, 0.0], &[0.0, 0.0]]),
+            matrix(&[&[1.0, 1.0], &[1.0, 1.0], &[1.0, 1.0]]),
+        ] {
+            let (u, values, v) = a.svd().unwrap();
+            let columns = u.columns;
+            let identity = Vector::from_decs(
+                (0..columns)
+                    .map(|r| (0..columns).map(|c| f64::from(u8::from(r == c))).collect())
+                    .collect(),
+            )
+            .unwrap();
+            assert!(close(&u.transpose().multiply(&u).unwrap(), &identity));
+
+            let mut scaled = u.clone();
+            for (index, element) in scaled.elements.iter_mut().enumerate() {
+                *element =
+                    Number::Dec(element.to_dec() * values.elements[index % columns].to_dec());
+            }
+            assert!(close(&scaled.multiply(&v.transpose()).unwrap(), &a));
+        }
+    }
+
+    #[test]
+    fn least_squares_test() {
+        // The line through (1, 1), (2, 2) and (3, 2) closest to all three
+        let a = matrix(&[&[1.0, 1.0], &[1.0, 2.0], &[1.0, 3.0]]);
+        let x = a.least_squares(&matrix(&[&[1.0, 2.0, 2.0]])).unwrap();
+        assert!(close(&x, &matrix(&[&[2.0 / 3.0, 0.5]])));
+
+        // A square system that can be solved gives the same as `solve`
+        let b = matrix(&[&[2.0, 1.0], &[1.0, 3.0]]);
+        let c = matrix(&[&[3.0], &[5.0]]);
+        assert!(close(&b.least_squares(&c).unwrap(), &b.solve(&c).unwrap()));
+
+        assert!(a.least_squares(&matrix(&[&[1.0, 2.0]])).is_err());
+    }
+
+    #[test]
+    fn norm_test() {
+        assert_eq!(matrix(&[&[3.0, 4.0]]).norm(), 5.0);
+        assert_eq!(matrix(&[&[3.0, -4.0]]).p_norm(1.0), Ok(7.0));
+        assert!(matrix(&[&[3.0, 4.0]]).p_norm(0.5).is_err());
+
+        let a = matrix(&[&[1.0, -2.0], &[3.0, 4.0]]);
+        assert_eq!(a.norm(), 30.0_f64.sqrt());
+        assert_eq!(a.p_norm(1.0), Ok(6.0));
+        assert!((a.p_norm(2.0).unwrap() - 5.116672736016927).abs() < 1e-9);
+        assert!(a.p_norm(3.0).is_err());
+    }
+}
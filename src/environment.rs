@@ -0,0 +1,334 @@
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// The number of function calls that can be running at once unless given another limit
+pub const DEFAULT_DEPTH_LIMIT: usize = 1000;
+
+/// The largest recursion limit that can be asked for, which needs about 640 MiB of stack
+pub const MAX_DEPTH_LIMIT: usize = 10_000;
+
+// A generous amount of the Rust stack for each running call, so the limit is reached well
+// before the stack runs out. A call like `n 1 - nested 1 +` takes about 40 KiB in a debug
+// build and about 12 KiB in a release build.
+const STACK_PER_CALL: usize = 64 * 1024;
+const STACK_BASE: usize = 8 * 1024 * 1024;
+
+/// A `break` or `continue` that is waiting for the loop it is in to handle it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Jump {
+    Break,
+    Continue,
+}
+
+/// The variables of one running function call, as a chain of scopes from the parameters out to
+/// the innermost block, along with the types its generics are bound to
+#[derive(Debug, Clone)]
+struct Frame {
+    id: usize,
+    scopes: Vec<HashMap<String, Value>>,
+    generics: HashMap<String, String>,
+
+    // The number of loops running in this call, so `break` in a function never leaves a loop
+    // in its caller
+    loops: usize,
+}
+
+impl Frame {
+    fn new(id: usize, bindings: HashMap<String, Value>, generics: HashMap<String, String>) -> Self {
+        Frame {
+            id,
+            scopes: vec![bindings],
+            generics,
+            loops: 0,
+        }
+    }
+}
+
+/// Everything defined so far, like `a int 5 =` or the functions from `f (x int) =: x 1 +`.
+///
+/// The first frame holds the globals. Each function call starts a new frame whose scopes only
+/// see their own variables and the globals, so a function never sees the variables of its
+/// caller. A block like `{ a int 1 = } unwrap` adds a scope to the running frame, and its
+/// declarations are gone when the block is done.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    frames: Vec<Frame>,
+    next_id: usize,
+    depth_limit: usize,
+    jump: Option<Jump>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::with_depth_limit(DEFAULT_DEPTH_LIMIT)
+    }
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    /// An environment where recursion stops with an error once `limit` calls are running
+    pub fn with_depth_limit(limit: usize) -> Self {
+        Environment {
+            frames: vec![Frame::new(0, HashMap::new(), HashMap::new())],
+            next_id: 1,
+            depth_limit: limit,
+            jump: None,
+        }
+    }
+
+    pub fn depth_limit(&self) -> usize {
+        self.depth_limit
+    }
+
+    /// The size of the stack to evaluate on so the recursion limit is an error rather than a
+    /// stack overflow, or `None` if the limit is too large to have a stack for
+    pub fn stack_size(&self) -> Option<usize> {
+        STACK_PER_CALL
+            .checked_mul(self.depth_limit)?
+            .checked_add(STACK_BASE)
+    }
+
+    /// The number of calls that are running, including closures unwrapped after their call
+    pub fn depth(&self) -> usize {
+        self.frames.len() - 1
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames
+            .last()
+            .expect("The global frame is never popped")
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("The global frame is never popped")
+    }
+
+    /// Declare a variable in the innermost scope, shadowing any variable with the same name
+    /// further out
+    pub fn declare(&mut self, name: &str, value: Value) {
+        self.frame_mut()
+            .scopes
+            .last_mut()
+            .expect("A frame always has a scope")
+            .insert(name.to_string(), value);
+    }
+
+    /// Look up a name from the innermost scope of the running call out to the globals
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        self.frame()
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.frames[0].scopes[0].get(name))
+    }
+
+    /// Change the value of a variable that is already declared, in the nearest scope that has
+    /// it, like `sum x +=`. Gives back whether the variable was found.
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        let last = self.frames.len() - 1;
+        let position = self.frames[last]
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(name));
+
+        let scope = match position {
+            Some(index) => &mut self.frames[last].scopes[index],
+            None if self.frames[0].scopes[0].contains_key(name) => &mut self.frames[0].scopes[0],
+            None => return false,
+        };
+
+        scope.insert(name.to_string(), value);
+        true
+    }
+
+    /// The number of arguments a user function takes, or a closure with parameters like `(2 *)`
+    pub fn arity(&self, name: &str) -> Option<usize> {
+        match self.lookup(name) {
+            Some(Value::Function(function)) => Some(function.arity()),
+            Some(Value::Closure(closure)) if !closure.parameters.is_empty() => {
+                Some(closure.parameters.len())
+            }
+            _ => None,
+        }
+    }
+
+    /// The id of the running call, where the globals have the id `0`
+    pub fn frame_id(&self) -> usize {
+        self.frame().id
+    }
+
+    /// A copy of every variable the running call can see other than the globals, which a
+    /// closure keeps for when it is unwrapped after the call is done
+    pub fn captured(&self) -> HashMap<String, Value> {
+        let frame = self.frame();
+        let skip = usize::from(frame.id == 0);
+
+        let mut out = HashMap::<String, Value>::new();
+        for scope in frame.scopes.iter().skip(skip) {
+            out.extend(scope.clone());
+        }
+
+        out
+    }
+
+    /// The type a generic like `number` is bound to in the running call, like `int` for the
+    /// `x <number>` parameter in `2 f`
+    pub fn generic(&self, constraint: &str) -> Option<&String> {
+        self.frame().generics.get(constraint)
+    }
+
+    /// Start a function call with its arguments bound to the parameter names and its generics
+    /// bound to types
+    pub fn push_frame(&mut self, bindings: HashMap<String, Value>, types: HashMap<String, String>) {
+        self.frames.push(Frame::new(self.next_id, bindings, types));
+        self.next_id += 1;
+    }
+
+    pub fn pop_frame(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    /// Start a block in the running call
+    pub fn push_scope(&mut self) {
+        self.frame_mut().scopes.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        let frame = self.frame_mut();
+        if frame.scopes.len() > 1 {
+            frame.scopes.pop();
+        }
+    }
+
+    /// Start a loop in the running call
+    pub fn push_loop(&mut self) {
+        self.frame_mut().loops += 1;
+    }
+
+    pub fn pop_loop(&mut self) {
+        let frame = self.frame_mut();
+        frame.loops = frame.loops.saturating_sub(1);
+        self.jump = None;
+    }
+
+    /// Whether a loop is running in the running call, so `break` and `continue` have somewhere
+    /// to go
+    pub fn in_loop(&self) -> bool {
+        self.frame().loops > 0
+    }
+
+    /// Stop running statements until the loop handles the jump
+    pub fn jump(&mut self, jump: Jump) {
+        self.jump = Some(jump);
+    }
+
+    pub fn jumping(&self) -> bool {
+        self.jump.is_some()
+    }
+
+    /// The jump waiting to be handled by the running loop, which is handled once it is taken
+    pub fn take_jump(&mut self) -> Option<Jump> {
+        self.jump.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_test() {
+        let mut env = Environment::new();
+        env.declare("a", Value::int(1));
+        assert_eq!(env.lookup("a"), Some(&Value::int(1)));
+        assert_eq!(env.lookup("b"), None);
+
+        // Arguments shadow globals until the call is done
+        env.push_frame(
+            HashMap::from([("a".to_string(), Value::int(2))]),
+            HashMap::from([("number".to_string(), "int".to_string())]),
+        );
+        assert_eq!(env.lookup("a"), Some(&Value::int(2)));
+        assert_eq!(env.generic("number"), Some(&"int".to_string()));
+        env.pop_frame();
+        assert_eq!(env.lookup("a"), Some(&Value::int(1)));
+        assert_eq!(env.generic("number"), None);
+    }
+
+    #[test]
+    fn scope_test() {
+        let mut env = Environment::new();
+        env.declare("a", Value::int(1));
+
+        // Declarations in a block shadow the outer scope and are gone after the block
+        env.push_scope();
+        env.declare("a", Value::int(2));
+        env.declare("b", Value::int(3));
+        assert_eq!(env.lookup("a"), Some(&Value::int(2)));
+        env.pop_scope();
+        assert_eq!(env.lookup("a"), Some(&Value::int(1)));
+        assert_eq!(env.lookup("b"), None);
+
+        // A call only sees its own variables and the globals
+        env.push_scope();
+        env.declare("c", Value::int(4));
+        env.push_frame(HashMap::new(), HashMap::new());
+        assert_eq!(env.lookup("a"), Some(&Value::int(1)));
+        assert_eq!(env.lookup("c"), None);
+
+        env.declare("d", Value::int(5));
+        assert_eq!(
+            env.captured(),
+            HashMap::from([("d".to_string(), Value::int(5))])
+        );
+        env.pop_frame();
+        assert_eq!(env.lookup("c"), Some(&Value::int(4)));
+
+        // Assigning changes the nearest declaration rather than shadowing it
+        env.push_scope();
+        assert!(env.assign("a", Value::int(6)));
+        assert!(!env.assign("e", Value::int(7)));
+        env.pop_scope();
+        assert_eq!(env.lookup("a"), Some(&Value::int(6)));
+        assert_eq!(env.lookup("e"), None);
+    }
+
+    #[test]
+    fn loop_test() {
+        let mut env = Environment::new();
+        assert!(!env.in_loop());
+
+        env.push_loop();
+        assert!(env.in_loop());
+        env.jump(Jump::Break);
+        assert!(env.jumping());
+
+        // A call in the loop has no loop of its own
+        env.push_frame(HashMap::new(), HashMap::new());
+        assert!(!env.in_loop());
+        env.pop_frame();
+
+        assert_eq!(env.take_jump(), Some(Jump::Break));
+        assert!(!env.jumping());
+        env.pop_loop();
+        assert!(!env.in_loop());
+    }
+
+    #[test]
+    fn stack_size_test() {
+        assert!(Environment::with_depth_limit(MAX_DEPTH_LIMIT)
+            .stack_size()
+            .is_some());
+
+        // A limit too large to count the bytes of its stack has no stack size
+        assert_eq!(Environment::with_depth_limit(usize::MAX).stack_size(), None);
+    }
+}
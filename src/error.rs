@@ -0,0 +1,21 @@
+use std::fmt;
+
+/// An error raised while evaluating a basis expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasisError {
+    pub message: String,
+}
+
+impl BasisError {
+    pub fn new(message: &str) -> Self {
+        BasisError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for BasisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error: {}", self.message)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::lexer::Token;
+use crate::parser::Expression;
+use crate::simplify::math_notation;
+use std::fmt;
+
+/// A typed parameter like the `x int` in `f (x int) =: x 1 +`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub type_token: Token,
+}
+
+/// A user function, made by `f (x int) =: x 1 +` and called like `1 f`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub body: Expression,
+
+    // The type of the result, if it is known
+    pub return_type: Option<String>,
+
+    // Whether the body was worked out rather than written, like the derivative from `h x d`,
+    // so the console shows it
+    pub derived: bool,
+}
+
+impl Function {
+    pub fn arity(&self) -> usize {
+        self.parameters.len()
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let types: Vec<&str> = self
+            .parameters
+            .iter()
+            .map(|p| p.type_token.value.as_str())
+            .collect();
+
+        // A result that could be any type has the `type` type
+        write!(
+            f,
+            "{} ({}) -> {}",
+            self.name,
+            types.join(", "),
+            self.return_type.as_deref().unwrap_or("type")
+        )?;
+
+        match self.derived {
+            true => write!(f, " = {}", math_notation(&self.body)),
+            false => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,915 @@
+use crate::environment::Environment;
+use crate::error::BasisError;
+use crate::function::Function;
+use crate::lexer::{Token, TokenType};
+use crate::parser::{
+    compound_operation, container_inner, generic_constraint, named_operation, operation_token,
+    vector_inner, Expression, InnerCall, Operation,
+};
+use crate::pattern::Pattern;
+use crate::solve::free_variables;
+use crate::value::{constant, Value};
+use std::collections::HashMap;
+
+/// The type of a part of a function body. `None` means the type comes from a call to the
+/// function itself, so it is not known until another part of the body gives it.
+type Inferred = Option<String>;
+
+/// Any value, like the result of `value` on an option with no inner type
+const ANY: &str = "type";
+
+/// The position in the promotion order, the same as the ranks used for number operations.
+/// `number` could be any of them.
+fn rank(type_name: &str) -> Option<u8> {
+    match type_name {
+        "int" => Some(0),
+        "ratio" => Some(1),
+        "dec" => Some(2),
+        "real" => Some(3),
+        "imaginary" | "complex" => Some(4),
+        _ => None,
+    }
+}
+
+fn is_numeric(type_name: &str) -> bool {
+    rank(type_name).is_some() || matches!(type_name, "number" | "<number>")
+}
+
+fn type_error(action: &str, type_name: &str) -> BasisError {
+    BasisError::new(&format!("Cannot {} {}", action, type_name))
+}
+
+/// The type of the elements of a vector type, where a plain `vector` could hold any number
+fn elements_of(type_name: &str) -> Option<&str> {
+    match type_name {
+        "vector" => Some("number"),
+        t => vector_inner(t),
+    }
+}
+
+/// The type of `a b op`, following the promotion rules of `Number::operate`. When the result
+/// depends on the values, like `int int ^` which is a ratio for a negative power, the type is
+/// `number`.
+fn operation_type(a: &str, b: &str, operation: &Token) -> Result<String, BasisError> {
+    if a == ANY || b == ANY {
+        return Ok(ANY.to_string());
+    }
+
+    // A vector keeps its shape, and its elements take the type of the operation on them
+    let (x, y) = (elements_of(a), elements_of(b));
+    if x.is_some() || y.is_some() {
+        let supported = match (x, y) {
+            (Some(_), Some(_)) => matches!(
+                operation.token_type,
+                TokenType::Addition | TokenType::Subtraction | TokenType::Multiplication
+            ),
+            _ => operation.token_type != TokenType::Power,
+        };
+
+        if !supported {
+            return Err(BasisError::new(&format!(
+                "Cannot apply `{}` to {} and {}",
+                operation.value, a, b
+            )));
+        }
+
+        let inner = operation_type(x.unwrap_or(a), y.unwrap_or(b), operation)?;
+        return Ok(format!("vector[{}]", inner));
+    }
+
+    // Literals stay symbolic, though they can simplify down to a number
+    if (a == "literal" && (is_numeric(b) || b == "literal")) || (is_numeric(a) && b == "literal") {
+        return Ok("literal".to_string());
+    }
+
+    if !is_numeric(a) || !is_numeric(b) {
+        return Err(BasisError::new(&format!(
+            "Cannot apply `{}` to {} and {}",
+            operation.value, a, b
+        )));
+    }
+
+    // A generic keeps its bound type under `+` and `-`, like `x <1> +`
+    if a == b
+        && generic_constraint(a).is_some()
+        && matches!(
+            operation.token_type,
+            TokenType::Addition | TokenType::Subtraction
+        )
+    {
+        return Ok(a.to_string());
+    }
+
+    if a == "imaginary" && b == "imaginary" {
+        let out = match operation.token_type {
+            TokenType::Addition | TokenType::Subtraction => "imaginary",
+            TokenType::Multiplication | TokenType::Division => "dec",
+            _ => "complex",
+        };
+
+        return Ok(out.to_string());
+    }
+
+    let (Some(x), Some(y)) = (rank(a), rank(b)) else {
+        return Ok("number".to_string());
+    };
+
+    let out = match (x.max(y), &operation.token_type) {
+        (4, _) => "complex",
+        (0, TokenType::Division) => "ratio",
+        (0, TokenType::Power) => "number",
+        (1, TokenType::Power) if b != "int" => "number",
+        (3, TokenType::Power) => "number",
+        (0, _) => "int",
+        (1, _) => "ratio",
+        (2, _) => "dec",
+        _ => "real",
+    };
+
+    Ok(out.to_string())
+}
+
+/// The type given by a linear algebra function like `det`, where ints become ratios for the
+/// functions that divide
+fn matrix_function_type(name: &str, arguments: &[String]) -> Result<String, BasisError> {
+    let vectors = match name {
+        "pnorm" => &arguments[..1],
+        _ => arguments,
+    };
+
+    if name == "pnorm" && !is_numeric(&arguments[1]) && arguments[1] != ANY {
+        return Err(type_error("use as the p of `pnorm`", &arguments[1]));
+    }
+
+    let mut elements = Vec::<&str>::new();
+    for argument in vectors {
+        match elements_of(argument) {
+            Some(inner) => elements.push(inner),
+            None if argument == ANY => elements.push("number"),
+            None => return Err(type_error(&format!("apply `{}` to", name), argument)),
+        }
+    }
+
+    let divided = match elements[0] {
+        "int" => "ratio",
+        other => other,
+    };
+
+    let out = match name {
+        "transpose" => format!("vector[{}]", elements[0]),
+        "det" | "trace" => elements[0].to_string(),
+        "rank" => "int".to_string(),
+        "inverse" | "solve" => format!("vector[{}]", divided),
+        "lstsq" => "vector[dec]".to_string(),
+        "norm" | "pnorm" => "dec".to_string(),
+
+        // The eigenvalues of a vector that is not symmetric can be complex
+        "eigvals" => "vector[number]".to_string(),
+        _ => "tuple[vector]".to_string(),
+    };
+
+    Ok(out)
+}
+
+/// The type given by a function on any container, like `int` for `len`
+fn container_function_type(name: &str, container: &str) -> Result<String, BasisError> {
+    let element = match container {
+        ANY => ANY,
+        "tuple" | "set" | "container" => ANY,
+        "sample" | "population" => "number",
+        t => match elements_of(t).or(container_inner(t)) {
+            Some(element) => element,
+            None => return Err(type_error(&format!("apply `{}` to", name), container)),
+        },
+    };
+
+    let out = match name {
+        "len" => "int",
+        "sort" | "filter" => container,
+        "map" => container.split('[').next().unwrap_or(container),
+        "fold" => ANY,
+        _ => element,
+    };
+
+    Ok(out.to_string())
+}
+
+/// The type of a statistic like `mean`, where ints give exact ratios and a square root like in
+/// `stdev` can give any number type
+fn statistic_type(name: &str, container: &str) -> Result<String, BasisError> {
+    let element = container_function_type(name, container)?;
+    if element == ANY {
+        return Ok(element);
+    }
+
+    if !is_numeric(&element) {
+        return Err(type_error(&format!("take the {} of", name), container));
+    }
+
+    match name {
+        "mode" | "min" | "max" | "range" => Ok(element),
+        "mean" | "variance" | "covariance" | "kurtosis" => {
+            operation_type(&element, "int", &operation_token(TokenType::Division))
+        }
+        _ => Ok("number".to_string()),
+    }
+}
+
+/// The type given by a math function like `sin`, where functions like `log` and `sqrt` that
+/// give a complex number for some values can give any number type
+fn math_function_type(name: &str, argument: &str) -> Result<String, BasisError> {
+    if matches!(argument, "literal" | "number") || argument == ANY {
+        return Ok(argument.to_string());
+    }
+
+    if argument == "<number>" {
+        return Ok("number".to_string());
+    }
+
+    if !is_numeric(argument) {
+        return Err(type_error(&format!("apply `{}` to", name), argument));
+    }
+
+    let complex = rank(argument) == Some(4);
+    let out = match name {
+        "abs" if matches!(argument, "int" | "ratio" | "real") => argument,
+        "abs" | "arg" => "dec",
+        "conj" => argument,
+        "re" | "im" if complex => "dec",
+        "re" => argument,
+        "im" => "int",
+        _ if complex => "complex",
+        "sin" | "cos" | "tan" | "exp" => "dec",
+        _ => "number",
+    };
+
+    Ok(out.to_string())
+}
+
+/// The type of a value that comes from one of several branches. A branch that calls the
+/// function itself takes the type of the others, and number types that differ give `number`.
+/// Branches of types that have nothing in common, like `int` and `string`, are an error.
+fn unify(a: Inferred, b: Inferred) -> Result<Inferred, BasisError> {
+    let out = match (a, b) {
+        (None, other) | (other, None) => other,
+        (Some(a), Some(b)) if a == b => Some(a),
+        (Some(a), Some(b)) if a == ANY || b == ANY => Some(ANY.to_string()),
+        (Some(a), Some(b)) if is_numeric(&a) && is_numeric(&b) => Some("number".to_string()),
+        (Some(a), Some(b)) if elements_of(&a).is_some() && elements_of(&b).is_some() => {
+            Some("vector".to_string())
+        }
+        (Some(a), Some(b)) => {
+            return Err(BasisError::new(&format!(
+                "The branches give both {} and {}",
+                a, b
+            )))
+        }
+    };
+
+    Ok(out)
+}
+
+/// The inner type of a generic type, like `int` for `option<int>`
+fn inner_type(type_name: &str) -> Option<&str> {
+    type_name
+        .strip_prefix("option<")
+        .and_then(|t| t.strip_suffix('>'))
+}
+
+/// The type after a cast with a type keyword, like `b ratio 3 =` or `5 option<int>`
+fn cast_type(value: &str, type_token: &Token) -> String {
+    match type_token.token_type {
+        TokenType::TypeNumberKeyword => value.to_string(),
+        TokenType::TypeOptionKeyword if inner_type(&type_token.value).is_none() => match value {
+            "nil" => "option".to_string(),
+            v if v.starts_with("option") || v == ANY => v.to_string(),
+            v => format!("option<{}>", v),
+        },
+        TokenType::TypeTupleKeyword | TokenType::TypeSetKeyword
+            if container_inner(&type_token.value).is_none() =>
+        {
+            match elements_of(value).or(container_inner(value)) {
+                Some(inner) => format!("{}[{}]", type_token.value, inner),
+                None => type_token.value.clone(),
+            }
+        }
+        _ => type_token.value.clone(),
+    }
+}
+
+struct Inference<'a> {
+    function: &'a Function,
+    env: &'a Environment,
+
+    // The types of the variables declared in the body so far
+    locals: HashMap<String, String>,
+}
+
+impl Inference<'_> {
+    fn token_type(&mut self, token: &Token) -> Result<String, BasisError> {
+        let out = match token.token_type {
+            TokenType::NumericIntLiteral => "int",
+            TokenType::NumericDecLiteral => "dec",
+            TokenType::NumericImaginaryLiteral => "imaginary",
+            TokenType::StringLiteral => "string",
+            TokenType::BoolLiteral => "bool",
+            TokenType::NilLiteral => "nil",
+
+            // A generic literal has the type of the generic parameters
+            TokenType::GenericLiteral => {
+                return self
+                    .function
+                    .parameters
+                    .iter()
+                    .map(|p| &p.type_token.value)
+                    .find(|t| generic_constraint(t) == Some("number"))
+                    .cloned()
+                    .ok_or_else(|| {
+                        BasisError::new(&format!(
+                            "`{}` needs a generic `<number>` parameter",
+                            token.value
+                        ))
+                    });
+            }
+            TokenType::Identifier => {
+                let parameter = self
+                    .function
+                    .parameters
+                    .iter()
+                    .find(|p| p.name == token.value);
+
+                if let Some(local) = self.locals.get(&token.value) {
+                    return Ok(local.clone());
+                }
+
+                return Ok(match (parameter, self.env.lookup(&token.value)) {
+                    (Some(parameter), _) => parameter.type_token.value.clone(),
+
+                    // A global can be declared again with another number type before the call,
+                    // so only its family is known
+                    (None, Some(Value::Number(_) | Value::Estimate { .. })) => "number".to_string(),
+                    (None, Some(Value::Vector(_))) => "vector".to_string(),
+                    (None, Some(value)) => value.type_name().to_string(),
+                    (None, None) if token.value == self.function.name => "function".to_string(),
+                    (None, None) if constant(&token.value).is_some() => "literal".to_string(),
+                    (None, None) => {
+                        return Err(BasisError::new(&format!(
+                            "Unknown identifier `{}`",
+                            token.value
+                        )))
+                    }
+                });
+            }
+            _ => {
+                return Err(BasisError::new(&format!(
+                    "Unexpected token `{}`",
+                    token.value
+                )))
+            }
+        };
+
+        Ok(out.to_string())
+    }
+
+    fn call_type(&mut self, call: &InnerCall) -> Result<Inferred, BasisError> {
+        let name = call.function.value.as_str();
+
+        // The variable of a derivative is a name rather than a value, and is a symbol in the
+        // expression being differentiated
+        if call.function.token_type == TokenType::Identifier && matches!(name, "d" | "deriv") {
+            let outer = self.locals.clone();
+            if let Expression::TokenExpression(variable) = &call.arguments[1] {
+                self.locals
+                    .insert(variable.token.value.clone(), "literal".to_string());
+            }
+
+            let out = self.infer(&call.arguments[0]);
+            self.locals = outer;
+
+            return Ok(match out? {
+                Some(t) if t == "function" => Some(t),
+                _ => Some("literal".to_string()),
+            });
+        }
+
+        // The free variable of a literal to solve, like `x` in `x 2 ^ 0 1 integrate`, is a symbol
+        if call.function.token_type == TokenType::Identifier
+            && matches!(name, "integrate" | "root" | "newton")
+        {
+            let mut variables = Vec::<String>::new();
+            free_variables(&call.arguments[0], &mut variables);
+
+            let outer = self.locals.clone();
+            for variable in variables {
+                let known = self.locals.contains_key(&variable)
+                    || self.env.lookup(&variable).is_some()
+                    || self.function.parameters.iter().any(|p| p.name == variable);
+                if !known {
+                    self.locals.insert(variable, "literal".to_string());
+                }
+            }
+
+            let out = self.infer(&call.arguments[0]);
+            self.locals = outer;
+            out?;
+
+            for bound in &call.arguments[1..] {
+                self.infer(bound)?;
+            }
+
+            return Ok(Some("dec".to_string()));
+        }
+
+        // Unwrapping a closure gives the type of its body
+        if let (TokenType::Identifier, "unwrap", [Expression::Closure(closure)]) =
+            (&call.function.token_type, name, call.arguments.as_slice())
+        {
+            return self.infer(&closure.body);
+        }
+
+        let mut arguments = Vec::<String>::new();
+        for argument in &call.arguments {
+            match self.infer(argument)? {
+                Some(t) => arguments.push(t),
+                None => return Ok(None),
+            }
+        }
+
+        let out = match (&call.function.token_type, name) {
+            (TokenType::Dot, _) => "dec".to_string(),
+            (TokenType::Identifier, "unwrap") => match arguments[0].as_str() {
+                "literal" => "dec".to_string(),
+                "nil" => return Err(type_error("unwrap", "nil")),
+                "closure" => ANY.to_string(),
+                t => inner_type(t).unwrap_or(t).to_string(),
+            },
+            (TokenType::Identifier, "not" | "and" | "or" | "exists" | "some" | "none") => {
+                "bool".to_string()
+            }
+            (TokenType::Identifier, "value") => ANY.to_string(),
+            (TokenType::TypeBoolKeyword, _) => "bool".to_string(),
+            (TokenType::TypeSizeKeyword, _) => "size".to_string(),
+            (
+                TokenType::TypeOptionKeyword
+                | TokenType::TypeTupleKeyword
+                | TokenType::TypeSetKeyword
+                | TokenType::TypeSampleKeyword
+                | TokenType::TypePopulationKeyword,
+                _,
+            ) => cast_type(&arguments[0], &call.function),
+            (TokenType::Identifier, "in") => "bool".to_string(),
+            (TokenType::Identifier, "compose") => "closure".to_string(),
+            (
+                TokenType::Identifier,
+                "mean" | "median" | "mode" | "variance" | "stdev" | "min" | "max" | "range"
+                | "skewness" | "kurtosis" | "quantile" | "covariance" | "correlation",
+            ) => statistic_type(name, &arguments[0])?,
+            (TokenType::Identifier, "add" | "sub" | "mul" | "div") => operation_type(
+                &arguments[0],
+                &arguments[1],
+                &operation_token(named_operation(name).unwrap()),
+            )?,
+            (TokenType::Identifier, "len" | "sort" | "at" | "map" | "filter" | "fold") => {
+                container_function_type(name, &arguments[0])?
+            }
+            (TokenType::Identifier, "union" | "intersection" | "difference") => {
+                match arguments[0] == arguments[1] {
+                    true => arguments[0].clone(),
+                    false => "set".to_string(),
+                }
+            }
+            (
+                TokenType::Identifier,
+                "sin" | "cos" | "tan" | "exp" | "log" | "sqrt" | "abs" | "arg" | "conj" | "re"
+                | "im",
+            ) => math_function_type(name, &arguments[0])?,
+            (TokenType::Identifier, "prec") => "real".to_string(),
+            (TokenType::Identifier, "integrate" | "root" | "newton" | "error") => "dec".to_string(),
+            (
+                TokenType::Identifier,
+                "transpose" | "det" | "inverse" | "rank" | "trace" | "solve" | "lu" | "qr" | "eig"
+                | "eigvals" | "svd" | "lstsq" | "norm" | "pnorm",
+            ) => matrix_function_type(name, &arguments)?,
+
+            // A call to the function being defined has the type of the rest of the body
+            (TokenType::Identifier, _) if name == self.function.name => return Ok(None),
+            (TokenType::Identifier, _) => match self.env.lookup(name) {
+                Some(Value::Function(function)) => function
+                    .return_type
+                    .clone()
+                    .unwrap_or_else(|| ANY.to_string()),
+                Some(Value::Closure(_)) => ANY.to_string(),
+                _ => return Err(BasisError::new(&format!("Unknown function `{}`", name))),
+            },
+            _ => {
+                return Err(BasisError::new(&format!(
+                    "Unknown function `{}`",
+                    call.function.value
+                )))
+            }
+        };
+
+        Ok(Some(out))
+    }
+
+    /// The type of the chosen branch of an `if` or `match`, where a closure is unwrapped
+    fn branch_type(
+        &mut self,
+        branch: &Expression,
+        binding: Option<(String, String)>,
+    ) -> Result<Inferred, BasisError> {
+        let body = match branch {
+            Expression::Closure(c) => c.body.as_ref(),
+            other => other,
+        };
+
+        let outer = self.locals.clone();
+        if let Some((name, type_name)) = binding {
+            self.locals.insert(name, type_name);
+        }
+
+        let out = self.infer(body);
+        self.locals = outer;
+
+        out
+    }
+
+    /// Check the body of a loop, where the loop variable and declarations in the body are gone
+    /// after the loop but compound assignments to variables outside it are kept
+    fn loop_type(
+        &mut self,
+        body: &Expression,
+        binding: Option<(String, String)>,
+    ) -> Result<Inferred, BasisError> {
+        let outer = self.locals.clone();
+        if let Some((name, type_name)) = binding {
+            self.locals.insert(name, type_name);
+        }
+
+        self.infer(body)?;
+
+        let inner = std::mem::replace(&mut self.locals, outer);
+        for (name, type_name) in self.locals.iter_mut() {
+            if let Some(changed) = inner.get(name) {
+                *type_name = changed.clone();
+            }
+        }
+
+        Ok(Some("nil".to_string()))
+    }
+
+    fn infer(&mut self, exp: &Expression) -> Result<Inferred, BasisError> {
+        match exp {
+            Expression::TokenExpression(t) => Ok(Some(self.token_type(&t.token)?)),
+            Expression::ExpExpOp(i) => {
+                let a = self.infer(&i.expression_1)?;
+                let b = self.infer(&i.expression_2)?;
+                let Operation::TokenOperation(op) = i.operation.as_ref();
+
+                if matches!(
+                    op.token.token_type,
+                    TokenType::Greater
+                        | TokenType::Less
+                        | TokenType::GreaterEqual
+                        | TokenType::LessEqual
+                        | TokenType::Equal
+                        | TokenType::NotEqual
+                ) {
+                    return Ok(Some("bool".to_string()));
+                }
+
+                // An int to a power that is a literal like `x 2 ^` stays an int unless the power is
+                // negative
+                if let (Some("int"), Expression::TokenExpression(power)) =
+                    (a.as_deref(), i.expression_2.as_ref())
+                {
+                    if op.token.token_type == TokenType::Power
+                        && power.token.token_type == TokenType::NumericIntLiteral
+                        && !power.token.value.starts_with('-')
+                    {
+                        return Ok(Some("int".to_string()));
+                    }
+                }
+
+                match (a, b) {
+                    (Some(a), Some(b)) => Ok(Some(operation_type(&a, &b, &op.token)?)),
+                    _ => Ok(None),
+                }
+            }
+            Expression::Assignment(a) => {
+                let Expression::TokenExpression(type_expression) = a.expression_2.as_ref() else {
+                    return Err(BasisError::new(
+                        "Assignment expects a name and a type before the value",
+                    ));
+                };
+
+                let out = self
+                    .infer(&a.expression_3)?
+                    .map(|value| cast_type(&value, &type_expression.token));
+
+                if let (Expression::TokenExpression(name), Some(type_name)) =
+                    (a.expression_1.as_ref(), &out)
+                {
+                    self.locals
+                        .insert(name.token.value.clone(), type_name.clone());
+                }
+
+                Ok(out)
+            }
+            Expression::Call(c) => self.call_type(c),
+            Expression::Function(_) => Ok(Some("function".to_string())),
+            Expression::Closure(_) => Ok(Some("closure".to_string())),
+            Expression::If(i) => {
+                self.infer(&i.condition)?;
+                let then = self.branch_type(&i.then, None)?;
+                let otherwise = self.branch_type(&i.otherwise, None)?;
+
+                unify(then, otherwise)
+            }
+            Expression::Match(m) => {
+                let subject = self.infer(&m.subject)?;
+
+                let mut out = None;
+                for arm in &m.arms {
+                    // The value named by `some v` has the inner type of the option
+                    let binding = match &arm.pattern {
+                        Pattern::Some(Some(name)) => {
+                            let inner = subject.as_deref().and_then(inner_type).unwrap_or(ANY);
+                            Some((name.clone(), inner.to_string()))
+                        }
+                        _ => None,
+                    };
+
+                    out = unify(out, self.branch_type(&arm.result, binding)?)?;
+                }
+
+                Ok(out)
+            }
+            Expression::Sequence(s) => {
+                let mut out = None;
+                for expression in &s.expressions {
+                    out = self.infer(expression)?;
+                }
+
+                Ok(out)
+            }
+            Expression::For(f) => {
+                let iterable = self.infer(&f.iterable)?;
+                let element = match (&f.end, iterable.as_deref()) {
+                    (Some(end), _) => {
+                        self.infer(end)?;
+                        "int"
+                    }
+                    (None, Some("string")) => "string",
+                    (None, Some(t)) => elements_of(t)
+                        .or(container_inner(t))
+                        .or(inner_type(t))
+                        .unwrap_or(ANY),
+                    (None, None) => ANY,
+                };
+
+                let element = match &f.type_token {
+                    Some(type_token) => cast_type(element, type_token),
+                    None => element.to_string(),
+                };
+
+                self.loop_type(&f.body, Some((f.variable.value.clone(), element)))
+            }
+            Expression::While(w) => {
+                self.infer(&w.condition)?;
+                self.loop_type(&w.body, None)
+            }
+            Expression::CompoundAssignment(c) => {
+                let Operation::TokenOperation(op) = c.operation.as_ref();
+                let current = self.token_type(&c.name)?;
+                let Some(value) = self.infer(&c.value)? else {
+                    return Ok(None);
+                };
+
+                let operation = compound_operation(&op.token.token_type)
+                    .map(operation_token)
+                    .unwrap_or_else(|| op.token.clone());
+                let out = operation_type(&current, &value, &operation)?;
+
+                self.locals.insert(c.name.value.clone(), out.clone());
+
+                Ok(Some(out))
+            }
+            Expression::Vector(v) => {
+                // The elements promote to the widest of their types
+                let mut out = "int".to_string();
+                for (index, element) in v.rows.iter().flatten().enumerate() {
+                    let Some(element) = self.infer(element)? else {
+                        return Ok(None);
+                    };
+
+                    if !is_numeric(&element) {
+                        return Err(BasisError::new(&format!(
+                            "A vector can only hold numbers, but got {}",
+                            element
+                        )));
+                    }
+
+                    out = match index {
+                        0 => element,
+                        _ => operation_type(&out, &element, &operation_token(TokenType::Addition))?,
+                    };
+                }
+
+                Ok(Some(format!("vector[{}]", out)))
+            }
+            Expression::Tuple(t) => self.container_type("tuple", &t.elements),
+            Expression::Set(s) => self.container_type("set", &s.elements),
+            Expression::Break | Expression::Continue => Ok(Some("nil".to_string())),
+            Expression::None => Err(BasisError::new("Empty expression")),
+        }
+    }
+
+    /// The type of a tuple or set like `tuple[int]`, following `element_type` for the values
+    fn container_type(
+        &mut self,
+        outer: &str,
+        elements: &[Expression],
+    ) -> Result<Option<String>, BasisError> {
+        let mut types = Vec::<String>::new();
+        for element in elements {
+            let Some(element) = self.infer(element)? else {
+                return Ok(None);
+            };
+
+            types.push(element);
+        }
+
+        let inner = match types.first() {
+            Some(first) if types.iter().all(|t| t == first) => first.as_str(),
+            Some(_) if types.iter().all(|t| is_numeric(t)) => "number",
+            Some(_) => ANY,
+            None => "nil",
+        };
+
+        Ok(Some(format!("{}[{}]", outer, inner)))
+    }
+}
+
+/// Work out the type a function gives back from the types of its parameters, like `int` for
+/// `f (x int) =: x 1 +`. Bodies that would always fail, like `x "a" +`, or that only ever call
+/// the function itself are errors.
+pub fn return_type(function: &Function, env: &Environment) -> Result<String, BasisError> {
+    let mut inference = Inference {
+        function,
+        env,
+        locals: HashMap::new(),
+    };
+
+    match inference.infer(&function.body) {
+        Ok(Some(out)) => Ok(out),
+        Ok(None) => Err(BasisError::new(&format!(
+            "Cannot determine the return type of `{}`, since it only ever calls itself",
+            function.name
+        ))),
+        Err(error) => Err(BasisError::new(&format!(
+            "Cannot determine the return type of `{}`: {}",
+            function.name, error.message
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{Lex, Lexer};
+    use crate::parser::tokens_to_expr_in;
+
+    fn infer_line(line: &str, env: &Environment) -> Result<String, BasisError> {
+        let mut lex = Lexer::new(vec![line.to_string()]);
+        let mut tokens = Vec::<Token>::new();
+        loop {
+            let token = lex.next();
+            if token.token_type == TokenType::EndToken {
+                break;
+            }
+            tokens.push(token);
+        }
+
+        let Expression::Function(definition) = tokens_to_expr_in(tokens, env) else {
+            panic!("Expected a function definition");
+        };
+
+        return_type(
+            &Function {
+                name: definition.name.value,
+                parameters: definition.parameters,
+                body: *definition.body,
+                return_type: None,
+                derived: false,
+            },
+            env,
+        )
+    }
+
+    #[test]
+    fn return_type_test() {
+        let env = Environment::new();
+        let infer = |line: &str| infer_line(line, &env);
+
+        assert_eq!(infer("f (x int) =: x 1 +"), Ok("int".to_string()));
+        assert_eq!(infer("f (x int) =: x 2 /"), Ok("ratio".to_string()));
+        assert_eq!(infer("f (x int, y dec) =: x y *"), Ok("dec".to_string()));
+        assert_eq!(infer("f (x ratio) =: x 2 ^"), Ok("ratio".to_string()));
+        assert_eq!(infer("f (x int) =: x 2 ^"), Ok("int".to_string()));
+        assert_eq!(infer("f (x int, n int) =: x n ^"), Ok("number".to_string()));
+        assert_eq!(infer("f (x real) =: x 1 +"), Ok("real".to_string()));
+        assert_eq!(infer("f (x dec) =: x 2i +"), Ok("complex".to_string()));
+        assert_eq!(infer("f (x dec) =: x sin"), Ok("dec".to_string()));
+        assert_eq!(infer("f (x int) =: x cos"), Ok("dec".to_string()));
+        assert_eq!(infer("f (x int) =: x sqrt"), Ok("number".to_string()));
+        assert_eq!(infer("f (x int) =: x 2 ^ x d"), Ok("literal".to_string()));
+        assert_eq!(
+            infer("f (a dec) =: t 2 ^ 0 a integrate"),
+            Ok("dec".to_string())
+        );
+        assert_eq!(infer("f (x int) =: x 1 >"), Ok("bool".to_string()));
+        assert_eq!(infer("f (x int) =: x pi *"), Ok("literal".to_string()));
+        assert_eq!(
+            infer("f (x int) =: x option"),
+            Ok("option<int>".to_string())
+        );
+        assert_eq!(
+            infer("f (x option<int>) =: x unwrap"),
+            Ok("int".to_string())
+        );
+        assert_eq!(infer("f (x size) =: x size"), Ok("size".to_string()));
+        assert_eq!(infer("f (x int) =: : (x 1 +)"), Ok("closure".to_string()));
+        assert_eq!(
+            infer("f (x int) =: : (x 1 +) unwrap"),
+            Ok("int".to_string())
+        );
+        assert_eq!(infer("f (x int) =: y dec 2 = x y *"), Ok("dec".to_string()));
+        assert_eq!(
+            infer("f (n int, a int) =: n 0 == : (a) : (n 1 - n a * f) if"),
+            Ok("int".to_string())
+        );
+        assert_eq!(
+            infer("f (x int) =: x 0 < 0.5 x if"),
+            Ok("number".to_string())
+        );
+        assert_eq!(
+            infer("f (x option<int>) =: x match (some v: v 1 +, none: 0)"),
+            Ok("int".to_string())
+        );
+        assert_eq!(infer("f (x <number>) x <1> +"), Ok("<number>".to_string()));
+        assert_eq!(infer("f (x <number>) =: x 2 /"), Ok("number".to_string()));
+        assert_eq!(infer("f (x int) =: (x 2 3)"), Ok("tuple[int]".to_string()));
+        assert_eq!(
+            infer("f (x dec) =: {x 2} set"),
+            Ok("set[number]".to_string())
+        );
+        assert_eq!(infer("f (s set[int]) =: 2 s in"), Ok("bool".to_string()));
+        assert_eq!(
+            infer("f (c container[ratio]) =: c 0 at"),
+            Ok("ratio".to_string())
+        );
+        assert_eq!(infer("f (c container) =: c len"), Ok("int".to_string()));
+        assert!(infer("f (x int) =: x sort").is_err());
+        assert_eq!(
+            infer("f (v vector[int]) =: v (2 *) map"),
+            Ok("vector".to_string())
+        );
+        assert_eq!(
+            infer("f (s set[int]) =: s (2 >) filter"),
+            Ok("set[int]".to_string())
+        );
+        assert_eq!(infer("f (x int) =: x 2 add"), Ok("int".to_string()));
+        assert_eq!(
+            infer("f (c container[int]) =: c mean"),
+            Ok("ratio".to_string())
+        );
+        assert_eq!(infer("f (v vector[dec]) =: v max"), Ok("dec".to_string()));
+        assert!(infer("f (x int) =: x mean").is_err());
+        assert_eq!(
+            infer("f (c container[int]) =: c sample"),
+            Ok("sample".to_string())
+        );
+        assert_eq!(infer("f (s sample) =: s max"), Ok("number".to_string()));
+
+        // Bodies with no type are flagged
+        assert!(infer("f (x size) =: x 1 +").is_err());
+        assert!(infer("f (x int) =: x f").is_err());
+        assert!(infer("f (x bool) =: x sin").is_err());
+        assert!(infer("f (x int) =: x <1> +").is_err());
+        assert!(infer("f (x int) =: x y +").is_err());
+        assert!(infer("f (x int) =: x 0 > : (x) : (\"a\") if").is_err());
+    }
+
+    #[test]
+    fn global_test() {
+        let mut env = Environment::new();
+        env.declare("a", Value::int(2));
+        env.declare("s", Value::String("b".to_string()));
+        let infer = |line: &str| infer_line(line, &env);
+
+        // A global number can be declared again as another number type before the call
+        assert_eq!(infer("f (x int) =: x a +"), Ok("number".to_string()));
+        assert_eq!(infer("f (x int) =: s"), Ok("string".to_string()));
+    }
+}
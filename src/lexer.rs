@@ -1,18 +1,34 @@
 use itertools::Itertools;
 
 fn is_char_symbol(ch: char) -> bool {
-    match ch {
-        '[' | ']' | '{' | '}' | '(' | ')' | '.' | ',' | ':' | ';' | '=' | '\'' | '\"' | '\\'
-        | '+' | '-' | '*' | '/' | '^' | '>' | '<' | '~' => true,
-        _ => false,
-    }
+    matches!(
+        ch,
+        '[' | ']'
+            | '{'
+            | '}'
+            | '('
+            | ')'
+            | '.'
+            | ','
+            | ':'
+            | ';'
+            | '='
+            | '\''
+            | '\"'
+            | '\\'
+            | '+'
+            | '-'
+            | '*'
+            | '/'
+            | '^'
+            | '>'
+            | '<'
+            | '~'
+    )
 }
 
 fn is_non_zero_number(ch: char) -> bool {
-    match ch {
-        '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => true,
-        _ => false,
-    }
+    matches!(ch, '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9')
 }
 
 fn is_number(ch: char) -> bool {
@@ -20,10 +36,7 @@ fn is_number(ch: char) -> bool {
 }
 
 fn is_char_whitespace(ch: char) -> bool {
-    match ch {
-        '\t' | ' ' | '\n' => true,
-        _ => false,
-    }
+    matches!(ch, '\t' | ' ' | '\n')
 }
 
 fn is_part_whitespace(string: &str) -> bool {
@@ -45,8 +58,7 @@ fn ends_token(cur: char, next: char) -> bool {
         return true;
     }
 
-    if is_char_whitespace(cur) {}
-    return false;
+    false
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -60,6 +72,22 @@ pub enum TokenType {
     // NOT: .3, 54
     NumericDecLiteral = 2,
 
+    // 3i, 2.5i
+    // NOT: i, i3
+    NumericImaginaryLiteral = 3,
+
+    // "hello", "two words"
+    StringLiteral,
+
+    // true, false
+    BoolLiteral,
+
+    // nil
+    NilLiteral,
+
+    // <1>, <2.5> in the body of a function with generic parameters
+    GenericLiteral,
+
     LeftBrace,
     RightBrace,
     LeftBracket,
@@ -74,11 +102,23 @@ pub enum TokenType {
     Subtraction,
     Multiplication,
     Division,
+    Power,
 
     Greater,
     Less,
+    GreaterEqual,
+    LessEqual,
+    Equal,
+    NotEqual,
 
     Assignment,
+    // =:
+    FunctionAssignment,
+    // +=, -=, *=, /=
+    AddAssignment,
+    SubtractAssignment,
+    MultiplyAssignment,
+    DivideAssignment,
     Semicolon,
     Colon,
     Tag,
@@ -105,6 +145,14 @@ pub enum TokenType {
     TypeComplexKeyword,
     TypeImaginaryKeyword,
     TypeSizeKeyword,
+    TypeBoolKeyword,
+    TypeOptionKeyword,
+    TypeVectorKeyword,
+    TypeTupleKeyword,
+    TypeSetKeyword,
+    TypeContainerKeyword,
+    TypeSampleKeyword,
+    TypePopulationKeyword,
 
     // Variable name like "a"
     Identifier,
@@ -125,6 +173,14 @@ fn is_type(maybe_type: &str) -> TokenType {
         "size" => TokenType::TypeSizeKeyword,
 
         // Other types
+        "bool" => TokenType::TypeBoolKeyword,
+        "option" => TokenType::TypeOptionKeyword,
+        "vector" => TokenType::TypeVectorKeyword,
+        "tuple" => TokenType::TypeTupleKeyword,
+        "set" => TokenType::TypeSetKeyword,
+        "container" => TokenType::TypeContainerKeyword,
+        "sample" => TokenType::TypeSampleKeyword,
+        "population" => TokenType::TypePopulationKeyword,
         // "literal" | "type" | "option" | "string" => true,
         _ => TokenType::NoType,
     }
@@ -145,10 +201,14 @@ pub struct Token {
 }
 
 fn is_part_int_numeric(part: &str) -> bool {
+    if part == "0" {
+        return true;
+    }
+
     let mut chars = part.chars();
 
     let first_char = chars
-        .nth(0)
+        .next()
         .expect("Part should have more than zero characters");
     if !(is_non_zero_number(first_char) || first_char == '-') {
         return false;
@@ -160,7 +220,51 @@ fn is_part_int_numeric(part: &str) -> bool {
         }
     }
 
-    return true;
+    true
+}
+
+// 100.10, 0.0124, 45.453
+fn is_part_dec_numeric(part: &str) -> bool {
+    match part.split_once('.') {
+        Some((whole, fraction)) => {
+            is_part_int_numeric(whole) && !fraction.is_empty() && fraction.chars().all(is_number)
+        }
+        None => false,
+    }
+}
+
+/// Whether the next character keeps a number going as a decimal like `2.5`. The `.` is only
+/// part of the number when there are digits on both sides, so `(2 5 /) .` is still the `.`
+/// function.
+fn continues_decimal(buffer: &[char], next: char, after: char) -> bool {
+    let part = String::from_iter(buffer);
+
+    if next == '.' {
+        return is_number(after) && is_part_int_numeric(&part);
+    }
+
+    match part.strip_suffix('.') {
+        Some(whole) => is_number(next) && !whole.is_empty() && is_part_int_numeric(whole),
+        None => false,
+    }
+}
+
+// 3i, 2.5i
+fn is_part_imaginary_numeric(part: &str) -> bool {
+    match part.strip_suffix('i') {
+        Some(number) if !number.is_empty() => {
+            is_part_int_numeric(number) || is_part_dec_numeric(number)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the next character makes a two character operator like `<=`, `+=` or `=:`
+fn continues_operator(buffer: &[char], next: char) -> bool {
+    matches!(
+        (buffer, next),
+        (['<' | '>' | '=' | '!' | '+' | '-' | '*' | '/'], '=') | (['='], ':')
+    )
 }
 
 fn is_part_alpha(part: &str) -> bool {
@@ -172,15 +276,15 @@ fn is_part_alpha(part: &str) -> bool {
         }
     }
 
-    return true;
+    true
 }
 
 impl TokenTrait for Token {
     fn default() -> Self {
-        return Token {
+        Token {
             token_type: TokenType::NoType,
             value: String::new(),
-        };
+        }
     }
 
     fn tokenize(tokens: String) -> Self {
@@ -203,6 +307,7 @@ impl TokenTrait for Token {
                 "-" => TokenType::Subtraction,
                 "*" => TokenType::Multiplication,
                 "/" => TokenType::Division,
+                "^" => TokenType::Power,
 
                 ">" => TokenType::Greater,
                 "<" => TokenType::Less,
@@ -235,12 +340,57 @@ impl TokenTrait for Token {
             }
         }
 
+        if tokens.len() == 2 {
+            let token_type = match token_str {
+                ">=" => TokenType::GreaterEqual,
+                "<=" => TokenType::LessEqual,
+                "==" => TokenType::Equal,
+                "!=" => TokenType::NotEqual,
+                "=:" => TokenType::FunctionAssignment,
+                "+=" => TokenType::AddAssignment,
+                "-=" => TokenType::SubtractAssignment,
+                "*=" => TokenType::MultiplyAssignment,
+                "/=" => TokenType::DivideAssignment,
+                _ => TokenType::NoType,
+            };
+
+            if token_type != TokenType::NoType {
+                token.token_type = token_type;
+                token.value = tokens;
+                return token;
+            }
+        }
+
         if is_part_int_numeric(token_str) {
             token.token_type = TokenType::NumericIntLiteral;
             token.value = tokens;
             return token;
         }
 
+        if is_part_dec_numeric(token_str) {
+            token.token_type = TokenType::NumericDecLiteral;
+            token.value = tokens;
+            return token;
+        }
+
+        if is_part_imaginary_numeric(token_str) {
+            token.token_type = TokenType::NumericImaginaryLiteral;
+            token.value = tokens;
+            return token;
+        }
+
+        if token_str == "true" || token_str == "false" {
+            token.token_type = TokenType::BoolLiteral;
+            token.value = tokens;
+            return token;
+        }
+
+        if token_str == "nil" {
+            token.token_type = TokenType::NilLiteral;
+            token.value = tokens;
+            return token;
+        }
+
         // Check for type keywords like "int" and "dec"
         let tok = is_type(token_str);
         if tok != TokenType::NoType {
@@ -257,7 +407,7 @@ impl TokenTrait for Token {
         }
 
         token.value = tokens;
-        return token;
+        token
     }
 
     fn from_chars(chars: Vec<char>) -> Self {
@@ -299,7 +449,7 @@ impl Lex for Lexer {
     }
 
     fn prev(&mut self) -> Token {
-        return self.prev_token.clone();
+        self.prev_token.clone()
     }
 
     fn next(&mut self) -> Token {
@@ -320,22 +470,41 @@ impl Lex for Lexer {
 
         // Iterate through using windows of size 2
         // abcd -> (a, b), (b, c), (c, d)
-        for (cur, next) in current_line.chars().into_iter().tuple_windows() {
+        let chars: Vec<char> = current_line.chars().collect();
+        for (index, (cur, next)) in chars.iter().copied().tuple_windows().enumerate() {
             // Skip whitespace at the start of a new section on un-lexed line
             if is_char_whitespace(cur) {
                 self.column_index += 1;
                 continue;
             }
 
+            // A string runs to the closing quote, so it can have spaces and symbols inside
+            if buffer.is_empty() && cur == '\"' {
+                if let Some(length) = chars[index + 1..].iter().position(|c| *c == '\"') {
+                    let inner = &chars[index + 1..index + 1 + length];
+                    self.column_index += inner.iter().map(|c| c.len_utf8()).sum::<usize>() + 2;
+
+                    return Token {
+                        token_type: TokenType::StringLiteral,
+                        value: String::from_iter(inner),
+                    };
+                }
+            }
+
             self.column_index += 1;
             buffer.push(cur);
+
+            let after = chars.get(index + 2).copied().unwrap_or(' ');
+            if continues_decimal(&buffer, next, after) || continues_operator(&buffer, next) {
+                continue;
+            }
+
             if ends_token(cur, next) {
                 break;
             }
         }
 
-        let new_token = Token::from_chars(buffer);
-        return new_token;
+        Token::from_chars(buffer)
     }
 
     fn reset_line(&mut self) {
@@ -371,6 +540,41 @@ mod tests {
 
         assert_eq!(Token::tokenize("1".to_string()).value, "1".to_string());
 
+        assert_eq!(
+            Token::tokenize("0".to_string()).token_type,
+            TokenType::NumericIntLiteral
+        );
+        assert_eq!(
+            Token::tokenize("^".to_string()).token_type,
+            TokenType::Power
+        );
+
+        assert_eq!(
+            Token::tokenize("2.5".to_string()).token_type,
+            TokenType::NumericDecLiteral
+        );
+        assert_eq!(
+            Token::tokenize("0.0124".to_string()).token_type,
+            TokenType::NumericDecLiteral
+        );
+        assert_ne!(
+            Token::tokenize("2.".to_string()).token_type,
+            TokenType::NumericDecLiteral
+        );
+
+        assert_eq!(
+            Token::tokenize("3i".to_string()).token_type,
+            TokenType::NumericImaginaryLiteral
+        );
+        assert_eq!(
+            Token::tokenize("2.5i".to_string()).token_type,
+            TokenType::NumericImaginaryLiteral
+        );
+        assert_ne!(
+            Token::tokenize("i".to_string()).token_type,
+            TokenType::NumericImaginaryLiteral
+        );
+
         // Note: "1 " should NOT be a valid token, because the value given to tokenize should cut
         // off after the 1 because of the ending token check with ends_token
         // However, when this is given to the lexer, it should lex "1 " as a valid
@@ -432,6 +636,24 @@ mod tests {
 
         lex.reset_line();
 
+        lex.lines = vec!["1 2.5 + 0.4 .".to_string()];
+
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "2.5");
+        assert_eq!(lex.next().value, "+");
+        assert_eq!(lex.next().value, "0.4");
+        assert_eq!(lex.next().value, ".");
+
+        lex.reset_line();
+
+        assert_eq!(lex.next().token_type, TokenType::NumericIntLiteral);
+        assert_eq!(lex.next().token_type, TokenType::NumericDecLiteral);
+        assert_eq!(lex.next().token_type, TokenType::Addition);
+        assert_eq!(lex.next().token_type, TokenType::NumericDecLiteral);
+        assert_eq!(lex.next().token_type, TokenType::Dot);
+
+        lex.reset_line();
+
         lex.lines = vec!["b ratio 3 4 / =".to_string()];
 
         assert_eq!(lex.next().value, "b");
@@ -452,6 +674,73 @@ mod tests {
 
         lex.reset_line();
 
+        lex.lines = vec!["f (x int) =: x 1 +".to_string()];
+
+        assert_eq!(lex.next().value, "f");
+        assert_eq!(lex.next().value, "(");
+        assert_eq!(lex.next().value, "x");
+        assert_eq!(lex.next().value, "int");
+        assert_eq!(lex.next().value, ")");
+        assert_eq!(lex.next().value, "=:");
+
+        lex.reset_line();
+
+        assert_eq!(lex.next().token_type, TokenType::Identifier);
+        assert_eq!(lex.next().token_type, TokenType::LeftParen);
+        assert_eq!(lex.next().token_type, TokenType::Identifier);
+        assert_eq!(lex.next().token_type, TokenType::TypeIntKeyword);
+        assert_eq!(lex.next().token_type, TokenType::RightParen);
+        assert_eq!(lex.next().token_type, TokenType::FunctionAssignment);
+
+        lex.reset_line();
+
+        lex.lines = vec!["sum x += count 1 -= a 2 *= b 2 /=".to_string()];
+
+        assert_eq!(lex.next().value, "sum");
+        assert_eq!(lex.next().value, "x");
+        assert_eq!(lex.next().token_type, TokenType::AddAssignment);
+        assert_eq!(lex.next().value, "count");
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().token_type, TokenType::SubtractAssignment);
+        assert_eq!(lex.next().value, "a");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().token_type, TokenType::MultiplyAssignment);
+        assert_eq!(lex.next().value, "b");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().value, "/=");
+
+        lex.reset_line();
+
+        lex.lines = vec!["1 2 <= true !=".to_string()];
+
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().value, "<=");
+        assert_eq!(lex.next().value, "true");
+        assert_eq!(lex.next().value, "!=");
+
+        lex.reset_line();
+
+        assert_eq!(lex.next().token_type, TokenType::NumericIntLiteral);
+        assert_eq!(lex.next().token_type, TokenType::NumericIntLiteral);
+        assert_eq!(lex.next().token_type, TokenType::LessEqual);
+        assert_eq!(lex.next().token_type, TokenType::BoolLiteral);
+        assert_eq!(lex.next().token_type, TokenType::NotEqual);
+
+        lex.reset_line();
+
+        lex.lines = vec!["\"hello world\" size".to_string()];
+
+        assert_eq!(lex.next().value, "hello world");
+        assert_eq!(lex.next().value, "size");
+
+        lex.reset_line();
+
+        assert_eq!(lex.next().token_type, TokenType::StringLiteral);
+        assert_eq!(lex.next().token_type, TokenType::TypeSizeKeyword);
+
+        lex.reset_line();
+
         lex.lines = vec!["e".to_string()];
 
         assert_eq!(lex.next().value, "e");
@@ -522,26 +811,27 @@ mod tests {
         assert_eq!(lex.next().value, "ratio");
         assert_eq!(lex.next().value, "]");
         assert_eq!(lex.next().value, ")");
-        assert_eq!(lex.next().value, "=");
-        assert_eq!(lex.next().value, ":");
+        assert_eq!(lex.next().value, "=:");
 
         lex.reset_line();
 
         assert_eq!(lex.next().token_type, TokenType::Identifier);
         assert_eq!(lex.next().token_type, TokenType::LeftParen);
         assert_eq!(lex.next().token_type, TokenType::Identifier);
-        assert_eq!(lex.next().token_type, TokenType::Identifier);
+        assert_eq!(lex.next().token_type, TokenType::TypeContainerKeyword);
         assert_eq!(lex.next().token_type, TokenType::LeftBracket);
         assert_eq!(lex.next().token_type, TokenType::TypeRatioKeyword);
         assert_eq!(lex.next().token_type, TokenType::RightBracket);
         assert_eq!(lex.next().token_type, TokenType::RightParen);
-        assert_eq!(lex.next().token_type, TokenType::Assignment);
-        assert_eq!(lex.next().token_type, TokenType::Colon);
+        assert_eq!(lex.next().token_type, TokenType::FunctionAssignment);
     }
 
     #[test]
     fn is_type_test() {
         assert_eq!(is_type("dec"), TokenType::TypeDecKeyword);
+        assert_eq!(is_type("vector"), TokenType::TypeVectorKeyword);
+        assert_eq!(is_type("set"), TokenType::TypeSetKeyword);
+        assert_eq!(is_type("sample"), TokenType::TypeSampleKeyword);
     }
 
     #[test]
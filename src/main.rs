@@ -1,47 +1,164 @@
+use crate::environment::{Environment, MAX_DEPTH_LIMIT};
 use crate::lexer::{Lex, Lexer, Token, TokenType};
-use crate::parser::{parse, tokens_to_expr, Expression};
-use efcl::{bold, color, Color};
+use crate::parser::{check_nesting, eval_in, tokens_to_expr_in, Expression};
+use efcl::{color, Color};
 use std::io::{stdin, stdout, Write};
+use std::process::exit;
+use std::thread;
 
+pub mod closure;
+pub mod container;
+pub mod deriv;
+pub mod eigen;
+pub mod environment;
+pub mod error;
+pub mod function;
+pub mod infer;
 pub mod lexer;
+pub mod matrix;
+pub mod number;
 pub mod parser;
+pub mod pattern;
+pub mod real;
+pub mod simplify;
+pub mod size;
+pub mod solve;
+pub mod statistics;
+pub mod value;
+pub mod vector;
 
-fn interactive() {
+fn lex_input(input: &str) -> Vec<Token> {
+    let mut lex: Lexer = Lexer::new(vec![input.to_string()]);
+
+    let mut tokens = Vec::<Token>::new();
+
+    loop {
+        let a = lex.next();
+
+        if a.token_type == TokenType::EndToken {
+            break;
+        }
+
+        tokens.push(a);
+    }
+
+    tokens
+}
+
+/// Whether a `{` or `[` has been opened without being closed, so the input carries on to the
+/// next line
+fn is_open(tokens: &[Token]) -> bool {
+    let count =
+        |token_type: TokenType| tokens.iter().filter(|t| t.token_type == token_type).count();
+    count(TokenType::LeftBrace) > count(TokenType::RightBrace)
+        || count(TokenType::LeftBracket) > count(TokenType::RightBracket)
+}
+
+fn interactive(mut env: Environment) {
     loop {
         print!("{}", color!(Color::GREEN, "\n> "));
         stdout().flush().expect("Failed to flush stdout");
 
         let mut input = String::new();
+        let mut tokens;
 
-        let _ = stdin().read_line(&mut input);
-
-        let mut lex: Lexer = Lexer::new(vec![input]);
+        loop {
+            let mut line = String::new();
 
-        let mut tokens = Vec::<Token>::new();
+            // Stop at the end of input
+            if let Ok(0) = stdin().read_line(&mut line) {
+                return;
+            }
 
-        loop {
-            let a = lex.next();
+            input.push_str(line.trim_end());
+            input.push(' ');
 
-            if a.token_type == TokenType::EndToken {
+            tokens = lex_input(&input);
+            if !is_open(&tokens) {
                 break;
             }
+        }
 
-            println!("{:?}", a);
-            tokens.push(a);
+        for token in &tokens {
+            println!("{:?}", token);
         }
 
-        let expr = tokens_to_expr(tokens);
-        let out = parse(expr);
+        if tokens.is_empty() {
+            continue;
+        }
 
-        match out {
-            Expression::None => {}
-            _ => {
-                println!("-> {:?}", out);
-            }
+        if let Err(error) = check_nesting(&tokens) {
+            println!("{}", color!(Color::RED, &error.to_string()));
+            continue;
+        }
+
+        let expr = tokens_to_expr_in(tokens, &env);
+
+        if expr == Expression::None {
+            println!(
+                "{}",
+                color!(Color::RED, "error: Could not parse expression")
+            );
+            continue;
+        }
+
+        match eval_in(&expr, &mut env) {
+            // An assignment shows the name it was given, like `a = int: 5`
+            Ok(value) => match &expr {
+                Expression::Assignment(a) => println!("{} = {}", a.expression_1.source(), value),
+                Expression::CompoundAssignment(c) => println!("{} = {}", c.name.value, value),
+                _ => println!("{}", value),
+            },
+            Err(error) => println!("{}", color!(Color::RED, &error.to_string())),
+        }
+    }
+}
+
+/// The recursion limit from `--depth-limit <calls>`, if it was given
+fn depth_limit() -> Option<usize> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|a| a == "--depth-limit")?;
+
+    match args.get(index + 1).map(|a| a.parse::<usize>()) {
+        Some(Ok(limit)) if limit <= MAX_DEPTH_LIMIT => Some(limit),
+        Some(Ok(_)) => {
+            eprintln!("--depth-limit can be at most {} calls", MAX_DEPTH_LIMIT);
+            exit(1);
+        }
+        _ => {
+            eprintln!("--depth-limit expects a number of calls");
+            exit(1);
         }
     }
 }
 
 fn main() {
-    interactive();
+    let env = match depth_limit() {
+        Some(limit) => Environment::with_depth_limit(limit),
+        None => Environment::new(),
+    };
+
+    let stack_size = match env.stack_size() {
+        Some(size) => size,
+        None => {
+            eprintln!("--depth-limit is too large to have a stack for");
+            exit(1);
+        }
+    };
+
+    // Deep recursion needs more stack than the main thread has
+    let interpreter = match thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(move || interactive(env))
+    {
+        Ok(interpreter) => interpreter,
+        Err(error) => {
+            eprintln!("Could not start the interpreter: {}", error);
+            exit(1);
+        }
+    };
+
+    interpreter
+        .join()
+        .expect("The interpreter stopped unexpectedly");
 }
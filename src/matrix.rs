@@ -0,0 +1,462 @@
+use crate::error::BasisError;
+use crate::lexer::TokenType;
+use crate::number::Number;
+use crate::vector::Vector;
+
+// Entries smaller than this are treated as zero when working with decs
+const TOLERANCE: f64 = 1e-12;
+
+type Rows = Vec<Vec<Number>>;
+
+fn operate(a: &Number, b: &Number, operation: TokenType) -> Result<Number, BasisError> {
+    a.clone().operate(b.clone(), &operation)
+}
+
+fn magnitude(number: &Number) -> f64 {
+    number.to_complex().abs()
+}
+
+/// Whether an entry is zero, where decs only need to be close to it
+fn negligible(number: &Number) -> bool {
+    match number {
+        Number::Int(_) | Number::Ratio(_) => number.is_zero(),
+        _ => magnitude(number) < TOLERANCE,
+    }
+}
+
+/// The row with the largest entry in a column from `start` down, which is the most stable pivot
+/// for decs, and any nonzero entry is exact for ints and ratios
+fn pivot_row(rows: &Rows, start: usize, column: usize) -> Option<usize> {
+    (start..rows.len())
+        .filter(|r| !negligible(&rows[*r][column]))
+        .max_by(|a, b| magnitude(&rows[*a][column]).total_cmp(&magnitude(&rows[*b][column])))
+}
+
+/// The parts of an LU decomposition, with the order the rows were swapped into and the number
+/// of swaps
+struct Decomposition {
+    order: Vec<usize>,
+    swaps: usize,
+    lower: Rows,
+    upper: Rows,
+}
+
+fn identity(size: usize) -> Rows {
+    (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|column| Number::Int(i64::from(row == column)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Reduce rows to reduced row echelon form, giving back the column of each pivot. Only the
+/// first `columns` columns are used for pivots, so the right side of `[A | B]` is carried along.
+fn reduce(rows: &mut Rows, columns: usize) -> Result<Vec<usize>, BasisError> {
+    let mut pivots = Vec::<usize>::new();
+
+    for column in 0..columns {
+        let row = pivots.len();
+        if row == rows.len() {
+            break;
+        }
+
+        let Some(pivot) = pivot_row(rows, row, column) else {
+            continue;
+        };
+
+        rows.swap(row, pivot);
+
+        let lead = rows[row][column].clone();
+        for entry in rows[row].iter_mut() {
+            *entry = operate(entry, &lead, TokenType::Division)?;
+        }
+
+        for other in 0..rows.len() {
+            let factor = rows[other][column].clone();
+            if other == row || factor.is_zero() {
+                continue;
+            }
+
+            for index in 0..rows[other].len() {
+                let step = operate(&factor, &rows[row][index], TokenType::Multiplication)?;
+                rows[other][index] = operate(&rows[other][index], &step, TokenType::Subtraction)?;
+            }
+        }
+
+        pivots.push(column);
+    }
+
+    Ok(pivots)
+}
+
+impl Vector {
+    /// Whether the elements are ints or ratios, so the results can be kept exact
+    fn is_exact(&self) -> bool {
+        matches!(self.element_type(), "int" | "ratio")
+    }
+
+    /// The rows to work on, where ints become ratios so division stays exact
+    fn working_rows(&self) -> Result<Rows, BasisError> {
+        let rows = self.to_rows();
+        if self.element_type() != "int" {
+            return Ok(rows);
+        }
+
+        rows.into_iter()
+            .map(|row| row.into_iter().map(|n| n.cast("ratio")).collect())
+            .collect()
+    }
+
+    fn expect_square(&self, name: &str) -> Result<usize, BasisError> {
+        if self.rows != self.columns {
+            return Err(BasisError::new(&format!(
+                "`{}` needs a square vector but got {}x{}",
+                name, self.rows, self.columns
+            )));
+        }
+
+        Ok(self.rows)
+    }
+
+    pub fn transpose(&self) -> Vector {
+        let mut elements = Vec::<Number>::new();
+        for column in 0..self.columns {
+            for row in 0..self.rows {
+                elements.push(self.elements[row * self.columns + column].clone());
+            }
+        }
+
+        Vector {
+            elements,
+            rows: self.columns,
+            columns: self.rows,
+        }
+    }
+
+    /// The matrix product, like `[1 2; 3 4] [5; 6] *`
+    pub fn multiply(&self, other: &Vector) -> Result<Vector, BasisError> {
+        if self.columns != other.rows {
+            return Err(BasisError::new(&format!(
+                "Cannot multiply a {}x{} vector by a {}x{} vector",
+                self.rows, self.columns, other.rows, other.columns
+            )));
+        }
+
+        let columns = other.transpose().to_rows();
+        let mut rows = Rows::new();
+
+        for row in self.to_rows() {
+            let mut out = Vec::<Number>::new();
+            for column in &columns {
+                let mut sum = Number::Int(0);
+                for (a, b) in row.iter().zip(column) {
+                    let product = operate(a, b, TokenType::Multiplication)?;
+                    sum = operate(&sum, &product, TokenType::Addition)?;
+                }
+
+                out.push(sum);
+            }
+
+            rows.push(out);
+        }
+
+        Vector::new(rows)
+    }
+
+    pub fn trace(&self) -> Result<Number, BasisError> {
+        let size = self.expect_square("trace")?;
+
+        let mut sum = Number::Int(0);
+        for index in 0..size {
+            sum = operate(
+                &sum,
+                &self.elements[index * size + index],
+                TokenType::Addition,
+            )?;
+        }
+
+        Ok(sum)
+    }
+
+    /// The determinant, which is an int for a vector of ints
+    pub fn determinant(&self) -> Result<Number, BasisError> {
+        self.expect_square("det")?;
+        let parts = self.decompose()?;
+
+        // Each swap of two rows flips the sign
+        let mut out = Number::Int(if parts.swaps % 2 == 0 { 1 } else { -1 });
+        for (index, row) in parts.upper.iter().enumerate() {
+            out = operate(&out, &row[index], TokenType::Multiplication)?;
+        }
+
+        match self.element_type() {
+            "int" => out.cast("int"),
+            _ => Ok(out),
+        }
+    }
+
+    pub fn rank(&self) -> Result<usize, BasisError> {
+        let mut rows = self.working_rows()?;
+        Ok(reduce(&mut rows, self.columns)?.len())
+    }
+
+    pub fn inverse(&self) -> Result<Vector, BasisError> {
+        let size = self.expect_square("inverse")?;
+        self.solve(&Vector::new(identity(size))?)
+            .map_err(|_| BasisError::new("The vector is singular, so it has no inverse"))
+    }
+
+    /// Solve `A x = b` for `x`, where `b` can be a single row that is read as a column like
+    /// `[1 2; 3 4] [5 6] solve`
+    pub fn solve(&self, other: &Vector) -> Result<Vector, BasisError> {
+        let size = self.expect_square("solve")?;
+
+        let row = other.rows == 1 && other.columns == size && size != 1;
+        let right = match row {
+            true => other.transpose(),
+            false => other.clone(),
+        };
+
+        if right.rows != size {
+            return Err(BasisError::new(&format!(
+                "Cannot solve a {}x{} system for a {}x{} vector",
+                size, size, other.rows, other.columns
+            )));
+        }
+
+        let exact = self.is_exact() && right.is_exact();
+        let mut rows = Rows::new();
+        for (a, b) in self.working_rows()?.into_iter().zip(right.to_rows()) {
+            let mut joined = a;
+            for entry in b {
+                joined.push(match exact {
+                    true => entry.cast("ratio")?,
+                    false => entry,
+                });
+            }
+
+            rows.push(joined);
+        }
+
+        if reduce(&mut rows, size)?.len() < size {
+            return Err(BasisError::new(
+                "The vector is singular, so the system has no single solution",
+            ));
+        }
+
+        let solution = Vector::new(rows.into_iter().map(|r| r[size..].to_vec()).collect())?;
+        Ok(match row {
+            true => solution.transpose(),
+            false => solution,
+        })
+    }
+
+    fn decompose(&self) -> Result<Decomposition, BasisError> {
+        let size = self.expect_square("lu")?;
+
+        let mut parts = Decomposition {
+            order: (0..size).collect(),
+            swaps: 0,
+            lower: identity(size),
+            upper: self.working_rows()?,
+        };
+
+        for column in 0..size {
+            let Some(pivot) = pivot_row(&parts.upper, column, column) else {
+                continue;
+            };
+
+            if pivot != column {
+                parts.upper.swap(column, pivot);
+                parts.order.swap(column, pivot);
+                parts.swaps += 1;
+
+                // The multipliers found so far move with their rows
+                for index in 0..column {
+                    let below = parts.lower[pivot][index].clone();
+                    parts.lower[pivot][index] =
+                        std::mem::replace(&mut parts.lower[column][index], below);
+                }
+            }
+
+            let pivot = parts.upper[column].clone();
+            for row in column + 1..size {
+                let below = &mut parts.upper[row];
+                let factor = operate(&below[column], &pivot[column], TokenType::Division)?;
+                for index in column..size {
+                    let step = operate(&factor, &pivot[index], TokenType::Multiplication)?;
+                    below[index] = operate(&below[index], &step, TokenType::Subtraction)?;
+                }
+
+                parts.lower[row][column] = factor;
+            }
+        }
+
+        Ok(parts)
+    }
+
+    /// The LU decomposition with row swaps, giving back `P`, `L` and `U` where `P A = L U`
+    pub fn lu(&self) -> Result<(Vector, Vector, Vector), BasisError> {
+        let parts = self.decompose()?;
+        let size = parts.order.len();
+
+        let permutation: Rows = parts
+            .order
+            .iter()
+            .map(|from| {
+                (0..size)
+                    .map(|c| Number::Int(i64::from(c == *from)))
+                    .collect()
+            })
+            .collect();
+
+        Ok((
+            Vector::new(permutation)?,
+            Vector::new(parts.lower)?,
+            Vector::new(parts.upper)?,
+        ))
+    }
+
+    /// The QR decomposition by Gram-Schmidt, giving back `Q` with orthonormal columns and an
+    /// upper triangular `R` where `A = Q R`. The square roots make this a decomposition of decs.
+    pub fn qr(&self) -> Result<(Vector, Vector), BasisError> {
+        let columns = self.columns;
+        if self.rows < columns {
+            return Err(BasisError::new(&format!(
+                "`qr` needs at least as many rows as columns but got {}x{}",
+                self.rows, columns
+            )));
+        }
+
+        let mut q = self.transpose().to_decs("qr")?;
+        let mut r = vec![vec![0.0; columns]; columns];
+
+        for column in 0..columns {
+            // Take out the part of the column along each column before it
+            let (done, rest) = q.split_at_mut(column);
+            for (previous, unit) in done.iter().enumerate() {
+                let dot: f64 = unit.iter().zip(&rest[0]).map(|(a, b)| a * b).sum();
+                r[previous][column] = dot;
+                for (entry, along) in rest[0].iter_mut().zip(unit) {
+                    *entry -= dot * along;
+                }
+            }
+
+            let norm = q[column].iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm < TOLERANCE {
+                return Err(BasisError::new(
+                    "`qr` needs columns that are independent of each other",
+                ));
+            }
+
+            r[column][column] = norm;
+            for entry in q[column].iter_mut() {
+                *entry /= norm;
+            }
+        }
+
+        Ok((Vector::from_decs(q)?.transpose(), Vector::from_decs(r)?))
+    }
+
+    /// The rows as decs, for the decompositions that are only numerical
+    pub fn to_decs(&self, name: &str) -> Result<Vec<Vec<f64>>, BasisError> {
+        if self.elements.iter().any(|n| !n.is_real()) {
+            return Err(BasisError::new(&format!(
+                "`{}` needs a vector of real numbers but got {}",
+                name,
+                self.element_type()
+            )));
+        }
+
+        Ok(self
+            .to_rows()
+            .iter()
+            .map(|row| row.iter().map(Number::to_dec).collect())
+            .collect())
+    }
+
+    pub fn from_decs(rows: Vec<Vec<f64>>) -> Result<Vector, BasisError> {
+        Vector::new(
+            rows.into_iter()
+                .map(|row| row.into_iter().map(Number::Dec).collect())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::number::Ratio;
+
+    fn matrix(rows: &[&[i64]]) -> Vector {
+        Vector::new(
+            rows.iter()
+                .map(|row| row.iter().map(|v| Number::Int(*v)).collect())
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn ratio(numerator: i64, denominator: i64) -> Number {
+        Number::Ratio(Ratio::new(numerator, denominator).unwrap())
+    }
+
+    #[test]
+    fn exact_test() {
+        let a = matrix(&[&[2, 1, 1], &[1, 3, 2], &[1, 0, 0]]);
+        assert_eq!(a.determinant(), Ok(Number::Int(-1)));
+        assert_eq!(a.trace(), Ok(Number::Int(5)));
+        assert_eq!(a.rank(), Ok(3));
+        assert_eq!(matrix(&[&[1, 2, 3], &[2, 3, 4], &[3, 4, 5]]).rank(), Ok(2));
+
+        // The inverse of an int vector is exact
+        let inverse = a.inverse().unwrap();
+        assert_eq!(
+            a.multiply(&inverse).unwrap().cast("int"),
+            Ok(matrix(&[&[1, 0, 0], &[0, 1, 0], &[0, 0, 1]]))
+        );
+
+        let half = matrix(&[&[2, 0], &[0, 2]]).inverse().unwrap();
+        assert_eq!(half.elements[0], ratio(1, 2));
+        assert!(matrix(&[&[1, 2], &[2, 4]]).inverse().is_err());
+
+        // A row on the right is read as a column, and the solution is a row again
+        let x = a.solve(&matrix(&[&[4, 5, 6]])).unwrap();
+        assert_eq!((x.rows, x.columns), (1, 3));
+        assert_eq!(x.cast("int"), Ok(matrix(&[&[6, 15, -23]])));
+
+        // P A = L U, where the swaps change the sign of the determinant
+        let b = matrix(&[&[0, 1], &[2, 3]]);
+        let (p, l, u) = b.lu().unwrap();
+        assert_eq!(p.multiply(&b), l.multiply(&u).and_then(|m| m.cast("int")));
+        assert_eq!(b.determinant(), Ok(Number::Int(-2)));
+
+        assert_eq!(
+            matrix(&[&[1, 2], &[3, 4]]).transpose(),
+            matrix(&[&[1, 3], &[2, 4]])
+        );
+        assert!(matrix(&[&[1, 2]]).multiply(&matrix(&[&[3, 4]])).is_err());
+        assert!(matrix(&[&[1, 2]]).determinant().is_err());
+    }
+
+    #[test]
+    fn qr_test() {
+        let a = matrix(&[&[1, 2], &[3, 4], &[5, 6]]);
+        let (q, r) = a.qr().unwrap();
+        assert_eq!((q.rows, q.columns, r.rows, r.columns), (3, 2, 2, 2));
+
+        // Q R gives back A, and R is upper triangular
+        let back = q.multiply(&r).unwrap().to_decs("qr").unwrap();
+        for (row, expected) in back.iter().zip(a.to_decs("qr").unwrap()) {
+            for (x, y) in row.iter().zip(expected) {
+                assert!((x - y).abs() < 1e-9);
+            }
+        }
+        assert_eq!(r.elements[2], Number::Dec(0.0));
+
+        assert!(matrix(&[&[1, 2], &[2, 4]]).qr().is_err());
+    }
+}
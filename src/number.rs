@@ -0,0 +1,820 @@
+use crate::error::BasisError;
+use crate::lexer::TokenType;
+use crate::real::{Real, DEFAULT_PRECISION};
+use std::cmp::Ordering;
+use std::fmt;
+
+fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+/// An exact fraction, kept in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ratio {
+    pub numerator: i64,
+    pub denominator: i64,
+}
+
+impl Ratio {
+    /// Gives back `None` when the denominator is zero, or on overflow when a negative
+    /// denominator like `i64::MIN` has no positive counterpart
+    pub fn new(numerator: i64, denominator: i64) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        // The divisor can be `2^63`, which only fits in an i64 once it is divided out
+        let divisor = i128::from(gcd(numerator, denominator).max(1));
+        let reduce = |value: i64| i64::try_from(i128::from(value) / divisor).ok();
+        let (numerator, denominator) = (reduce(numerator)?, reduce(denominator)?);
+
+        match denominator < 0 {
+            true => Some(Ratio {
+                numerator: numerator.checked_neg()?,
+                denominator: denominator.checked_neg()?,
+            }),
+            false => Some(Ratio {
+                numerator,
+                denominator,
+            }),
+        }
+    }
+
+    pub fn whole(value: i64) -> Self {
+        Ratio {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.numerator == 1 && self.denominator == 1
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.denominator == 1
+    }
+
+    // The arithmetic gives back `None` on overflow or division by zero
+
+    pub fn add(&self, other: &Ratio) -> Option<Self> {
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)?
+            .checked_add(other.numerator.checked_mul(self.denominator)?)?;
+
+        Ratio::new(numerator, self.denominator.checked_mul(other.denominator)?)
+    }
+
+    pub fn sub(&self, other: &Ratio) -> Option<Self> {
+        self.add(&other.neg()?)
+    }
+
+    pub fn mul(&self, other: &Ratio) -> Option<Self> {
+        Ratio::new(
+            self.numerator.checked_mul(other.numerator)?,
+            self.denominator.checked_mul(other.denominator)?,
+        )
+    }
+
+    pub fn div(&self, other: &Ratio) -> Option<Self> {
+        self.mul(&other.inverse()?)
+    }
+
+    pub fn neg(&self) -> Option<Self> {
+        Some(Ratio {
+            numerator: self.numerator.checked_neg()?,
+            denominator: self.denominator,
+        })
+    }
+
+    pub fn inverse(&self) -> Option<Self> {
+        Ratio::new(self.denominator, self.numerator)
+    }
+
+    pub fn pow(&self, exponent: i64) -> Option<Self> {
+        if exponent < 0 {
+            return self.inverse()?.pow(exponent.checked_neg()?);
+        }
+
+        let exponent = u32::try_from(exponent).ok()?;
+        Ratio::new(
+            self.numerator.checked_pow(exponent)?,
+            self.denominator.checked_pow(exponent)?,
+        )
+    }
+
+    pub fn to_dec(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_whole() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{} / {}", self.numerator, self.denominator)
+        }
+    }
+}
+
+/// A complex number `re + im i`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    pub fn add(&self, other: &Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+
+    pub fn sub(&self, other: &Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+
+    pub fn mul(&self, other: &Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    /// Gives back `None` when dividing by zero
+    pub fn div(&self, other: &Complex) -> Option<Complex> {
+        let denominator = other.re * other.re + other.im * other.im;
+        if denominator == 0.0 {
+            return None;
+        }
+
+        Some(Complex::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        ))
+    }
+
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(&self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn exp(&self) -> Complex {
+        let scale = self.re.exp();
+        Complex::new(scale * self.im.cos(), scale * self.im.sin())
+    }
+
+    /// The principal branch of the natural log
+    pub fn log(&self) -> Complex {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    /// The principal square root
+    pub fn sqrt(&self) -> Complex {
+        let r = self.abs().sqrt();
+        let theta = self.arg() / 2.0;
+        Complex::new(r * theta.cos(), r * theta.sin())
+    }
+
+    pub fn sin(&self) -> Complex {
+        Complex::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    pub fn cos(&self) -> Complex {
+        Complex::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    pub fn pow(&self, other: &Complex) -> Complex {
+        if self.re == 0.0 && self.im == 0.0 {
+            return if other.re == 0.0 && other.im == 0.0 {
+                Complex::new(1.0, 0.0)
+            } else {
+                Complex::new(0.0, 0.0)
+            };
+        }
+
+        other.mul(&self.log()).exp()
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.im < 0.0 {
+            write!(f, "{} - {}i", self.re, -self.im)
+        } else {
+            write!(f, "{} + {}i", self.re, self.im)
+        }
+    }
+}
+
+/// A value of one of the number types.
+///
+/// When an operation mixes two number types, the lower one is promoted to the higher one before
+/// the operation runs, so the result has the higher type:
+///
+/// ```text
+/// int -> ratio -> dec -> real -> complex
+///                     imaginary -> complex
+/// ```
+///
+/// A dec becomes the shortest real that reads back as the same dec, so `0.1` is promoted to
+/// exactly `0.1`. The exceptions are that `/` on two ints gives a ratio (`2 3 /` is `2 / 3`), `^` gives a dec
+/// whenever the exponent is not a whole number, and `*` and `/` on two imaginary numbers give a
+/// dec (`2i 3i *` is `-6`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Int(i64),
+    Ratio(Ratio),
+    Dec(f64),
+
+    // An arbitrary precision decimal
+    Real(Real),
+
+    // A multiple of i, like `3i`
+    Imaginary(f64),
+    Complex(Complex),
+}
+
+fn overflow() -> BasisError {
+    BasisError::new("Number is too large")
+}
+
+impl Number {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Number::Int(_) => "int",
+            Number::Ratio(_) => "ratio",
+            Number::Dec(_) => "dec",
+            Number::Real(_) => "real",
+            Number::Imaginary(_) => "imaginary",
+            Number::Complex(_) => "complex",
+        }
+    }
+
+    /// The position in the promotion order. Imaginary numbers only promote to complex, so they
+    /// share its rank.
+    fn rank(&self) -> u8 {
+        match self {
+            Number::Int(_) => 0,
+            Number::Ratio(_) => 1,
+            Number::Dec(_) => 2,
+            Number::Real(_) => 3,
+            Number::Imaginary(_) | Number::Complex(_) => 4,
+        }
+    }
+
+    /// Promote up to the number type with the given rank, which never loses precision
+    fn promote(self, rank: u8) -> Number {
+        match (self, rank) {
+            (Number::Int(value), 1) => Number::Ratio(Ratio::whole(value)),
+            (Number::Int(value), 2) => Number::Dec(value as f64),
+            (Number::Ratio(value), 2) => Number::Dec(value.to_dec()),
+            (number, 3) => number.to_real(DEFAULT_PRECISION),
+            (Number::Imaginary(value), 4) => Number::Complex(Complex::new(0.0, value)),
+            (number, 4) => Number::Complex(number.to_complex()),
+            (number, _) => number,
+        }
+    }
+
+    /// Promote to a real with the given precision, leaving numbers that have no real value as
+    /// they are
+    pub fn to_real(self, precision: u32) -> Number {
+        let real = match &self {
+            Number::Int(value) => Some(Real::int(*value, precision)),
+            Number::Ratio(value) => Some(Real::from_ratio(value, precision)),
+            Number::Dec(value) => Real::from_dec(*value, precision),
+            Number::Real(value) => {
+                Some(Real::new(value.mantissa.clone(), value.exponent, precision))
+            }
+            _ => None,
+        };
+
+        match real {
+            Some(value) => Number::Real(value),
+            None => self,
+        }
+    }
+
+    /// The precision of a real, or `None` for the other number types
+    fn precision(&self) -> Option<u32> {
+        match self {
+            Number::Real(value) => Some(value.precision),
+            _ => None,
+        }
+    }
+
+    /// The real part as a float
+    pub fn to_dec(&self) -> f64 {
+        match self {
+            Number::Int(value) => *value as f64,
+            Number::Ratio(value) => value.to_dec(),
+            Number::Dec(value) => *value,
+            Number::Real(value) => value.to_dec(),
+            Number::Imaginary(_) => 0.0,
+            Number::Complex(value) => value.re,
+        }
+    }
+
+    pub fn to_complex(&self) -> Complex {
+        match self {
+            Number::Imaginary(value) => Complex::new(0.0, *value),
+            Number::Complex(value) => *value,
+            other => Complex::new(other.to_dec(), 0.0),
+        }
+    }
+
+    pub fn is_real(&self) -> bool {
+        !matches!(self, Number::Imaginary(_) | Number::Complex(_))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Int(value) => *value == 0,
+            Number::Ratio(value) => value.is_zero(),
+            Number::Dec(value) | Number::Imaginary(value) => *value == 0.0,
+            Number::Real(value) => value.is_zero(),
+            Number::Complex(value) => value.re == 0.0 && value.im == 0.0,
+        }
+    }
+
+    /// Apply one of the builtin number functions, like `abs` or `sqrt`. Functions on real
+    /// numbers give back real numbers except where the answer is complex, like the `sqrt` of a
+    /// negative number.
+    pub fn apply(self, name: &str) -> Result<Number, BasisError> {
+        let real = self.is_real();
+        let z = self.to_complex();
+
+        let out = match (name, self) {
+            ("abs", Number::Int(value)) => Number::Int(value.checked_abs().ok_or_else(overflow)?),
+            ("abs", Number::Ratio(value)) => Number::Ratio(
+                Ratio::new(
+                    value.numerator.checked_abs().ok_or_else(overflow)?,
+                    value.denominator,
+                )
+                .ok_or_else(overflow)?,
+            ),
+            ("abs", Number::Real(value)) => Number::Real(value.abs()),
+            ("abs", _) => Number::Dec(z.abs()),
+            ("arg", _) => Number::Dec(z.arg()),
+
+            ("conj", Number::Imaginary(value)) => Number::Imaginary(-value),
+            ("conj", Number::Complex(value)) => Number::Complex(value.conj()),
+            ("conj", number) => number,
+
+            ("re", Number::Imaginary(_)) | ("re", Number::Complex(_)) => Number::Dec(z.re),
+            ("re", number) => number,
+            ("im", _) if real => Number::Int(0),
+            ("im", _) => Number::Dec(z.im),
+
+            ("sqrt", Number::Real(value)) => match value.sqrt() {
+                Some(root) => Number::Real(root),
+                None => Number::Imaginary(value.neg().to_dec().sqrt()),
+            },
+            ("sqrt", _) if real && z.re < 0.0 => Number::Imaginary((-z.re).sqrt()),
+            ("sqrt", _) if real => Number::Dec(z.re.sqrt()),
+            ("sqrt", _) => Number::Complex(z.sqrt()),
+
+            ("log", _) if real && z.re > 0.0 => Number::Dec(z.re.ln()),
+            ("log", _) if z.re == 0.0 && z.im == 0.0 => {
+                return Err(BasisError::new("The log of zero is undefined"))
+            }
+            ("log", _) => Number::Complex(z.log()),
+
+            ("exp", _) if real => Number::Dec(z.re.exp()),
+            ("exp", _) => Number::Complex(z.exp()),
+            ("sin", _) if real => Number::Dec(z.re.sin()),
+            ("sin", _) => Number::Complex(z.sin()),
+            ("cos", _) if real => Number::Dec(z.re.cos()),
+            ("cos", _) => Number::Complex(z.cos()),
+            ("tan", _) if real => Number::Dec(z.re.tan()),
+            ("tan", _) => match z.sin().div(&z.cos()) {
+                Some(value) => Number::Complex(value),
+                None => return Err(BasisError::new("Division by zero")),
+            },
+
+            (name, number) => {
+                return Err(BasisError::new(&format!(
+                    "Unknown function `{}` on {}",
+                    name,
+                    number.type_name()
+                )))
+            }
+        };
+
+        Ok(out)
+    }
+
+    /// Cast to a number type by name, like the type in `a ratio 3 =`. Casting down to a lower
+    /// type is only allowed when no precision is lost.
+    pub fn cast(self, type_name: &str) -> Result<Number, BasisError> {
+        let rank = match type_name {
+            "int" => 0,
+            "ratio" => 1,
+            "dec" => 2,
+            "real" => 3,
+            "complex" => 4,
+            "imaginary" => match self {
+                Number::Imaginary(_) => return Ok(self),
+                Number::Complex(value) if value.re == 0.0 => {
+                    return Ok(Number::Imaginary(value.im))
+                }
+                _ if self.is_zero() => return Ok(Number::Imaginary(0.0)),
+                _ => {
+                    return Err(BasisError::new(&format!(
+                        "Cannot cast {} to imaginary without losing precision",
+                        self.type_name()
+                    )))
+                }
+            },
+            _ => {
+                return Err(BasisError::new(&format!(
+                    "Cannot cast {} to {}",
+                    self.type_name(),
+                    type_name
+                )))
+            }
+        };
+
+        if rank >= self.rank() {
+            return Ok(self.promote(rank));
+        }
+
+        let from = self.type_name();
+        let exact = match (&self, rank) {
+            (Number::Ratio(value), 0) if value.is_whole() => Some(Number::Int(value.numerator)),
+            (Number::Dec(value), 0) if value.fract() == 0.0 => Some(Number::Int(*value as i64)),
+            (Number::Dec(value), 1) if value.fract() == 0.0 => {
+                Some(Number::Ratio(Ratio::whole(*value as i64)))
+            }
+            (Number::Real(value), 0) => value.to_int().map(Number::Int),
+            (Number::Real(value), 1) => value.to_ratio().map(Number::Ratio),
+            (Number::Real(value), 2) => {
+                let dec = value.to_dec();
+                match Real::from_dec(dec, value.precision) {
+                    Some(back) if back.compare(value) == Ordering::Equal => Some(Number::Dec(dec)),
+                    _ => None,
+                }
+            }
+            (Number::Complex(value), _) if value.im == 0.0 => {
+                return Number::Dec(value.re).cast(type_name)
+            }
+            (Number::Imaginary(value), _) if *value == 0.0 => {
+                return Number::Int(0).cast(type_name)
+            }
+            _ => None,
+        };
+
+        match exact {
+            Some(number) => Ok(number),
+            None => Err(BasisError::new(&format!(
+                "Cannot cast {} to {} without losing precision",
+                from, type_name
+            ))),
+        }
+    }
+
+    /// Promote both sides to the higher of the two number types. Reals are promoted with the
+    /// larger precision of the two sides.
+    fn promote_pair(self, other: Number) -> (Number, Number) {
+        let rank = self.rank().max(other.rank());
+
+        match (rank, self.precision().max(other.precision())) {
+            (3, Some(precision)) => (self.to_real(precision), other.to_real(precision)),
+            _ => (self.promote(rank), other.promote(rank)),
+        }
+    }
+
+    /// The number type both numbers promote to, like `dec` for an int and a dec
+    pub fn common_type(&self, other: &Number) -> &'static str {
+        match (self, other) {
+            (Number::Imaginary(_), Number::Imaginary(_)) => "imaginary",
+            _ => self.clone().promote_pair(other.clone()).0.type_name(),
+        }
+    }
+
+    /// Compare two numbers after promoting them to the same number type. Complex numbers have
+    /// no order, so they give back `None` unless they are equal.
+    pub fn compare(self, other: Number) -> Option<Ordering> {
+        match self.promote_pair(other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+            // Denominators are always positive, so cross multiplying keeps the order
+            (Number::Ratio(a), Number::Ratio(b)) => Some(
+                (a.numerator as i128 * b.denominator as i128)
+                    .cmp(&(b.numerator as i128 * a.denominator as i128)),
+            ),
+            (Number::Dec(a), Number::Dec(b)) => a.partial_cmp(&b),
+            (Number::Real(a), Number::Real(b)) => Some(a.compare(&b)),
+            (Number::Complex(a), Number::Complex(b)) if a == b => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+
+    /// Run an arithmetic operation, promoting both sides to the same number type first
+    pub fn operate(self, other: Number, operation: &TokenType) -> Result<Number, BasisError> {
+        // Imaginary numbers stay imaginary under `+` and `-`, and become real under `*` and `/`
+        if let (Number::Imaginary(a), Number::Imaginary(b)) = (&self, &other) {
+            match operation {
+                TokenType::Addition => return Ok(Number::Imaginary(a + b)),
+                TokenType::Subtraction => return Ok(Number::Imaginary(a - b)),
+                TokenType::Multiplication => return Ok(Number::Dec(-a * b)),
+                TokenType::Division if *b == 0.0 => {
+                    return Err(BasisError::new("Division by zero"))
+                }
+                TokenType::Division => return Ok(Number::Dec(a / b)),
+                _ => {}
+            }
+        }
+
+        let (a, b) = self.promote_pair(other);
+
+        match (a, b, operation) {
+            // ---
+            // TWO INTS
+            (Number::Int(_), Number::Int(0), TokenType::Division) => {
+                Err(BasisError::new("Division by zero"))
+            }
+            (Number::Int(a), Number::Int(b), TokenType::Division) => {
+                Ratio::new(a, b).map(Number::Ratio).ok_or_else(overflow)
+            }
+
+            (Number::Int(a), Number::Int(b), TokenType::Power) if b < 0 => {
+                Number::Ratio(Ratio::whole(a)).operate(Number::Int(b), operation)
+            }
+
+            (Number::Int(a), Number::Int(b), _) => {
+                let out = match operation {
+                    TokenType::Addition => a.checked_add(b),
+                    TokenType::Subtraction => a.checked_sub(b),
+                    TokenType::Multiplication => a.checked_mul(b),
+                    TokenType::Power => a.checked_pow(u32::try_from(b).map_err(|_| overflow())?),
+                    _ => return Err(BasisError::new("Unknown operation on int")),
+                };
+
+                out.map(Number::Int).ok_or_else(overflow)
+            }
+
+            // ---
+            // TWO RATIOS
+            (Number::Ratio(a), Number::Ratio(b), _) => {
+                let by_zero = match operation {
+                    TokenType::Division => b.is_zero(),
+                    TokenType::Power => a.is_zero() && b.numerator < 0,
+                    _ => false,
+                };
+
+                if by_zero {
+                    return Err(BasisError::new("Division by zero"));
+                }
+
+                if *operation == TokenType::Power && !b.is_whole() {
+                    return Ok(Number::Dec(a.to_dec().powf(b.to_dec())));
+                }
+
+                let out = match operation {
+                    TokenType::Addition => a.add(&b),
+                    TokenType::Subtraction => a.sub(&b),
+                    TokenType::Multiplication => a.mul(&b),
+                    TokenType::Division => a.div(&b),
+                    TokenType::Power => a.pow(b.numerator),
+                    _ => return Err(BasisError::new("Unknown operation on ratio")),
+                };
+
+                out.map(Number::Ratio).ok_or_else(overflow)
+            }
+
+            // ---
+            // TWO DECS
+            (Number::Dec(a), Number::Dec(b), _) => match operation {
+                TokenType::Addition => Ok(Number::Dec(a + b)),
+                TokenType::Subtraction => Ok(Number::Dec(a - b)),
+                TokenType::Multiplication => Ok(Number::Dec(a * b)),
+                TokenType::Division => Ok(Number::Dec(a / b)),
+                TokenType::Power => Ok(Number::Dec(a.powf(b))),
+                _ => Err(BasisError::new("Unknown operation on dec")),
+            },
+
+            // ---
+            // TWO REALS
+            (Number::Real(a), Number::Real(b), _) => match operation {
+                TokenType::Addition => Ok(Number::Real(a.add(&b))),
+                TokenType::Subtraction => Ok(Number::Real(a.sub(&b))),
+                TokenType::Multiplication => Ok(Number::Real(a.mul(&b)?)),
+                TokenType::Division => Ok(Number::Real(a.div(&b)?)),
+                TokenType::Power => match b.to_int() {
+                    Some(n) => Ok(Number::Real(a.pow(n)?)),
+                    None => Ok(Number::Dec(a.to_dec().powf(b.to_dec()))),
+                },
+                _ => Err(BasisError::new("Unknown operation on real")),
+            },
+
+            // ---
+            // TWO COMPLEX NUMBERS
+            (Number::Complex(a), Number::Complex(b), _) => match operation {
+                TokenType::Addition => Ok(Number::Complex(a.add(&b))),
+                TokenType::Subtraction => Ok(Number::Complex(a.sub(&b))),
+                TokenType::Multiplication => Ok(Number::Complex(a.mul(&b))),
+                TokenType::Division => match a.div(&b) {
+                    Some(value) => Ok(Number::Complex(value)),
+                    None => Err(BasisError::new("Division by zero")),
+                },
+                TokenType::Power => Ok(Number::Complex(a.pow(&b))),
+                _ => Err(BasisError::new("Unknown operation on complex")),
+            },
+
+            (a, b, _) => Err(BasisError::new(&format!(
+                "Cannot operate on {} and {}",
+                a.type_name(),
+                b.type_name()
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Int(value) => write!(f, "int: {}", value),
+            Number::Ratio(value) => write!(f, "ratio: {}", value),
+            Number::Dec(value) => write!(f, "dec: {}", value),
+            Number::Real(value) => write!(f, "real: {}", value),
+            Number::Imaginary(value) => write!(f, "imaginary: {}i", value),
+            Number::Complex(value) => write!(f, "complex: {}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numerator: i64, denominator: i64) -> Number {
+        Number::Ratio(Ratio::new(numerator, denominator).unwrap())
+    }
+
+    #[test]
+    fn ratio_test() {
+        assert_eq!(Ratio::new(6, -4), Ratio::new(-3, 2));
+        assert_eq!(Ratio::new(1, 0), None);
+        assert_eq!(
+            Ratio::new(1, 2).unwrap().add(&Ratio::new(1, 3).unwrap()),
+            Ratio::new(5, 6)
+        );
+        assert_eq!(Ratio::new(2, 3).unwrap().pow(-2), Ratio::new(9, 4));
+
+        // The smallest i64 has no positive counterpart, so flipping its sign overflows
+        assert_eq!(Ratio::new(i64::MIN, i64::MIN), Ratio::new(1, 1));
+        assert_eq!(Ratio::new(i64::MIN, 2), Ratio::new(i64::MIN / 2, 1));
+        assert_eq!(Ratio::new(i64::MIN, -1), None);
+        assert_eq!(Ratio::new(1, i64::MIN), None);
+        assert_eq!(
+            Number::Int(i64::MIN).operate(Number::Int(-1), &TokenType::Division),
+            Err(overflow())
+        );
+    }
+
+    #[test]
+    fn promotion_test() {
+        assert_eq!(Number::Int(1).common_type(&Number::Dec(2.0)), "dec");
+        assert_eq!(
+            Number::Imaginary(1.0).common_type(&Number::Imaginary(2.0)),
+            "imaginary"
+        );
+
+        // int int / is a ratio
+        assert_eq!(
+            Number::Int(2).operate(Number::Int(3), &TokenType::Division),
+            Ok(ratio(2, 3))
+        );
+
+        // int ratio + is a ratio
+        assert_eq!(
+            Number::Int(1).operate(ratio(1, 2), &TokenType::Addition),
+            Ok(ratio(3, 2))
+        );
+
+        // int dec + is a dec
+        assert_eq!(
+            Number::Int(1).operate(Number::Dec(2.5), &TokenType::Addition),
+            Ok(Number::Dec(3.5))
+        );
+
+        // ratio dec * is a dec
+        assert_eq!(
+            ratio(1, 2).operate(Number::Dec(3.0), &TokenType::Multiplication),
+            Ok(Number::Dec(1.5))
+        );
+
+        // A negative int power is a ratio
+        assert_eq!(
+            Number::Int(2).operate(Number::Int(-2), &TokenType::Power),
+            Ok(ratio(1, 4))
+        );
+
+        assert!(Number::Int(1)
+            .operate(Number::Int(0), &TokenType::Division)
+            .is_err());
+    }
+
+    #[test]
+    fn complex_test() {
+        // 2 3i + is 2 + 3i
+        assert_eq!(
+            Number::Int(2).operate(Number::Imaginary(3.0), &TokenType::Addition),
+            Ok(Number::Complex(Complex::new(2.0, 3.0)))
+        );
+
+        // 2i 3i * is -6
+        assert_eq!(
+            Number::Imaginary(2.0).operate(Number::Imaginary(3.0), &TokenType::Multiplication),
+            Ok(Number::Dec(-6.0))
+        );
+
+        // (1 + 2i) (3 - 1i) is 5 + 5i
+        let a = Number::Complex(Complex::new(1.0, 2.0));
+        let b = Number::Complex(Complex::new(3.0, -1.0));
+        assert_eq!(
+            a.operate(b, &TokenType::Multiplication),
+            Ok(Number::Complex(Complex::new(5.0, 5.0)))
+        );
+
+        let z = Number::Complex(Complex::new(3.0, 4.0));
+        assert_eq!(z.clone().apply("abs"), Ok(Number::Dec(5.0)));
+        assert_eq!(
+            z.clone().apply("conj"),
+            Ok(Number::Complex(Complex::new(3.0, -4.0)))
+        );
+        assert_eq!(z.clone().apply("re"), Ok(Number::Dec(3.0)));
+        assert_eq!(z.apply("im"), Ok(Number::Dec(4.0)));
+
+        assert_eq!(Number::Int(-4).apply("sqrt"), Ok(Number::Imaginary(2.0)));
+
+        // e^(i pi) is -1
+        let Ok(Number::Complex(value)) = Number::Imaginary(std::f64::consts::PI).apply("exp")
+        else {
+            unreachable!()
+        };
+        assert!((value.re + 1.0).abs() < 1e-6 && value.im.abs() < 1e-6);
+
+        assert_eq!(
+            Number::Complex(Complex::new(2.0, -3.0)).to_string(),
+            "complex: 2 - 3i"
+        );
+    }
+
+    #[test]
+    fn compare_test() {
+        assert_eq!(Number::Int(1).compare(ratio(3, 2)), Some(Ordering::Less));
+        assert_eq!(ratio(1, 2).compare(Number::Dec(0.5)), Some(Ordering::Equal));
+        assert_eq!(
+            Number::Dec(2.5).compare(Number::Int(2)),
+            Some(Ordering::Greater)
+        );
+
+        // Complex numbers are only equal or not
+        let z = Number::Complex(Complex::new(1.0, 2.0));
+        assert_eq!(z.clone().compare(z.clone()), Some(Ordering::Equal));
+        assert_eq!(z.compare(Number::Int(1)), None);
+    }
+
+    #[test]
+    fn cast_test() {
+        assert_eq!(Number::Int(3).cast("ratio"), Ok(ratio(3, 1)));
+        assert_eq!(ratio(4, 2).cast("int"), Ok(Number::Int(2)));
+        assert!(ratio(1, 2).cast("int").is_err());
+        assert_eq!(ratio(1, 4).cast("dec"), Ok(Number::Dec(0.25)));
+    }
+
+    #[test]
+    fn number_display_test() {
+        assert_eq!(Number::Int(2).to_string(), "int: 2");
+        assert_eq!(ratio(2, 3).to_string(), "ratio: 2 / 3");
+        assert_eq!(Number::Dec(0.4).to_string(), "dec: 0.4");
+    }
+}
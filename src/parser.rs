@@ -1,237 +1,2841 @@
-use crate::lexer::{Lex, Lexer, Token, TokenType};
+use crate::closure::{closure_source, placeholders, Closure};
+use crate::container::Container;
+use crate::deriv::derive;
+use crate::environment::{Environment, Jump};
+use crate::error::BasisError;
+use crate::function::{Function, Parameter};
+use crate::infer::return_type;
+use crate::lexer::{Token, TokenTrait, TokenType};
+use crate::number::Number;
+use crate::pattern::Pattern;
+use crate::real::MAX_PRECISION;
+use crate::simplify::simplify;
+use crate::size::Size;
+use crate::solve::{free_variables, integrate, newton, root, LiteralFunction};
+use crate::statistics::Data;
+use crate::value::{constant, imaginary_part, unique, Value};
+use crate::vector::Vector;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerTokenExpression {
+    pub token: Token,
+}
+
+/// Expression Expression Operation
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerExpExpOp {
+    pub expression_1: Box<Expression>,
+    pub expression_2: Box<Expression>,
+    pub operation: Box<Operation>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerAssignment {
+    pub expression_1: Box<Expression>,
+    pub expression_2: Box<Expression>,
+    pub expression_3: Box<Expression>,
+    pub operation: Box<Operation>,
+}
+
+/// A function applied to the expressions on its left, like `x unwrap` or `(2 5 /) .`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerCall {
+    pub function: Token,
+    pub arguments: Vec<Expression>,
+}
+
+/// A function definition like `f (x int) =: x 1 +`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerFunction {
+    pub name: Token,
+    pub parameters: Vec<Parameter>,
+    pub body: Box<Expression>,
+}
+
+/// A closure like `: (1 1 +)` or `{ 1 1 + }`, where the body is only evaluated when it is
+/// unwrapped
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerClosure {
+    pub parameters: Vec<String>,
+    pub body: Box<Expression>,
+}
+
+/// A conditional `cond then else if`, where only the branch that is chosen is evaluated
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerIf {
+    pub condition: Box<Expression>,
+    pub then: Box<Expression>,
+    pub otherwise: Box<Expression>,
+}
+
+/// One arm of a match, like the `0..10: "small"` in `x match (0..10: "small", _: "large")`
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub result: Expression,
+}
+
+/// A match over a value, where the result of the first arm with a matching pattern is the value
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerMatch {
+    pub subject: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+/// Statements that run in order, like `c int 3 = c 1 +`, where the last gives the value
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerSequence {
+    pub expressions: Vec<Expression>,
+}
+
+/// A loop over the values of a string, an option or a range, like `for x in "abc" { ... }` or
+/// `for i: dec in 0..10 { ... }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerFor {
+    pub variable: Token,
+    pub type_token: Option<Token>,
+    pub iterable: Box<Expression>,
+
+    // The end of a range like `0..10`, where the iterable is the start
+    pub end: Option<Box<Expression>>,
+    pub body: Box<Expression>,
+}
+
+/// A loop that runs its body for as long as the condition is true, like
+/// `while i 10 < { i 1 += }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerWhile {
+    pub condition: Box<Expression>,
+    pub body: Box<Expression>,
+}
+
+/// A change to a variable that is already declared, like `sum x +=`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerCompoundAssignment {
+    pub name: Token,
+    pub value: Box<Expression>,
+    pub operation: Box<Operation>,
+}
+
+/// A vector literal like `[1 2 3]`, or `[1 2; 3 4]` with more than one row
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerVector {
+    pub rows: Vec<Vec<Expression>>,
+}
+
+/// Several values together in order, like `(1 2 3)` or the result of `lu`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerTuple {
+    pub elements: Vec<Expression>,
+}
+
+/// Values without repeats, like `{1 2 3}`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerSet {
+    pub elements: Vec<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    TokenExpression(InnerTokenExpression),
+    ExpExpOp(InnerExpExpOp),
+    Assignment(InnerAssignment),
+    Call(InnerCall),
+    Function(InnerFunction),
+    Closure(InnerClosure),
+    Sequence(InnerSequence),
+    If(InnerIf),
+    Match(InnerMatch),
+    For(InnerFor),
+    While(InnerWhile),
+    CompoundAssignment(InnerCompoundAssignment),
+    Break,
+    Continue,
+    Vector(InnerVector),
+    Tuple(InnerTuple),
+    Set(InnerSet),
+    None,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct InnerTokenOperation {
+    pub token: Token,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation {
+    TokenOperation(InnerTokenOperation),
+}
+
+/// Create the token for an operation from its type, e.g. `Addition` becomes `+`
+pub fn operation_token(token_type: TokenType) -> Token {
+    let value = match token_type {
+        TokenType::Addition => "+",
+        TokenType::Subtraction => "-",
+        TokenType::Multiplication => "*",
+        TokenType::Division => "/",
+        TokenType::Power => "^",
+        TokenType::Dot => ".",
+        TokenType::Greater => ">",
+        TokenType::Less => "<",
+        TokenType::GreaterEqual => ">=",
+        TokenType::LessEqual => "<=",
+        TokenType::Equal => "==",
+        TokenType::NotEqual => "!=",
+        _ => "",
+    };
+
+    Token {
+        token_type,
+        value: value.to_string(),
+    }
+}
+
+/// The operation a compound assignment applies, like `Addition` for `+=`
+pub fn compound_operation(token_type: &TokenType) -> Option<TokenType> {
+    match token_type {
+        TokenType::AddAssignment => Some(TokenType::Addition),
+        TokenType::SubtractAssignment => Some(TokenType::Subtraction),
+        TokenType::MultiplyAssignment => Some(TokenType::Multiplication),
+        TokenType::DivideAssignment => Some(TokenType::Division),
+        _ => None,
+    }
+}
+
+/// The operation a builtin like `add` stands for, so it can be passed as a function like
+/// `c 0 add fold`
+pub fn named_operation(name: &str) -> Option<TokenType> {
+    match name {
+        "add" => Some(TokenType::Addition),
+        "sub" => Some(TokenType::Subtraction),
+        "mul" => Some(TokenType::Multiplication),
+        "div" => Some(TokenType::Division),
+        _ => None,
+    }
+}
+
+impl Expression {
+    pub fn from_token(token: Token) -> Self {
+        Expression::TokenExpression(InnerTokenExpression { token })
+    }
+
+    pub fn int(value: i64) -> Self {
+        Expression::from_token(Token {
+            token_type: TokenType::NumericIntLiteral,
+            value: format!("{}", value),
+        })
+    }
+
+    pub fn identifier(name: &str) -> Self {
+        Expression::from_token(Token {
+            token_type: TokenType::Identifier,
+            value: name.to_string(),
+        })
+    }
+
+    pub fn binary(expression_1: Expression, expression_2: Expression, op: TokenType) -> Self {
+        Expression::ExpExpOp(InnerExpExpOp {
+            expression_1: Box::new(expression_1),
+            expression_2: Box::new(expression_2),
+            operation: Box::new(Operation::TokenOperation(InnerTokenOperation {
+                token: operation_token(op),
+            })),
+        })
+    }
+
+    pub fn call(name: &str, arguments: Vec<Expression>) -> Self {
+        Expression::Call(InnerCall {
+            function: Token {
+                token_type: TokenType::Identifier,
+                value: name.to_string(),
+            },
+            arguments,
+        })
+    }
+
+    /// Replace every use of a name with another expression
+    pub fn substitute(&self, name: &str, replacement: &Expression) -> Expression {
+        match self {
+            Expression::TokenExpression(t)
+                if t.token.token_type == TokenType::Identifier && t.token.value == name =>
+            {
+                replacement.clone()
+            }
+            Expression::ExpExpOp(i) => Expression::ExpExpOp(InnerExpExpOp {
+                expression_1: Box::new(i.expression_1.substitute(name, replacement)),
+                expression_2: Box::new(i.expression_2.substitute(name, replacement)),
+                operation: i.operation.clone(),
+            }),
+            Expression::Call(c) => Expression::Call(InnerCall {
+                function: c.function.clone(),
+                arguments: c
+                    .arguments
+                    .iter()
+                    .map(|a| a.substitute(name, replacement))
+                    .collect(),
+            }),
+            _ => self.clone(),
+        }
+    }
+
+    /// The expression written back out in postfix, like `1 1 +`
+    pub fn source(&self) -> String {
+        match self {
+            Expression::TokenExpression(t) if t.token.token_type == TokenType::StringLiteral => {
+                format!("\"{}\"", t.token.value)
+            }
+            Expression::TokenExpression(t) => t.token.value.clone(),
+            Expression::ExpExpOp(i) => {
+                let Operation::TokenOperation(c) = i.operation.as_ref();
+                format!(
+                    "{} {} {}",
+                    i.expression_1.source(),
+                    i.expression_2.source(),
+                    c.token.value
+                )
+            }
+            Expression::Assignment(a) => format!(
+                "{} {} {} =",
+                a.expression_1.source(),
+                a.expression_2.source(),
+                a.expression_3.source()
+            ),
+            Expression::Call(c) => {
+                let mut parts: Vec<String> = c.arguments.iter().map(|a| a.source()).collect();
+                parts.push(c.function.value.clone());
+                parts.join(" ")
+            }
+            Expression::Function(f) => {
+                let parameters: Vec<String> = f
+                    .parameters
+                    .iter()
+                    .map(|p| format!("{} {}", p.name, p.type_token.value))
+                    .collect();
+
+                format!(
+                    "{} ({}) =: {}",
+                    f.name.value,
+                    parameters.join(", "),
+                    f.body.source()
+                )
+            }
+            Expression::Closure(c) if !c.parameters.is_empty() => {
+                closure_source(&c.parameters, &c.body)
+            }
+            Expression::Closure(c) => format!(": ({})", c.body.source()),
+            Expression::Sequence(s) => {
+                let parts: Vec<String> = s.expressions.iter().map(|e| e.source()).collect();
+                parts.join(" ")
+            }
+            Expression::If(i) => format!(
+                "{} {} {} if",
+                i.condition.source(),
+                i.then.source(),
+                i.otherwise.source()
+            ),
+            Expression::Match(m) => {
+                let arms: Vec<String> = m
+                    .arms
+                    .iter()
+                    .map(|a| format!("{}: {}", a.pattern, a.result.source()))
+                    .collect();
+
+                format!("{} match ({})", m.subject.source(), arms.join(", "))
+            }
+            Expression::For(f) => {
+                let variable = match &f.type_token {
+                    Some(type_token) => format!("{}: {}", f.variable.value, type_token.value),
+                    None => f.variable.value.clone(),
+                };
+
+                let iterable = match &f.end {
+                    Some(end) => format!("{}..{}", f.iterable.source(), end.source()),
+                    None => f.iterable.source(),
+                };
+
+                format!("for {} in {} {{ {} }}", variable, iterable, f.body.source())
+            }
+            Expression::While(w) => {
+                format!("while {} {{ {} }}", w.condition.source(), w.body.source())
+            }
+            Expression::CompoundAssignment(c) => {
+                let Operation::TokenOperation(op) = c.operation.as_ref();
+                format!("{} {} {}", c.name.value, c.value.source(), op.token.value)
+            }
+            Expression::Vector(v) => {
+                let rows: Vec<String> = v
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let elements: Vec<String> = row.iter().map(|e| e.source()).collect();
+                        elements.join(" ")
+                    })
+                    .collect();
+
+                format!("[{}]", rows.join("; "))
+            }
+            Expression::Tuple(t) => format!("({})", element_source(&t.elements)),
+            Expression::Set(s) => format!("{{{}}}", element_source(&s.elements)),
+            Expression::Break => "break".to_string(),
+            Expression::Continue => "continue".to_string(),
+            Expression::None => String::new(),
+        }
+    }
+
+    /// The operation token type if this is an `ExpExpOp`
+    pub fn operation_type(&self) -> Option<TokenType> {
+        match self {
+            Expression::ExpExpOp(i) => {
+                let Operation::TokenOperation(c) = i.operation.as_ref();
+                Some(c.token.token_type.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The elements of a tuple or set, which are split by commas when one of them is more than a
+/// single token, like `(1 2 +, 4)`
+fn element_source(elements: &[Expression]) -> String {
+    let sources: Vec<String> = elements.iter().map(Expression::source).collect();
+    match sources.iter().any(|s| s.contains(' ')) {
+        true => sources.join(", "),
+        false => sources.join(" "),
+    }
+}
+
+/// Whether a type keyword is used as a function, like `size` in `4 size`, rather than as the
+/// type in an assignment like `a size 4 =`
+fn is_type_call(tokens: &[Token], index: usize) -> bool {
+    let declares = index == 1
+        && tokens
+            .last()
+            .is_some_and(|t| t.token_type == TokenType::Assignment);
+
+    matches!(
+        tokens[index].token_type,
+        TokenType::TypeSizeKeyword
+            | TokenType::TypeBoolKeyword
+            | TokenType::TypeOptionKeyword
+            | TokenType::TypeTupleKeyword
+            | TokenType::TypeSetKeyword
+            | TokenType::TypeSampleKeyword
+            | TokenType::TypePopulationKeyword
+    ) && !declares
+}
+
+fn is_comparison(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Greater
+            | TokenType::Less
+            | TokenType::GreaterEqual
+            | TokenType::LessEqual
+            | TokenType::Equal
+            | TokenType::NotEqual
+    )
+}
+
+/// The number of expressions each builtin function takes from its left
+fn builtin_arity(name: &str) -> Option<usize> {
+    match name {
+        "unwrap" => Some(1),
+        "sin" | "cos" | "tan" | "exp" | "log" | "sqrt" => Some(1),
+        "abs" | "arg" | "conj" | "re" | "im" => Some(1),
+        "d" | "deriv" => Some(2),
+        "error" => Some(1),
+        "prec" => Some(2),
+        "and" | "or" => Some(2),
+        "not" => Some(1),
+        "exists" | "value" | "some" | "none" => Some(1),
+        "newton" => Some(2),
+        "integrate" | "root" => Some(3),
+        "if" => Some(3),
+        "match" => Some(1),
+        "for" | "while" | "break" | "continue" => Some(0),
+        "transpose" | "det" | "inverse" | "rank" | "trace" | "lu" | "qr" => Some(1),
+        "eig" | "eigvals" | "svd" | "norm" => Some(1),
+        "solve" | "lstsq" | "pnorm" => Some(2),
+        "union" | "intersection" | "difference" | "in" => Some(2),
+        "len" | "sort" => Some(1),
+        "at" => Some(2),
+        "map" | "filter" | "compose" => Some(2),
+        "fold" => Some(3),
+        "add" | "sub" | "mul" | "div" => Some(2),
+        "mean" | "median" | "mode" | "variance" | "stdev" | "min" | "max" | "range" => Some(1),
+        "skewness" | "kurtosis" => Some(1),
+        "quantile" | "covariance" | "correlation" => Some(2),
+        _ => None,
+    }
+}
+
+fn create_assignment(
+    arg1: &Expression,
+    arg2: &Expression,
+    arg3: &Expression,
+    operation: &Operation,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    let Operation::TokenOperation(op) = operation;
+    if op.token.token_type != TokenType::Assignment {
+        return Err(BasisError::new("Expected `=` for an assignment"));
+    }
+
+    let (Expression::TokenExpression(name), Expression::TokenExpression(type_expression)) =
+        (arg1, arg2)
+    else {
+        return Err(BasisError::new(
+            "Assignment expects a name and a type before the value",
+        ));
+    };
+
+    if name.token.token_type != TokenType::Identifier {
+        return Err(BasisError::new(&format!(
+            "Cannot assign to `{}`",
+            name.token.value
+        )));
+    }
+
+    if builtin_arity(&name.token.value).is_some() {
+        return Err(BasisError::new(&format!(
+            "Cannot assign to builtin `{}`",
+            name.token.value
+        )));
+    }
+
+    let value = cast_value(eval_in(arg3, env)?, &type_expression.token)?;
+    env.declare(&name.token.value, value.clone());
+
+    Ok(value)
+}
+
+/// Cast a value to the type named by a type keyword, like the `ratio` in `b ratio 3 =` or the
+/// `option<int>` in `5 option<int>`
+fn cast_value(value: Value, type_token: &Token) -> Result<Value, BasisError> {
+    match (value.without_estimate(), &type_token.token_type) {
+        (
+            Value::Number(number),
+            TokenType::TypeIntKeyword
+            | TokenType::TypeRatioKeyword
+            | TokenType::TypeDecKeyword
+            | TokenType::TypeRealKeyword
+            | TokenType::TypeComplexKeyword
+            | TokenType::TypeImaginaryKeyword,
+        ) => Ok(Value::Number(number.cast(&type_token.value)?)),
+        (value, TokenType::TypeSizeKeyword) => Ok(Value::Size(value.size()?)),
+        (value, TokenType::TypeBoolKeyword) => Ok(Value::Bool(value.truthy()?)),
+        (Value::Vector(vector), TokenType::TypeVectorKeyword) => {
+            match vector_inner(&type_token.value) {
+                Some(name) => Ok(Value::Vector(vector.cast(name)?)),
+                None => Ok(Value::Vector(vector)),
+            }
+        }
+        // Any container becomes a tuple, set, sample or population of its elements, like
+        // `[1 2 2] set`, and a `container[ratio]` keeps the kind of container but casts its
+        // elements
+        (
+            value,
+            TokenType::TypeTupleKeyword
+            | TokenType::TypeSetKeyword
+            | TokenType::TypeSampleKeyword
+            | TokenType::TypePopulationKeyword
+            | TokenType::TypeContainerKeyword,
+        ) => {
+            let container = match &type_token.token_type {
+                TokenType::TypeContainerKeyword if !Container::is_container(&value) => {
+                    return Ok(value)
+                }
+                TokenType::TypeContainerKeyword => Container::from_value(value)?,
+                TokenType::TypeSetKeyword => Container::Set(unique(value.elements()?)),
+                TokenType::TypeSampleKeyword => Container::sample(value.elements()?)?,
+                TokenType::TypePopulationKeyword => Container::population(value.elements()?)?,
+                _ => Container::Tuple(value.elements()?),
+            };
+
+            let container = match container_inner(&type_token.value) {
+                Some(name) => container.map(|e| cast_value(e, &type_from_name(name)))?,
+                None => container,
+            };
+
+            Ok(container.into_value())
+        }
+        (value, TokenType::TypeOptionKeyword) => {
+            let inner_type = type_token
+                .value
+                .strip_prefix("option<")
+                .and_then(|t| t.strip_suffix('>'));
+
+            match (value.into_option(), inner_type) {
+                (Value::Option(Some(inner)), Some(name)) => Ok(Value::Option(Some(Box::new(
+                    cast_value(*inner, &type_from_name(name))?,
+                )))),
+                (option, _) => Ok(option),
+            }
+        }
+        (Value::Number(number), TokenType::TypeNumberKeyword) => Ok(Value::Number(number)),
+
+        // Literals stay symbolic until they are unwrapped, so they pass for any number type
+        (
+            literal @ Value::Literal(_),
+            TokenType::TypeNumberKeyword
+            | TokenType::TypeIntKeyword
+            | TokenType::TypeRatioKeyword
+            | TokenType::TypeDecKeyword
+            | TokenType::TypeRealKeyword
+            | TokenType::TypeComplexKeyword
+            | TokenType::TypeImaginaryKeyword,
+        ) => Ok(literal),
+        (value, token_type) if is_type_keyword(token_type) => Err(BasisError::new(&format!(
+            "Cannot cast {} to {}",
+            value.type_name(),
+            type_token.value
+        ))),
+
+        // A name that is not a type keyword, like `float` in `foo float 39400 =`, is not checked
+        (value, _) => Ok(value),
+    }
+}
+
+fn is_type_keyword(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::TypeNumberKeyword
+            | TokenType::TypeIntKeyword
+            | TokenType::TypeRatioKeyword
+            | TokenType::TypeRealKeyword
+            | TokenType::TypeDecKeyword
+            | TokenType::TypeComplexKeyword
+            | TokenType::TypeImaginaryKeyword
+            | TokenType::TypeSizeKeyword
+            | TokenType::TypeBoolKeyword
+            | TokenType::TypeOptionKeyword
+            | TokenType::TypeVectorKeyword
+            | TokenType::TypeTupleKeyword
+            | TokenType::TypeSetKeyword
+            | TokenType::TypeSampleKeyword
+            | TokenType::TypePopulationKeyword
+            | TokenType::TypeContainerKeyword
+    )
+}
+
+/// The type token for a type name, including generic types like `option<int>` or `vector[int]`
+fn type_from_name(name: &str) -> Token {
+    match name.split_once(['<', '[']) {
+        Some((outer, _)) => Token {
+            token_type: Token::tokenize(outer.to_string()).token_type,
+            value: name.to_string(),
+        },
+        None => Token::tokenize(name.to_string()),
+    }
+}
+
+/// Join a generic type like `option < int >` or `vector [ int ]` into a single `option<int>` or
+/// `vector[int]` type token
+fn merge_generic_types(mut tokens: Vec<Token>) -> Vec<Token> {
+    let mut index = 0;
+
+    while index + 3 < tokens.len() {
+        let brackets = match tokens[index].token_type {
+            TokenType::TypeOptionKeyword => Some((TokenType::Less, TokenType::Greater)),
+            TokenType::TypeVectorKeyword
+            | TokenType::TypeTupleKeyword
+            | TokenType::TypeSetKeyword
+            | TokenType::TypeContainerKeyword => {
+                Some((TokenType::LeftBracket, TokenType::RightBracket))
+            }
+            _ => None,
+        };
+
+        let generic = brackets.is_some_and(|(open, close)| {
+            tokens[index + 1].token_type == open
+                && is_type_keyword(&tokens[index + 2].token_type)
+                && tokens[index + 3].token_type == close
+        });
+
+        if generic {
+            let inner: Vec<Token> = tokens.drain(index + 1..index + 4).collect();
+            tokens[index].value = format!(
+                "{}{}{}{}",
+                tokens[index].value, inner[0].value, inner[1].value, inner[2].value
+            );
+
+            // Step back in case this was the inner type of another generic
+            index = index.saturating_sub(2);
+        } else {
+            index += 1;
+        }
+    }
+
+    tokens
+}
+
+/// `1 2 <` compares two values. Numbers and literals with a numeric value are ordered, and any
+/// two values of the same type can be checked for equality.
+fn run_comparison(arg1: Value, arg2: Value, operation: &TokenType) -> Result<Value, BasisError> {
+    let (a, b) = match (arg1.without_estimate(), arg2.without_estimate()) {
+        // Literals like `pi` are compared by their value
+        (Value::Literal(a), Value::Literal(b)) if a == b => (Value::int(0), Value::int(0)),
+        (a @ Value::Literal(_), b) | (a, b @ Value::Literal(_)) => {
+            (Value::dec(a.as_dec()?), Value::dec(b.as_dec()?))
+        }
+        pair => pair,
+    };
+
+    let order = match (&a, &b) {
+        (Value::Number(x), Value::Number(y)) => x.clone().compare(y.clone()),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        (x, y) if x.type_name() == y.type_name() && x.same(y) => Some(Ordering::Equal),
+        (x, y) if x.type_name() == y.type_name() => None,
+        (x, y) => {
+            return Err(BasisError::new(&format!(
+                "Cannot compare {} and {}",
+                x.type_name(),
+                y.type_name()
+            )))
+        }
+    };
+
+    let out = match (operation, order) {
+        (TokenType::Equal, order) => order == Some(Ordering::Equal),
+        (TokenType::NotEqual, order) => order != Some(Ordering::Equal),
+        (TokenType::Less, Some(order)) => order == Ordering::Less,
+        (TokenType::Greater, Some(order)) => order == Ordering::Greater,
+        (TokenType::LessEqual, Some(order)) => order != Ordering::Greater,
+        (TokenType::GreaterEqual, Some(order)) => order != Ordering::Less,
+        _ => {
+            return Err(BasisError::new(&format!(
+                "Cannot order {} and {}",
+                a.type_name(),
+                b.type_name()
+            )))
+        }
+    };
+
+    Ok(Value::Bool(out))
+}
+
+fn run_operation(arg1: Value, arg2: Value, operation: &Operation) -> Result<Value, BasisError> {
+    let Operation::TokenOperation(c) = operation;
+
+    if is_comparison(&c.token.token_type) {
+        return run_comparison(arg1, arg2, &c.token.token_type);
+    }
+
+    match (arg1.without_estimate(), arg2.without_estimate()) {
+        // ---
+        // TWO NUMBERS
+        (Value::Number(a), Value::Number(b)) => {
+            Ok(Value::Number(a.operate(b, &c.token.token_type)?))
+        }
+
+        // ---
+        // ANY LITERAL
+        // Literals stay symbolic, so the operation is kept as an expression and simplified
+        (a @ Value::Literal(_), b @ (Value::Literal(_) | Value::Number(_)))
+        | (a @ Value::Number(_), b @ Value::Literal(_)) => {
+            // A literal that simplifies to zero is already a number, so this is every exact zero
+            if c.token.token_type == TokenType::Division
+                && matches!(&b, Value::Number(n) if n.is_zero())
+            {
+                return Err(BasisError::new("Division by zero"));
+            }
+
+            let expression = Expression::ExpExpOp(InnerExpExpOp {
+                expression_1: Box::new(a.to_expression()),
+                expression_2: Box::new(b.to_expression()),
+                operation: Box::new(operation.clone()),
+            });
+
+            Ok(Value::from_expression(simplify(&expression)))
+        }
+
+        // ---
+        // VECTORS
+        // `+` and `-` go element by element, `*` is the matrix product, and a number is spread
+        // across every element
+        (Value::Vector(a), Value::Vector(b))
+            if matches!(
+                c.token.token_type,
+                TokenType::Addition | TokenType::Subtraction
+            ) =>
+        {
+            Ok(Value::Vector(a.broadcast(&b, &c.token.token_type)?))
+        }
+        (Value::Vector(a), Value::Vector(b)) if c.token.token_type == TokenType::Multiplication => {
+            Ok(Value::Vector(a.multiply(&b)?))
+        }
+        (Value::Vector(a), Value::Number(b)) if c.token.token_type != TokenType::Power => {
+            Ok(Value::Vector(a.scalar(&b, &c.token.token_type, false)?))
+        }
+        (Value::Number(a), Value::Vector(b)) if c.token.token_type != TokenType::Power => {
+            Ok(Value::Vector(b.scalar(&a, &c.token.token_type, true)?))
+        }
+
+        (a, b) => Err(BasisError::new(&format!(
+            "Cannot apply `{}` to {} and {}",
+            c.token.value,
+            a.type_name(),
+            b.type_name()
+        ))),
+    }
+}
+
+/// Apply a math function like `sin`, keeping exact and symbolic results like `0 cos` or `x sin`
+/// as expressions
+fn run_function(name: &str, argument: Value) -> Result<Value, BasisError> {
+    let exp = Expression::call(name, vec![argument.to_expression()]);
+
+    match argument {
+        Value::Number(number @ (Number::Int(_) | Number::Ratio(_))) => {
+            match Value::from_expression(simplify(&exp)) {
+                exact @ Value::Number(_) => Ok(exact),
+                _ => Ok(Value::Number(number.apply(name)?)),
+            }
+        }
+        Value::Number(number) => Ok(Value::Number(number.apply(name)?)),
+        _ => Ok(Value::from_expression(simplify(&exp))),
+    }
+}
+
+/// `x 2 ^ x d` differentiates `x 2 ^` with respect to the variable `x`
+fn run_derivative(
+    exp: &Expression,
+    variable: &Expression,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    let name = match variable {
+        Expression::TokenExpression(t) if t.token.token_type == TokenType::Identifier => {
+            t.token.value.clone()
+        }
+        _ => {
+            return Err(BasisError::new(
+                "Expected a variable name to differentiate by",
+            ))
+        }
+    };
+
+    // The variable is a symbol while the expression is built, even when it shadows a value
+    env.push_scope();
+    env.declare(&name, Value::Literal(Expression::identifier(&name)));
+    let value = eval_in(exp, env);
+    env.pop_scope();
+
+    match value? {
+        Value::Literal(e) => Ok(Value::from_expression(derive(&e, &name)?)),
+
+        // `f x d` is the function that gives the derivative of `f` by its parameter `x`
+        Value::Function(function) => {
+            if !function.parameters.iter().any(|p| p.name == name) {
+                return Err(BasisError::new(&format!(
+                    "`{}` has no parameter `{}`",
+                    function.name, name
+                )));
+            }
+
+            let mut derivative = Function {
+                name: format!("{}'", function.name),
+                body: derive(&function.body, &name)?,
+                return_type: None,
+                derived: true,
+                ..function
+            };
+
+            derivative.return_type = return_type(&derivative, env).ok();
+            Ok(Value::Function(derivative))
+        }
+
+        // A number does not change with the variable
+        Value::Number(_) | Value::Estimate { .. } => Ok(Value::int(0)),
+        other => Err(BasisError::new(&format!(
+            "Cannot differentiate {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Numerically solve or integrate a literal like `x 2 ^ 0 1 integrate`
+fn run_solver(
+    name: &str,
+    arguments: &[Expression],
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    // The names that are not defined are symbols while the literal is built, like `x` in
+    // `x 2 ^`, though only one of them can be the variable
+    let mut variables = Vec::<String>::new();
+    free_variables(&arguments[0], &mut variables);
+    variables.retain(|v| env.lookup(v).is_none());
+
+    env.push_scope();
+    for variable in &variables {
+        env.declare(variable, Value::Literal(Expression::identifier(variable)));
+    }
+    let function = eval_in(&arguments[0], env);
+    env.pop_scope();
+
+    let function = match function? {
+        Value::Literal(body) => LiteralFunction::new(body)?,
+        Value::Function(function) if function.arity() == 1 => LiteralFunction {
+            variable: function.parameters[0].name.clone(),
+            body: function.body,
+        },
+        other => {
+            return Err(BasisError::new(&format!(
+                "`{}` expects a function but got {}",
+                name,
+                other.type_name()
+            )))
+        }
+    };
+
+    let mut bounds = Vec::<f64>::new();
+    for argument in &arguments[1..] {
+        bounds.push(eval_in(argument, env)?.as_dec()?);
+    }
+
+    let (value, error) = match name {
+        "integrate" => integrate(&function, bounds[0], bounds[1])?,
+        "root" => root(&function, bounds[0], bounds[1])?,
+        _ => newton(&function, bounds[0])?,
+    };
+
+    Ok(Value::Estimate { value, error })
+}
+
+/// Run one of the linear algebra functions on a vector, like `[1 2; 3 4] det`
+fn run_matrix(name: &str, mut arguments: Vec<Value>) -> Result<Value, BasisError> {
+    // The `p` of `[3 4] 1 pnorm` is the only argument that is not a vector
+    let p = match name {
+        "pnorm" => arguments.remove(1).as_dec()?,
+        _ => 2.0,
+    };
+
+    let mut vectors = Vec::<Vector>::new();
+    for argument in arguments.drain(..) {
+        match argument {
+            Value::Vector(vector) => vectors.push(vector),
+            other => {
+                return Err(BasisError::new(&format!(
+                    "`{}` expects a vector but got {}",
+                    name,
+                    other.type_name()
+                )))
+            }
+        }
+    }
+
+    let a = &vectors[0];
+    match name {
+        "transpose" => Ok(Value::Vector(a.transpose())),
+        "det" => Ok(Value::Number(a.determinant()?)),
+        "inverse" => Ok(Value::Vector(a.inverse()?)),
+        "rank" => Ok(Value::int(a.rank()? as i64)),
+        "trace" => Ok(Value::Number(a.trace()?)),
+        "solve" => Ok(Value::Vector(a.solve(&vectors[1])?)),
+        "lu" => {
+            let (p, l, u) = a.lu()?;
+            Ok(Value::Tuple(vec![
+                Value::Vector(p),
+                Value::Vector(l),
+                Value::Vector(u),
+            ]))
+        }
+        "qr" => {
+            let (q, r) = a.qr()?;
+            Ok(Value::Tuple(vec![Value::Vector(q), Value::Vector(r)]))
+        }
+        "eig" => {
+            let (values, vectors) = a.eigen()?;
+            Ok(Value::Tuple(vec![
+                Value::Vector(values),
+                Value::Vector(vectors),
+            ]))
+        }
+        "eigvals" => Ok(Value::Vector(a.eigen()?.0)),
+        "svd" => {
+            let (u, s, v) = a.svd()?;
+            Ok(Value::Tuple(vec![
+                Value::Vector(u),
+                Value::Vector(s),
+                Value::Vector(v),
+            ]))
+        }
+        "lstsq" => Ok(Value::Vector(a.least_squares(&vectors[1])?)),
+        "norm" => Ok(Value::dec(a.norm())),
+        "pnorm" => Ok(Value::dec(a.p_norm(p)?)),
+        _ => Err(BasisError::new(&format!("Unknown function `{}`", name))),
+    }
+}
+
+/// `(3 1 2) sort` and the other functions that work on any container
+fn run_container(name: &str, mut arguments: Vec<Value>) -> Result<Value, BasisError> {
+    let container = match arguments.remove(0) {
+        value if Container::is_container(&value) => Container::from_value(value)?,
+        other => {
+            return Err(BasisError::new(&format!(
+                "`{}` expects a container but got {}",
+                name,
+                other.type_name()
+            )))
+        }
+    };
+
+    match name {
+        "len" => Ok(Value::int(container.len() as i64)),
+        "sort" => Ok(container.sort()?.into_value()),
+        _ => match arguments.remove(0) {
+            Value::Number(Number::Int(index)) => container.get(index),
+            other => Err(BasisError::new(&format!(
+                "The index must be an int but got {}",
+                other.type_name()
+            ))),
+        },
+    }
+}
+
+/// `(1 2 3) mean` and the other statistics of the numbers in a container
+fn run_statistic(name: &str, mut arguments: Vec<Value>) -> Result<Value, BasisError> {
+    // The fraction of a quantile comes after the container
+    let q = match name {
+        "quantile" => arguments.pop(),
+        _ => None,
+    };
+
+    let mut data = Vec::<Data>::new();
+    let mut kinds = Vec::<Option<bool>>::new();
+    for argument in arguments {
+        let container = Container::from_value(argument)?;
+        kinds.push(match container {
+            Container::Sample(_) => Some(true),
+            Container::Population(_) => Some(false),
+            _ => None,
+        });
+
+        data.push(Data::from_container(&container)?);
+    }
+
+    // A plain container paired with a sample or population is taken to be the same kind, so the
+    // order of the two does not matter
+    if let [a, b] = kinds[..] {
+        if a.is_some() && b.is_some() && a != b {
+            return Err(BasisError::new(&format!(
+                "Cannot take the {} of a sample and a population",
+                name
+            )));
+        }
+
+        let sample = a.or(b).unwrap_or(false);
+        for d in data.iter_mut() {
+            d.sample = sample;
+        }
+    }
+
+    let d = &data[0];
+    let out = match name {
+        "mean" => d.mean()?,
+        "median" => d.median()?,
+        "mode" => d.mode()?,
+        "variance" => d.variance()?,
+        "stdev" => d.stdev()?,
+        "min" => d.min()?,
+        "max" => d.max()?,
+        "range" => d.range()?,
+        "skewness" => d.skewness()?,
+        "kurtosis" => d.kurtosis()?,
+        "covariance" => d.covariance(&data[1])?,
+        "correlation" => d.correlation(&data[1])?,
+        _ => match q.unwrap() {
+            Value::Number(q) => d.quantile(&q)?,
+            other => {
+                return Err(BasisError::new(&format!(
+                    "A quantile must be a number but got {}",
+                    other.type_name()
+                )))
+            }
+        },
+    };
+
+    Ok(Value::Number(out))
+}
+
+/// `{1 2} {2 3} union` and the other operations between two sets
+fn run_set(name: &str, arguments: Vec<Value>) -> Result<Value, BasisError> {
+    let mut sets = Vec::<Vec<Value>>::new();
+    for argument in arguments {
+        match argument {
+            Value::Set(values) => sets.push(values),
+            other => {
+                return Err(BasisError::new(&format!(
+                    "`{}` expects a set but got {}",
+                    name,
+                    other.type_name()
+                )))
+            }
+        }
+    }
+
+    let b = sets.pop().unwrap();
+    let a = sets.pop().unwrap();
+    let in_b = |value: &Value| b.iter().any(|v| v.same(value));
+
+    let out = match name {
+        "union" => Container::Set(unique(a.into_iter().chain(b.clone()).collect())),
+        "intersection" => Container::Set(a).filter(|v| Ok(in_b(v)))?,
+        _ => Container::Set(a).filter(|v| Ok(!in_b(v)))?,
+    };
+
+    Ok(out.into_value())
+}
+
+/// `0.1 30 prec` gives a real with 30 significant digits
+fn run_precision(value: Value, digits: Value) -> Result<Value, BasisError> {
+    let precision = match digits {
+        Value::Number(Number::Int(n)) if n > i64::from(MAX_PRECISION) => {
+            return Err(BasisError::new(&format!(
+                "The precision is too large, since a real keeps at most {} digits",
+                MAX_PRECISION
+            )))
+        }
+        Value::Number(Number::Int(n)) if n > 0 => n as u32,
+        other => {
+            return Err(BasisError::new(&format!(
+                "The precision must be a positive int but got {}",
+                other
+            )))
+        }
+    };
+
+    let number = match value.without_estimate() {
+        Value::Number(number) => number,
+        other => Number::Dec(other.as_dec()?),
+    };
+
+    match number.to_real(precision) {
+        real @ Number::Real(_) => Ok(Value::Number(real)),
+        other => Err(BasisError::new(&format!(
+            "Cannot give {} a precision",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Define a function like `f (x int) =: x 1 +` and give it back as a value
+fn define_function(definition: &InnerFunction, env: &mut Environment) -> Result<Value, BasisError> {
+    let name = &definition.name.value;
+    if builtin_arity(name).is_some() {
+        return Err(BasisError::new(&format!(
+            "Cannot redefine the builtin function `{}`",
+            name
+        )));
+    }
+
+    let mut function = Function {
+        name: name.clone(),
+        parameters: definition.parameters.clone(),
+        body: definition.body.as_ref().clone(),
+        return_type: None,
+        derived: false,
+    };
+
+    // Bodies that have no type are flagged here rather than when the function is called
+    function.return_type = Some(return_type(&function, env)?);
+
+    let function = Value::Function(function);
+    env.declare(name, function.clone());
+    Ok(function)
+}
+
+/// Call a user function with its arguments cast to the parameter types
+fn call_function(
+    function: &Function,
+    mut arguments: Vec<Value>,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    // A call to itself at the end of the body runs in the same loop rather than nesting, so
+    // loops written like `n acc fact` do not grow the stack
+    loop {
+        if env.depth() >= env.depth_limit() {
+            return Err(BasisError::new(&format!(
+                "`{}` went past the recursion limit of {} calls",
+                function.name,
+                env.depth_limit()
+            )));
+        }
+
+        let (bindings, types) = bind_arguments(function, arguments)?;
+
+        env.push_frame(bindings, types);
+        let out = eval_tail(&function.body, function, env);
+        env.pop_frame();
+
+        match out? {
+            Tail::Value(value) => return Ok(value),
+            Tail::Call(next) => arguments = next,
+        }
+    }
+}
+
+/// The result of a function body, which is either a value or the arguments of a call to the
+/// same function in tail position
+enum Tail {
+    Value(Value),
+    Call(Vec<Value>),
+}
+
+/// Evaluate a function body, giving back the arguments of a tail call to the function itself
+/// instead of making the call
+fn eval_tail(
+    exp: &Expression,
+    function: &Function,
+    env: &mut Environment,
+) -> Result<Tail, BasisError> {
+    match exp {
+        Expression::Sequence(s) => {
+            let Some((last, statements)) = s.expressions.split_last() else {
+                return Err(BasisError::new("Empty expression"));
+            };
+
+            for statement in statements {
+                eval_in(statement, env)?;
+            }
+
+            eval_tail(last, function, env)
+        }
+
+        Expression::Call(c)
+            if c.function.token_type == TokenType::Identifier
+                && c.function.value == function.name
+                && c.arguments.len() == function.arity()
+                && matches!(env.lookup(&function.name), Some(Value::Function(f)) if f.name == function.name) =>
+        {
+            let mut arguments = Vec::<Value>::new();
+            for argument in &c.arguments {
+                arguments.push(eval_in(argument, env)?);
+            }
+
+            Ok(Tail::Call(arguments))
+        }
+
+        // The chosen branch of a conditional is also in tail position
+        Expression::If(_) | Expression::Match(_) => {
+            let Some((branch, binding)) = choose_branch(exp, env)? else {
+                return Err(BasisError::new("Expected a conditional"));
+            };
+
+            let body = match branch {
+                Expression::Closure(c) if c.parameters.is_empty() => c.body.as_ref(),
+                other => other,
+            };
+
+            env.push_scope();
+            if let Some((name, value)) = binding {
+                env.declare(&name, value);
+            }
+
+            let out = eval_tail(body, function, env);
+            env.pop_scope();
+
+            out
+        }
+
+        _ => Ok(Tail::Value(eval_in(exp, env)?)),
+    }
+}
+
+/// A branch of an `if` or `match`, along with a name the pattern gives to part of the value,
+/// like `v` in `some v`
+type Branch<'a> = (&'a Expression, Option<(String, Value)>);
+
+/// The branch of an `if` or `match` to evaluate
+fn choose_branch<'a>(
+    exp: &'a Expression,
+    env: &mut Environment,
+) -> Result<Option<Branch<'a>>, BasisError> {
+    match exp {
+        Expression::If(i) => {
+            let branch = match eval_in(&i.condition, env)?.truthy()? {
+                true => i.then.as_ref(),
+                false => i.otherwise.as_ref(),
+            };
+
+            Ok(Some((branch, None)))
+        }
+        Expression::Match(m) => {
+            let subject = eval_in(&m.subject, env)?;
+
+            match m.arms.iter().find(|arm| arm.pattern.matches(&subject)) {
+                Some(arm) => Ok(Some((&arm.result, arm.pattern.binding(&subject)))),
+                None => Err(BasisError::new(&format!("No pattern matches {}", subject))),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Evaluate the chosen branch of a conditional in its own scope, where a closure like the
+/// `: (0 x -)` in `x 0 < : (0 x -) : (x) if` is unwrapped
+fn eval_branch(
+    branch: &Expression,
+    binding: Option<(String, Value)>,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    let body = match branch {
+        Expression::Closure(c) if c.parameters.is_empty() => c.body.as_ref(),
+        other => other,
+    };
+
+    env.push_scope();
+    if let Some((name, value)) = binding {
+        env.declare(&name, value);
+    }
+
+    let out = eval_in(body, env);
+    env.pop_scope();
+
+    out
+}
+
+/// The arguments of a call by parameter name, and the types its generics are bound to
+type Bindings = (HashMap<String, Value>, HashMap<String, String>);
+
+/// Cast each argument to the type of its parameter and bind any generics to a type
+fn bind_arguments(function: &Function, arguments: Vec<Value>) -> Result<Bindings, BasisError> {
+    let mut bindings = HashMap::<String, Value>::new();
+    let mut bound = HashMap::<String, Number>::new();
+
+    for (parameter, argument) in function.parameters.iter().zip(arguments) {
+        let value = cast_value(argument, &parameter.type_token)?;
+
+        // Literals stay symbolic, so `pi f` works for a number parameter
+        let expected = &parameter.type_token.value;
+        if !matches!(value, Value::Literal(_)) && !type_matches(&value, expected) {
+            return Err(BasisError::new(&format!(
+                "`{}` expects {} for `{}` but got {}",
+                function.name,
+                expected,
+                parameter.name,
+                value.type_name()
+            )));
+        }
+
+        // A generic is bound to the type that all of its arguments promote to
+        if let (Some(constraint), Value::Number(number)) = (generic_constraint(expected), &value) {
+            let common = match bound.remove(constraint) {
+                Some(other) => {
+                    let type_name = other.common_type(number);
+                    other.cast(type_name)?
+                }
+                None => number.clone(),
+            };
+
+            bound.insert(constraint.to_string(), common);
+        }
+
+        bindings.insert(parameter.name.clone(), value);
+    }
+
+    let types: HashMap<String, String> = bound
+        .iter()
+        .map(|(constraint, number)| (constraint.clone(), number.type_name().to_string()))
+        .collect();
+
+    for parameter in &function.parameters {
+        let constraint = generic_constraint(&parameter.type_token.value);
+        let type_name = constraint.and_then(|c| types.get(c));
+
+        if let (Some(type_name), Some(Value::Number(number))) =
+            (type_name, bindings.get_mut(&parameter.name))
+        {
+            *number = number.clone().cast(type_name)?;
+        }
+    }
+
+    Ok((bindings, types))
+}
+
+/// Whether a value has the named type, where `option<int>` or `vector[int]` only checks the outer type and any
+/// number has the abstract `number` type
+fn type_matches(value: &Value, type_name: &str) -> bool {
+    let name = generic_constraint(type_name).unwrap_or(type_name);
+    let outer = name.split(['<', '[']).next().unwrap_or(name);
+
+    match outer {
+        "number" => matches!(value, Value::Number(_)),
+        "container" => Container::is_container(value),
+        _ => value.type_name() == outer,
+    }
+}
+
+/// The element type of a vector type, like `int` for `vector[int]`
+pub fn vector_inner(type_name: &str) -> Option<&str> {
+    type_name
+        .strip_prefix("vector[")
+        .and_then(|t| t.strip_suffix(']'))
+}
+
+/// The type of the elements of a container type, like `int` for `set[int]`
+pub fn container_inner(type_name: &str) -> Option<&str> {
+    type_name
+        .split_once('[')
+        .and_then(|(_, inner)| inner.strip_suffix(']'))
+}
+
+/// The abstract type that constrains a generic parameter, like `number` for `x <number>`
+pub fn generic_constraint(type_name: &str) -> Option<&str> {
+    type_name
+        .strip_prefix('<')
+        .and_then(|t| t.strip_suffix('>'))
+}
+
+/// Evaluate the body of a closure in a new scope, so its declarations stay inside it, with
+/// its parameters bound to the arguments
+fn run_closure(
+    closure: &Closure,
+    arguments: Vec<Value>,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    if arguments.len() != closure.parameters.len() {
+        return Err(BasisError::new(&format!(
+            "The closure `{}` takes {} arguments but got {}",
+            closure,
+            closure.parameters.len(),
+            arguments.len()
+        )));
+    }
+
+    // The call the closure was made in is still running, so it sees the variables as they are,
+    // other than ones from a block that is already done
+    let same_frame = closure.frame == env.frame_id();
+    match same_frame {
+        true => env.push_scope(),
+        false => env.push_frame(closure.captured.clone(), HashMap::new()),
+    }
+
+    if same_frame {
+        for (name, value) in &closure.captured {
+            if env.lookup(name).is_none() {
+                env.declare(name, value.clone());
+            }
+        }
+    }
+
+    env.push_scope();
+    for (name, argument) in closure.parameters.iter().zip(arguments) {
+        env.declare(name, argument);
+    }
+
+    let out = eval_in(&closure.body, env);
+    match same_frame {
+        true => {
+            env.pop_scope();
+            env.pop_scope();
+        }
+        false => env.pop_frame(),
+    }
+
+    out
+}
+
+/// Call a function or a closure that was passed as a value, like the `f` in `[1 2 3] f map`
+fn call_value(
+    name: &str,
+    function: &Value,
+    arguments: Vec<Value>,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    match function {
+        Value::Function(function) => {
+            if function.arity() != arguments.len() {
+                return Err(BasisError::new(&format!(
+                    "`{}` needs a function of {} arguments but `{}` takes {}",
+                    name,
+                    arguments.len(),
+                    function.name,
+                    function.arity()
+                )));
+            }
+
+            call_function(function, arguments, env)
+        }
+        Value::Closure(closure) => run_closure(closure, arguments, env),
+        other => Err(BasisError::new(&format!(
+            "`{}` expects a function but got {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+/// The number of arguments a function value takes
+fn value_arity(name: &str, function: &Value) -> Result<usize, BasisError> {
+    match function {
+        Value::Function(function) => Ok(function.arity()),
+        Value::Closure(closure) => Ok(closure.parameters.len()),
+        other => Err(BasisError::new(&format!(
+            "`{}` expects a function but got {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+/// `[1 2 3] f map`, `c pred filter` and `c 0 add fold`, which call a function on each value
+/// of a container
+fn run_higher_order(
+    name: &str,
+    mut arguments: Vec<Value>,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    let function = arguments.pop().unwrap();
+    let container = match arguments.remove(0) {
+        value if Container::is_container(&value) => Container::from_value(value)?,
+        other => {
+            return Err(BasisError::new(&format!(
+                "`{}` expects a container but got {}",
+                name,
+                other.type_name()
+            )))
+        }
+    };
+
+    match name {
+        "map" => Ok(container
+            .map(|v| call_value(name, &function, vec![v], env))?
+            .into_value()),
+        "filter" => Ok(container
+            .filter(|v| call_value(name, &function, vec![v.clone()], env)?.truthy())?
+            .into_value()),
+        _ => container.fold(arguments.remove(0), |total, v| {
+            call_value(name, &function, vec![total, v], env)
+        }),
+    }
+}
+
+/// `f g compose` is the function that gives `x f g` for `x`, so it runs `f` then `g`
+fn run_compose(mut arguments: Vec<Value>, env: &Environment) -> Result<Value, BasisError> {
+    let second = arguments.pop().unwrap();
+    let first = arguments.pop().unwrap();
+
+    let parameters = placeholders(value_arity("compose", &first)?);
+    if value_arity("compose", &second)? != 1 {
+        return Err(BasisError::new(
+            "`compose` needs the second function to take one argument",
+        ));
+    }
+
+    // A user function is called by its name, and anything else by a name that cannot clash
+    let name = |value: &Value, fallback: &str| match value {
+        Value::Function(function) => function.name.clone(),
+        _ => fallback.to_string(),
+    };
+
+    let (first_name, second_name) = (name(&first, "_f"), name(&second, "_g"));
+    let inner = parameters
+        .iter()
+        .map(|p| Expression::identifier(p))
+        .collect();
+    let body = Expression::call(&second_name, vec![Expression::call(&first_name, inner)]);
+
+    // Shown the way it was written, where a composition inside another is grouped
+    let written = |value: &Value| match value {
+        Value::Function(function) => function.name.clone(),
+        Value::Closure(closure) if closure.source.is_some() => format!("({})", closure),
+        Value::Closure(closure) => closure.to_string(),
+        value => value.to_string(),
+    };
+    let source = format!("{} {} compose", written(&first), written(&second));
+
+    Ok(Value::Closure(Closure {
+        parameters,
+        body,
+        captured: HashMap::from([(first_name, first), (second_name, second)]),
+        frame: env.frame_id(),
+        source: Some(source),
+    }))
+}
+
+/// Run the body of a loop once in its own scope, giving back whether the loop carries on
+fn run_body(
+    body: &Expression,
+    binding: Option<(&str, Value)>,
+    env: &mut Environment,
+) -> Result<bool, BasisError> {
+    env.push_scope();
+    if let Some((name, value)) = binding {
+        env.declare(name, value);
+    }
+
+    let out = eval_in(body, env);
+    env.pop_scope();
+    out?;
+
+    Ok(env.take_jump() != Some(Jump::Break))
+}
+
+/// The ints from the start of a range up to but not including the end, like `0..3`
+fn range_elements(start: Value, end: Value) -> Result<Vec<Value>, BasisError> {
+    match (start, end) {
+        (Value::Number(Number::Int(start)), Value::Number(Number::Int(end))) => {
+            Ok((start..end).map(Value::int).collect())
+        }
+        (start, end) => Err(BasisError::new(&format!(
+            "A range needs int bounds but got {} and {}",
+            start.type_name(),
+            end.type_name()
+        ))),
+    }
+}
+
+fn run_for(f: &InnerFor, env: &mut Environment) -> Result<Value, BasisError> {
+    let iterable = eval_in(&f.iterable, env)?;
+    let elements = match &f.end {
+        Some(end) => range_elements(iterable, eval_in(end, env)?)?,
+        None => iterable.elements()?,
+    };
+
+    let mut elements = elements.into_iter();
+
+    env.push_loop();
+    let out = loop {
+        let Some(element) = elements.next() else {
+            break Ok(Value::Nil);
+        };
+
+        let value = match &f.type_token {
+            Some(type_token) => cast_value(element, type_token),
+            None => Ok(element),
+        };
+
+        match value.and_then(|v| run_body(&f.body, Some((&f.variable.value, v)), env)) {
+            Ok(true) => {}
+            Ok(false) => break Ok(Value::Nil),
+            Err(error) => break Err(error),
+        }
+    };
+    env.pop_loop();
+
+    out
+}
+
+fn run_while(w: &InnerWhile, env: &mut Environment) -> Result<Value, BasisError> {
+    env.push_loop();
+    let out = loop {
+        match eval_in(&w.condition, env).and_then(|condition| condition.truthy()) {
+            Ok(true) => {}
+            Ok(false) => break Ok(Value::Nil),
+            Err(error) => break Err(error),
+        }
+
+        match run_body(&w.body, None, env) {
+            Ok(true) => {}
+            Ok(false) => break Ok(Value::Nil),
+            Err(error) => break Err(error),
+        }
+    };
+    env.pop_loop();
+
+    out
+}
+
+/// Apply an operation to a variable that is already declared, like `sum x +=`, where the
+/// result has the type the operation gives
+fn run_compound_assignment(
+    c: &InnerCompoundAssignment,
+    env: &mut Environment,
+) -> Result<Value, BasisError> {
+    let Operation::TokenOperation(op) = c.operation.as_ref();
+    let Some(operation) = compound_operation(&op.token.token_type) else {
+        return Err(BasisError::new(&format!(
+            "Unknown assignment `{}`",
+            op.token.value
+        )));
+    };
+
+    let Some(current) = env.lookup(&c.name.value).cloned() else {
+        return Err(BasisError::new(&format!(
+            "`{}` has to be declared before `{}`",
+            c.name.value, op.token.value
+        )));
+    };
+
+    let value = eval_in(&c.value, env)?;
+    let out = run_operation(
+        current,
+        value,
+        &Operation::TokenOperation(InnerTokenOperation {
+            token: operation_token(operation),
+        }),
+    )?;
+
+    env.assign(&c.name.value, out.clone());
+    Ok(out)
+}
+
+/// Start a `break` or `continue`, which the statements around it pass up to the loop
+fn run_jump(jump: Jump, env: &mut Environment) -> Result<Value, BasisError> {
+    if !env.in_loop() {
+        let name = match jump {
+            Jump::Break => "break",
+            Jump::Continue => "continue",
+        };
+
+        return Err(BasisError::new(&format!(
+            "`{}` can only be used in a loop",
+            name
+        )));
+    }
+
+    env.jump(jump);
+    Ok(Value::Nil)
+}
+
+fn run_call(call: &InnerCall, env: &mut Environment) -> Result<Value, BasisError> {
+    let name = call.function.value.as_str();
+
+    // The variable is a name, so it is not evaluated
+    if call.function.token_type == TokenType::Identifier && matches!(name, "d" | "deriv") {
+        return run_derivative(&call.arguments[0], &call.arguments[1], env);
+    }
+
+    // The free variable of a literal to solve is a name too
+    if call.function.token_type == TokenType::Identifier
+        && matches!(name, "integrate" | "root" | "newton")
+    {
+        return run_solver(name, &call.arguments, env);
+    }
+
+    // `and` and `or` only evaluate the right side when it is needed
+    if call.function.token_type == TokenType::Identifier && matches!(name, "and" | "or") {
+        let left = eval_in(&call.arguments[0], env)?.truthy()?;
+        if left == (name == "or") {
+            return Ok(Value::Bool(left));
+        }
+
+        return Ok(Value::Bool(eval_in(&call.arguments[1], env)?.truthy()?));
+    }
+
+    let mut arguments = Vec::<Value>::new();
+    for argument in &call.arguments {
+        arguments.push(eval_in(argument, env)?);
+    }
+
+    match (&call.function.token_type, name) {
+        (TokenType::Dot, _) => arguments.remove(0).to_dec(),
+        (TokenType::Identifier, "unwrap") => match arguments.remove(0) {
+            Value::Closure(closure) => run_closure(&closure, Vec::new(), env),
+            other => other.unwrap(),
+        },
+        (TokenType::Identifier, "not") => Ok(Value::Bool(!arguments.remove(0).truthy()?)),
+        (TokenType::Identifier, "exists" | "some") => Ok(Value::Bool(arguments[0].exists())),
+        (TokenType::Identifier, "none") => Ok(Value::Bool(!arguments[0].exists())),
+        (TokenType::Identifier, "value") => Ok(arguments.remove(0).option_value()),
+        (
+            TokenType::TypeBoolKeyword
+            | TokenType::TypeOptionKeyword
+            | TokenType::TypeTupleKeyword
+            | TokenType::TypeSetKeyword
+            | TokenType::TypeSampleKeyword
+            | TokenType::TypePopulationKeyword,
+            _,
+        ) => cast_value(arguments.remove(0), &call.function),
+        (TokenType::TypeSizeKeyword, _) => {
+            let mut dimensions = Vec::<usize>::new();
+            for argument in &arguments {
+                dimensions.extend(argument.size()?.dimensions);
+            }
+
+            Ok(Value::Size(Size::new(dimensions)))
+        }
+        (
+            TokenType::Identifier,
+            "sin" | "cos" | "tan" | "exp" | "log" | "sqrt" | "abs" | "arg" | "conj" | "re" | "im",
+        ) => run_function(name, arguments.remove(0)),
+        (TokenType::Identifier, "prec") => {
+            let digits = arguments.remove(1);
+            run_precision(arguments.remove(0), digits)
+        }
+        (
+            TokenType::Identifier,
+            "transpose" | "det" | "inverse" | "rank" | "trace" | "solve" | "lu" | "qr" | "eig"
+            | "eigvals" | "svd" | "lstsq" | "norm" | "pnorm",
+        ) => run_matrix(name, arguments),
+        (TokenType::Identifier, "union" | "intersection" | "difference") => {
+            run_set(name, arguments)
+        }
+        (TokenType::Identifier, "len" | "sort" | "at") => run_container(name, arguments),
+        (
+            TokenType::Identifier,
+            "mean" | "median" | "mode" | "variance" | "stdev" | "min" | "max" | "range"
+            | "skewness" | "kurtosis" | "quantile" | "covariance" | "correlation",
+        ) => run_statistic(name, arguments),
+        (TokenType::Identifier, "map" | "filter" | "fold") => {
+            run_higher_order(name, arguments, env)
+        }
+        (TokenType::Identifier, "compose") => run_compose(arguments, env),
+        (TokenType::Identifier, "add" | "sub" | "mul" | "div") => {
+            let token = operation_token(named_operation(name).unwrap());
+            let b = arguments.remove(1);
+            run_operation(
+                arguments.remove(0),
+                b,
+                &Operation::TokenOperation(InnerTokenOperation { token }),
+            )
+        }
+        (TokenType::Identifier, "in") => {
+            let container = arguments.remove(1);
+            Ok(Value::Bool(container.contains(&arguments[0])?))
+        }
+        (TokenType::Identifier, "error") => match arguments.remove(0) {
+            Value::Estimate { error, .. } => Ok(Value::dec(error)),
+            other => Err(BasisError::new(&format!(
+                "{} has no error estimate",
+                other.type_name()
+            ))),
+        },
+        (TokenType::Identifier, _) => match env.lookup(name) {
+            Some(Value::Function(function)) => {
+                let function = function.clone();
+                call_function(&function, arguments, env)
+            }
+            Some(Value::Closure(closure)) if !closure.parameters.is_empty() => {
+                let closure = closure.clone();
+                run_closure(&closure, arguments, env)
+            }
+            _ => Err(BasisError::new(&format!("Unknown function `{}`", name))),
+        },
+        _ => Err(BasisError::new(&format!(
+            "Unknown function `{}`",
+            call.function.value
+        ))),
+    }
+}
+
+fn eval_token(token: &Token, env: &Environment) -> Result<Value, BasisError> {
+    match token.token_type {
+        TokenType::NumericIntLiteral => match token.value.parse::<i64>() {
+            Ok(value) => Ok(Value::int(value)),
+            Err(_) => Err(BasisError::new(&format!("Invalid int `{}`", token.value))),
+        },
+
+        TokenType::NumericDecLiteral => match token.value.parse::<f64>() {
+            Ok(value) => Ok(Value::dec(value)),
+            Err(_) => Err(BasisError::new(&format!("Invalid dec `{}`", token.value))),
+        },
+
+        TokenType::NumericImaginaryLiteral => match imaginary_part(&token.value) {
+            Some(value) => Ok(Value::Number(Number::Imaginary(value))),
+            None => Err(BasisError::new(&format!(
+                "Invalid imaginary `{}`",
+                token.value
+            ))),
+        },
+
+        TokenType::StringLiteral => Ok(Value::String(token.value.clone())),
+        TokenType::BoolLiteral => Ok(Value::Bool(token.value == "true")),
+        TokenType::NilLiteral => Ok(Value::Nil),
+
+        // `<1>` is cast to the type the `number` generic is bound to in the running call
+        TokenType::GenericLiteral => {
+            let inner = generic_constraint(&token.value).unwrap_or(&token.value);
+            let value = eval_token(&Token::tokenize(inner.to_string()), env)?;
+
+            match env.generic("number") {
+                Some(type_name) => cast_value(value, &type_from_name(type_name)),
+                None => Ok(value),
+            }
+        }
+
+        TokenType::Identifier => match env.lookup(&token.value) {
+            Some(value) => Ok(value.clone()),
+
+            // Provided constants like `pi` stay literals until they are unwrapped
+            None if constant(&token.value).is_some() => {
+                Ok(Value::Literal(Expression::from_token(token.clone())))
+            }
+            None => Err(BasisError::new(&format!(
+                "Unknown identifier `{}`",
+                token.value
+            ))),
+        },
+
+        _ => Err(BasisError::new(&format!(
+            "Unexpected token `{}`",
+            token.value
+        ))),
+    }
+}
+
+/// Evaluate an expression into a value with nothing defined
+pub fn eval(exp: &Expression) -> Result<Value, BasisError> {
+    eval_in(exp, &mut Environment::new())
+}
+
+/// Evaluate an expression into a value, using and adding to the definitions in the environment
+pub fn eval_in(exp: &Expression, env: &mut Environment) -> Result<Value, BasisError> {
+    match exp {
+        Expression::TokenExpression(t) => eval_token(&t.token, env),
+        Expression::ExpExpOp(i) => {
+            let a = eval_in(&i.expression_1, env)?;
+            let b = eval_in(&i.expression_2, env)?;
+            run_operation(a, b, &i.operation)
+        }
+        Expression::Assignment(a) => create_assignment(
+            &a.expression_1,
+            &a.expression_2,
+            &a.expression_3,
+            &a.operation,
+            env,
+        ),
+        Expression::Call(c) => run_call(c, env),
+        Expression::Function(f) => define_function(f, env),
+        Expression::Closure(c) => Ok(Value::Closure(Closure {
+            parameters: c.parameters.clone(),
+            body: c.body.as_ref().clone(),
+            captured: env.captured(),
+            frame: env.frame_id(),
+            source: None,
+        })),
+        Expression::If(_) | Expression::Match(_) => match choose_branch(exp, env)? {
+            Some((branch, binding)) => eval_branch(branch, binding, env),
+            None => Err(BasisError::new("Expected a conditional")),
+        },
+        Expression::Sequence(s) => {
+            let mut out = Err(BasisError::new("Empty expression"));
+            for expression in &s.expressions {
+                out = Ok(eval_in(expression, env)?);
+
+                // The rest of the statements are skipped after a `break` or `continue`
+                if env.jumping() {
+                    return Ok(Value::Nil);
+                }
+            }
+
+            out
+        }
+        Expression::For(f) => run_for(f, env),
+        Expression::While(w) => run_while(w, env),
+        Expression::CompoundAssignment(c) => run_compound_assignment(c, env),
+        Expression::Vector(v) => {
+            let mut rows = Vec::<Vec<Number>>::new();
+            for row in &v.rows {
+                let mut elements = Vec::<Number>::new();
+                for element in row {
+                    match eval_in(element, env)?.without_estimate() {
+                        Value::Number(number) => elements.push(number),
+                        other => {
+                            return Err(BasisError::new(&format!(
+                                "A vector can only hold numbers, but got {}",
+                                other.type_name()
+                            )))
+                        }
+                    }
+                }
+
+                rows.push(elements);
+            }
+
+            Ok(Value::Vector(Vector::new(rows)?))
+        }
+        Expression::Tuple(t) => {
+            let mut values = Vec::<Value>::new();
+            for element in &t.elements {
+                values.push(eval_in(element, env)?);
+            }
+
+            Ok(Value::Tuple(values))
+        }
+        Expression::Set(s) => {
+            let mut values = Vec::<Value>::new();
+            for element in &s.elements {
+                values.push(eval_in(element, env)?);
+            }
+
+            Ok(Value::set(values))
+        }
+        Expression::Break => run_jump(Jump::Break, env),
+        Expression::Continue => run_jump(Jump::Continue, env),
+        Expression::None => Err(BasisError::new("Empty expression")),
+    }
+}
+
+/// The index of the token that closes a group opened just before `start`, like the `)` that
+/// matches a `(`
+fn group_end(tokens: &[Token], start: usize, close: TokenType) -> Option<usize> {
+    let open = match close {
+        TokenType::RightBrace => TokenType::LeftBrace,
+        TokenType::RightBracket => TokenType::LeftBracket,
+        _ => TokenType::LeftParen,
+    };
+
+    let mut depth = 1;
+    for (index, token) in tokens.iter().enumerate().skip(start) {
+        if token.token_type == open {
+            depth += 1;
+        } else if token.token_type == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+
+    None
+}
+
+/// Split tokens by the commas that are not inside another group
+fn split_commas(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parts = Vec::<&[Token]>::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => depth += 1,
+            TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => depth -= 1,
+            TokenType::Comma if depth == 0 => {
+                parts.push(&tokens[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+
+    parts
+}
 
-#[derive(Debug, PartialEq)]
-pub struct InnerTokenExpression {
-    token: Token,
+/// Parse the elements of a tuple or set. Each value left on the stack is an element, like
+/// `(1 2 3)`, unless commas split the elements into whole expressions like `(1 2 +, 4)`, where a
+/// comma at the end makes a single element like `(5,)`.
+fn parse_elements(
+    tokens: &[Token],
+    arity: &dyn Fn(&str) -> Option<usize>,
+) -> Option<Vec<Expression>> {
+    let mut parts = split_commas(tokens);
+    if parts.len() == 1 {
+        let elements = build_stack(tokens, arity)?;
+        return match elements.iter().any(is_statement) {
+            true => None,
+            false => Some(elements),
+        };
+    }
+
+    if parts.last().is_some_and(|p| p.is_empty()) {
+        parts.pop();
+    }
+
+    parts
+        .into_iter()
+        .map(|part| match build_expression(part.to_vec(), arity) {
+            Expression::None => None,
+            exp => Some(exp),
+        })
+        .collect()
 }
 
-/// Expression Expression Operation
-#[derive(Debug, PartialEq)]
-pub struct InnerExpExpOp {
-    expression_1: Box<Expression>,
-    expression_2: Box<Expression>,
-    operation: Box<Operation>,
+/// Parse the arms of a match, which are split by commas that are not inside another group
+fn parse_arms(tokens: &[Token], arity: &dyn Fn(&str) -> Option<usize>) -> Option<Vec<MatchArm>> {
+    let mut arms = Vec::<MatchArm>::new();
+    for part in split_commas(tokens) {
+        let colon = part.iter().position(|t| t.token_type == TokenType::Colon)?;
+        let pattern = Pattern::parse(&part[..colon])?;
+
+        // The name from a pattern like `some v` is a value in the result
+        let name = match &pattern {
+            Pattern::Some(Some(name)) => Some(name.clone()),
+            _ => None,
+        };
+        let result_arity = |n: &str| match &name {
+            Some(name) if name == n => None,
+            _ => arity(n),
+        };
+
+        let result = build_expression(part[colon + 1..].to_vec(), &result_arity);
+        if result == Expression::None {
+            return None;
+        }
+
+        arms.push(MatchArm { pattern, result });
+    }
+
+    Some(arms)
 }
 
-#[derive(Debug, PartialEq)]
-pub struct InnerAssignment {
-    expression_1: Box<Expression>,
-    expression_2: Box<Expression>,
-    expression_3: Box<Expression>,
-    operation: Box<Operation>,
+/// Parse a loop that starts at `start`, like `for x: int in c { ... }` or `while i 10 < { ... }`,
+/// giving back the loop and the index after its body. The head can have a set in it, like
+/// `for x in {1 2} { ... }`, so the body is the first brace that leaves a head that parses.
+fn parse_loop(
+    tokens: &[Token],
+    start: usize,
+    arity: &dyn Fn(&str) -> Option<usize>,
+) -> Option<(Expression, usize)> {
+    (start + 1..tokens.len())
+        .filter(|index| tokens[*index].token_type == TokenType::LeftBrace)
+        .find_map(|brace| parse_loop_body(tokens, start, brace, arity))
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Expression {
-    TokenExpression(InnerTokenExpression),
-    ExpExpOp(InnerExpExpOp),
-    Assignment(InnerAssignment),
-    None,
+/// Parse a loop whose body starts at the `{` at `brace`
+fn parse_loop_body(
+    tokens: &[Token],
+    start: usize,
+    brace: usize,
+    arity: &dyn Fn(&str) -> Option<usize>,
+) -> Option<(Expression, usize)> {
+    let keyword = &tokens[start].value;
+    let end = group_end(tokens, brace + 1, TokenType::RightBrace)?;
+
+    let body = build_expression(tokens[brace + 1..end].to_vec(), arity);
+    if body == Expression::None {
+        return None;
+    }
+
+    let head = &tokens[start + 1..brace];
+    let parse = |tokens: &[Token]| match build_expression(tokens.to_vec(), arity) {
+        Expression::None => None,
+        exp => Some(Box::new(exp)),
+    };
+
+    if keyword == "while" {
+        let condition = parse(head)?;
+        return Some((
+            Expression::While(InnerWhile {
+                condition,
+                body: Box::new(body),
+            }),
+            end + 1,
+        ));
+    }
+
+    // `x in c` or `x: ratio in c`
+    let (variable, type_token, rest) = match head {
+        [variable, colon, type_token, keyword, rest @ ..]
+            if colon.token_type == TokenType::Colon
+                && is_type_keyword(&type_token.token_type)
+                && keyword.value == "in" =>
+        {
+            (variable, Some(type_token.clone()), rest)
+        }
+        [variable, keyword, rest @ ..] if keyword.value == "in" => (variable, None, rest),
+        _ => return None,
+    };
+
+    if variable.token_type != TokenType::Identifier || builtin_arity(&variable.value).is_some() {
+        return None;
+    }
+
+    // A range like `0..10` goes through the ints from the start up to the end
+    let range = rest
+        .windows(2)
+        .position(|w| w.iter().all(|t| t.token_type == TokenType::Dot));
+
+    let (iterable, end_of_range) = match range {
+        Some(split) => (parse(&rest[..split])?, Some(parse(&rest[split + 2..])?)),
+        None => (parse(rest)?, None),
+    };
+
+    Some((
+        Expression::For(InnerFor {
+            variable: variable.clone(),
+            type_token,
+            iterable,
+            end: end_of_range,
+            body: Box::new(body),
+        }),
+        end + 1,
+    ))
 }
 
-#[derive(Debug, PartialEq)]
-pub struct InnerTokenOperation {
-    token: Token,
+/// A group that is missing arguments on its left, like `(2 *)` or `(sin)`, is a closure that
+/// takes them, so `[1 2] (2 *) map` doubles each element
+fn parse_partial(tokens: &[Token], arity: &dyn Fn(&str) -> Option<usize>) -> Option<Expression> {
+    if tokens.is_empty() || split_commas(tokens).len() > 1 {
+        return None;
+    }
+
+    (1..=3).find_map(|count| {
+        let parameters = placeholders(count);
+
+        let mut partial: Vec<Token> = parameters
+            .iter()
+            .map(|name| Token {
+                token_type: TokenType::Identifier,
+                value: name.clone(),
+            })
+            .collect();
+        partial.extend_from_slice(tokens);
+
+        match build_expression(partial, arity) {
+            Expression::None => None,
+            body => Some(Expression::Closure(InnerClosure {
+                parameters,
+                body: Box::new(body),
+            })),
+        }
+    })
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Operation {
-    TokenOperation(InnerTokenOperation),
+/// Whether a builtin takes a function as its last argument, like `map`
+fn is_higher_order(name: &str) -> bool {
+    matches!(name, "map" | "filter" | "fold" | "compose")
 }
 
-fn create_assignment(
-    arg1: Box<Expression>,
-    arg2: Box<Expression>,
-    arg3: Box<Expression>,
-    operation: Box<Operation>,
-) -> Expression {
-    println!("{:?} {:?} {:?} {:?}", arg1, arg2, arg3, operation);
+/// Whether a name is a function that can be passed as a value, which leaves out the builtins
+/// that are part of the syntax like `if` and `for`
+fn is_function_name(name: &str, arity: &dyn Fn(&str) -> Option<usize>) -> bool {
+    arity(name).is_some()
+        && !is_higher_order(name)
+        && !matches!(
+            name,
+            "if" | "for" | "while" | "break" | "continue" | "match" | "d" | "deriv"
+        )
+}
+
+/// Whether the function at `index` is passed to a function like `map` rather than called,
+/// like `f` in `[1 2 3] f map`. Other functions and groups can come between them, so both `f`
+/// and `g` are values in `f g compose`, and a function followed by a variable and `d`, like
+/// `h x d`, is differentiated.
+fn is_function_argument(
+    tokens: &[Token],
+    index: usize,
+    arity: &dyn Fn(&str) -> Option<usize>,
+) -> bool {
+    if !is_function_name(&tokens[index].value, arity) {
+        return false;
+    }
+
+    if let [variable, derivative, ..] = &tokens[index + 1..] {
+        if variable.token_type == TokenType::Identifier
+            && arity(&variable.value).is_none()
+            && matches!(derivative.value.as_str(), "d" | "deriv")
+        {
+            return true;
+        }
+    }
+
+    let mut next = index + 1;
+    while let Some(token) = tokens.get(next) {
+        match token.token_type {
+            TokenType::LeftParen => match group_end(tokens, next + 1, TokenType::RightParen) {
+                Some(end) => next = end + 1,
+                None => return false,
+            },
+            TokenType::Identifier if is_higher_order(&token.value) => return true,
+            TokenType::Identifier if is_function_name(&token.value, arity) => next += 1,
+            _ => return false,
+        }
+    }
+
+    false
+}
+
+/// A function passed as a value, where a user function is looked up by its name and a builtin
+/// becomes a closure over its arguments, like `(add)`
+fn function_value(token: Token) -> Expression {
+    let Some(count) = builtin_arity(&token.value) else {
+        return Expression::from_token(token);
+    };
+
+    let parameters = placeholders(count);
+    let arguments = parameters
+        .iter()
+        .map(|p| Expression::identifier(p))
+        .collect();
+
+    Expression::Closure(InnerClosure {
+        parameters,
+        body: Box::new(Expression::Call(InnerCall {
+            function: token,
+            arguments,
+        })),
+    })
+}
+
+/// Pop the last `count` expressions off the stack, keeping their order
+fn pop_arguments(stack: &mut Vec<Expression>, count: usize) -> Option<Vec<Expression>> {
+    if stack.len() < count {
+        return None;
+    }
+
+    Some(stack.split_off(stack.len() - count))
+}
+
+/// Join a generic parameter type like `< number >` into a single `<number>` type token. Only
+/// abstract types like `number` can be generic.
+fn merge_generic_parameters(tokens: &[Token]) -> Vec<Token> {
+    let mut out = Vec::<Token>::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match &tokens[index..] {
+            [Token {
+                token_type: TokenType::Less,
+                ..
+            }, inner @ Token {
+                token_type: TokenType::TypeNumberKeyword,
+                ..
+            }, Token {
+                token_type: TokenType::Greater,
+                ..
+            }, ..] => {
+                out.push(Token {
+                    token_type: inner.token_type.clone(),
+                    value: format!("<{}>", inner.value),
+                });
+                index += 3;
+            }
+            _ => {
+                out.push(tokens[index].clone());
+                index += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// Join a generic literal like `< 1 >` into a single `<1>` token
+fn merge_generic_literals(tokens: &[Token]) -> Vec<Token> {
+    let mut out = Vec::<Token>::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match &tokens[index..] {
+            [Token {
+                token_type: TokenType::Less,
+                ..
+            }, inner, Token {
+                token_type: TokenType::Greater,
+                ..
+            }, ..]
+                if matches!(
+                    inner.token_type,
+                    TokenType::NumericIntLiteral
+                        | TokenType::NumericDecLiteral
+                        | TokenType::NumericImaginaryLiteral
+                ) =>
+            {
+                out.push(Token {
+                    token_type: TokenType::GenericLiteral,
+                    value: format!("<{}>", inner.value),
+                });
+                index += 3;
+            }
+            _ => {
+                out.push(tokens[index].clone());
+                index += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// Parse the parameter list of a function definition, like `(x int, y dec)` or `(x <number>)`
+fn parse_parameters(tokens: &[Token]) -> Option<Vec<Parameter>> {
+    let tokens = merge_generic_parameters(tokens);
+
+    let [Token {
+        token_type: TokenType::LeftParen,
+        ..
+    }, inner @ .., Token {
+        token_type: TokenType::RightParen,
+        ..
+    }] = tokens.as_slice()
+    else {
+        return None;
+    };
+
+    let inner: Vec<&Token> = inner
+        .iter()
+        .filter(|t| t.token_type != TokenType::Comma)
+        .collect();
+
+    let mut parameters = Vec::<Parameter>::new();
+    for pair in inner.chunks(2) {
+        match pair {
+            [name, type_token]
+                if name.token_type == TokenType::Identifier
+                    && is_type_keyword(&type_token.token_type) =>
+            {
+                parameters.push(Parameter {
+                    name: name.value.clone(),
+                    type_token: (*type_token).clone(),
+                })
+            }
+            _ => return None,
+        }
+    }
+
+    Some(parameters)
+}
+
+/// Where the parameters of a function definition without `=:` end, like the `)` in
+/// `f (x <number>) x <1> +`
+fn parameters_end(tokens: &[Token]) -> Option<usize> {
+    if tokens.first()?.token_type != TokenType::Identifier
+        || tokens.get(1)?.token_type != TokenType::LeftParen
+    {
+        return None;
+    }
+
+    let end = tokens
+        .iter()
+        .position(|t| t.token_type == TokenType::RightParen)?;
 
-    return *arg3;
+    match parse_parameters(&tokens[1..=end]) {
+        Some(parameters) if !parameters.is_empty() && end + 1 < tokens.len() => Some(end),
+        _ => None,
+    }
 }
 
-fn run_operation(
-    arg1: Box<Expression>,
-    arg2: Box<Expression>,
-    operation: Box<Operation>,
+/// Parse a function definition `name (parameters) =: body`, where the `=:` can be left out
+fn function_definition(
+    head: &[Token],
+    body: &[Token],
+    arity: &dyn Fn(&str) -> Option<usize>,
 ) -> Expression {
-    match (*arg1, *arg2) {
-        // ---
-        // TWO TOKENS
-        (Expression::TokenExpression(a), Expression::TokenExpression(b)) => {
-            match (a.token.token_type, b.token.token_type) {
-                // ---
-                // TWO NUMBER LITERALS
-                (TokenType::NumericIntLiteral, TokenType::NumericIntLiteral) => {
-                    let a_int = a.token.value.parse::<i32>().unwrap();
-                    let b_int = b.token.value.parse::<i32>().unwrap();
-
-                    let op = *operation;
-
-                    match op {
-                        Operation::TokenOperation(c) => match c.token.token_type {
-                            TokenType::Addition => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_int + b_int),
-                                    },
-                                })
-                            }
-
-                            TokenType::Subtraction => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_int - b_int),
-                                    },
-                                })
-                            }
-
-                            TokenType::Multiplication => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_int * b_int),
-                                    },
-                                })
-                            }
-
-                            TokenType::Division => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_int / b_int),
-                                    },
-                                })
-                            }
-                            _ => Expression::None,
-                        },
-                    }
-                }
+    let Some(name) = head
+        .first()
+        .filter(|t| t.token_type == TokenType::Identifier)
+    else {
+        return Expression::None;
+    };
+
+    let Some(parameters) = parse_parameters(&head[1..]) else {
+        return Expression::None;
+    };
+
+    // In the body, parameters are values and the function can call itself
+    let body_arity = |n: &str| {
+        if n == name.value {
+            Some(parameters.len())
+        } else if parameters.iter().any(|p| p.name == n) {
+            None
+        } else {
+            arity(n)
+        }
+    };
 
-                // ---
-                // TWO NUMBER LITERALS
-                (TokenType::NumericDecLiteral, TokenType::NumericDecLiteral) => {
-                    let a_float = a.token.value.parse::<f32>().unwrap();
-                    let b_float = b.token.value.parse::<f32>().unwrap();
-
-                    let op = *operation;
-
-                    match op {
-                        Operation::TokenOperation(c) => match c.token.token_type {
-                            TokenType::Addition => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_float + b_float),
-                                    },
-                                })
-                            }
-
-                            TokenType::Subtraction => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_float - b_float),
-                                    },
-                                })
-                            }
-
-                            TokenType::Multiplication => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_float * b_float),
-                                    },
-                                })
-                            }
-
-                            TokenType::Division => {
-                                return Expression::TokenExpression(InnerTokenExpression {
-                                    token: Token {
-                                        token_type: TokenType::NumericIntLiteral,
-                                        value: format!("{}", a_float / b_float),
-                                    },
-                                })
-                            }
-
-                            _ => Expression::None,
-                        },
-                    }
-                }
+    let body = build_expression(merge_generic_literals(body), &body_arity);
+    if body == Expression::None {
+        return Expression::None;
+    }
+
+    Expression::Function(InnerFunction {
+        name: name.clone(),
+        parameters,
+        body: Box::new(body),
+    })
+}
 
-                _ => Expression::None,
+/// The most groups, vectors and blocks that can be inside one another, so parsing them, which
+/// goes a level deeper for each, cannot run out of stack
+pub const MAX_NESTING: usize = 256;
+
+/// An error for tokens with groups nested past `MAX_NESTING`
+pub fn check_nesting(tokens: &[Token]) -> Result<(), BasisError> {
+    let mut depth = 0usize;
+    for token in tokens {
+        match token.token_type {
+            TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace => {
+                depth += 1;
+                if depth > MAX_NESTING {
+                    return Err(BasisError::new(&format!(
+                        "Groups can only be nested {} deep",
+                        MAX_NESTING
+                    )));
+                }
+            }
+            TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace => {
+                depth = depth.saturating_sub(1)
             }
+            _ => {}
         }
+    }
+
+    Ok(())
+}
+
+/// Parse tokens into an expression, only knowing about the builtin functions
+pub fn tokens_to_expr(tokens: Vec<Token>) -> Expression {
+    if check_nesting(&tokens).is_err() {
+        return Expression::None;
+    }
+
+    build_expression(tokens, &builtin_arity)
+}
+
+/// Parse tokens into an expression, where functions defined in the environment take their
+/// arguments from the left like the builtin functions
+pub fn tokens_to_expr_in(tokens: Vec<Token>, env: &Environment) -> Expression {
+    if check_nesting(&tokens).is_err() {
+        return Expression::None;
+    }
 
+    build_expression(tokens, &|name: &str| {
+        builtin_arity(name).or_else(|| env.arity(name))
+    })
+}
+
+fn build_expression(tokens: Vec<Token>, arity: &dyn Fn(&str) -> Option<usize>) -> Expression {
+    let tokens = merge_generic_types(tokens);
+
+    if let Some(split) = tokens
+        .iter()
+        .position(|t| t.token_type == TokenType::FunctionAssignment)
+    {
+        return function_definition(&tokens[..split], &tokens[split + 1..], arity);
+    }
+
+    if let Some(end) = parameters_end(&tokens) {
+        return function_definition(&tokens[..=end], &tokens[end + 1..], arity);
+    }
+
+    let Some(mut stack) = build_stack(&tokens, arity) else {
+        return Expression::None;
+    };
+
+    match stack.len() {
+        0 => Expression::None,
+        1 => stack.pop().unwrap(),
+        _ if stack[..stack.len() - 1].iter().all(is_statement) => {
+            Expression::Sequence(InnerSequence { expressions: stack })
+        }
         _ => Expression::None,
     }
 }
 
-pub fn tokens_to_expr(tokens: Vec<Token>) -> Expression {
-    if tokens.len() == 3 {
-        let b = Expression::ExpExpOp({
-            InnerExpExpOp {
-                expression_1: Box::new(Expression::TokenExpression(InnerTokenExpression {
-                    token: tokens[0].clone(),
-                })),
+/// Run tokens through the postfix stack, giving back everything left on it, like each element
+/// of `[1 2 3]`
+fn build_stack(tokens: &[Token], arity: &dyn Fn(&str) -> Option<usize>) -> Option<Vec<Expression>> {
+    // Basis is postfix, so each operation takes its arguments off the top of the stack
+    let mut stack = Vec::<Expression>::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let token = tokens[index].clone();
+        index += 1;
+
+        match token.token_type {
+            // `(1 1 +)` is a group when it is a single expression, a tuple like `(1 2 3)` when it
+            // is more than one, and a partial application like `(2 *)` when it is missing some
+            TokenType::LeftParen => {
+                let end = group_end(tokens, index, TokenType::RightParen)?;
+                let inner = &tokens[index..end];
+
+                let group = match split_commas(inner).len() {
+                    1 => build_expression(inner.to_vec(), arity),
+                    _ => Expression::None,
+                };
+
+                index = end + 1;
+                stack.push(match group {
+                    Expression::None => match parse_elements(inner, arity) {
+                        Some(elements) => Expression::Tuple(InnerTuple { elements }),
+                        None => parse_partial(inner, arity)?,
+                    },
+                    group => group,
+                });
+            }
 
-                expression_2: Box::new(Expression::TokenExpression(InnerTokenExpression {
-                    token: tokens[1].clone(),
-                })),
+            // `[1 2 3]` is a vector, where each value left on the stack is an element, and `;`
+            // starts a new row like `[1 2; 3 4]`
+            TokenType::LeftBracket => {
+                let end = group_end(tokens, index, TokenType::RightBracket)?;
+
+                // Elements can also be split by commas, like `[100, 40, 10]`
+                let inner: Vec<Token> = tokens[index..end]
+                    .iter()
+                    .filter(|t| t.token_type != TokenType::Comma)
+                    .cloned()
+                    .collect();
+
+                let mut rows = Vec::<Vec<Expression>>::new();
+                for row in inner.split(|t| t.token_type == TokenType::Semicolon) {
+                    rows.push(build_stack(row, arity)?);
+                }
 
-                operation: Box::new(Operation::TokenOperation(InnerTokenOperation {
-                    token: tokens[2].clone(),
-                })),
+                index = end + 1;
+                stack.push(Expression::Vector(InnerVector { rows }));
             }
-        });
 
-        return b;
-    }
+            // `{1 2 3}` is a set when it is not a single expression like the closure `{ 1 1 + }`
+            TokenType::LeftBrace
+                if group_end(tokens, index, TokenType::RightBrace).is_some_and(|end| {
+                    let inner = &tokens[index..end];
+                    split_commas(inner).len() > 1
+                        || build_expression(inner.to_vec(), arity) == Expression::None
+                }) =>
+            {
+                let end = group_end(tokens, index, TokenType::RightBrace)?;
+                let elements = parse_elements(&tokens[index..end], arity)?;
+
+                index = end + 1;
+                stack.push(Expression::Set(InnerSet { elements }));
+            }
 
-    if tokens.len() == 4 && tokens[3].token_type == TokenType::Assignment {
-        let b = Expression::Assignment({
-            InnerAssignment {
-                expression_1: Box::new(Expression::TokenExpression(InnerTokenExpression {
-                    token: tokens[0].clone(),
-                })),
+            // `: (1 1 +)` and `{ 1 1 + }` are closures
+            TokenType::Colon | TokenType::LeftBrace => {
+                let (start, close) = match token.token_type {
+                    TokenType::Colon => match tokens.get(index) {
+                        Some(t) if t.token_type == TokenType::LeftParen => {
+                            (index + 1, TokenType::RightParen)
+                        }
+                        _ => return None,
+                    },
+                    _ => (index, TokenType::RightBrace),
+                };
 
-                expression_2: Box::new(Expression::TokenExpression(InnerTokenExpression {
-                    token: tokens[1].clone(),
-                })),
+                let end = group_end(tokens, start, close)?;
 
-                expression_3: Box::new(Expression::TokenExpression(InnerTokenExpression {
-                    token: tokens[2].clone(),
-                })),
+                let body = build_expression(tokens[start..end].to_vec(), arity);
+                if body == Expression::None {
+                    return None;
+                }
 
-                operation: Box::new(Operation::TokenOperation(InnerTokenOperation {
-                    token: tokens[3].clone(),
-                })),
+                index = end + 1;
+                stack.push(Expression::Closure(InnerClosure {
+                    parameters: Vec::new(),
+                    body: Box::new(body),
+                }));
             }
-        });
 
-        return b;
+            TokenType::Addition
+            | TokenType::Subtraction
+            | TokenType::Multiplication
+            | TokenType::Division
+            | TokenType::Power
+            | TokenType::Greater
+            | TokenType::Less
+            | TokenType::GreaterEqual
+            | TokenType::LessEqual
+            | TokenType::Equal
+            | TokenType::NotEqual => {
+                let mut arguments = pop_arguments(&mut stack, 2)?;
+
+                let expression_2 = arguments.pop().unwrap();
+                let expression_1 = arguments.pop().unwrap();
+
+                stack.push(Expression::ExpExpOp(InnerExpExpOp {
+                    expression_1: Box::new(expression_1),
+                    expression_2: Box::new(expression_2),
+                    operation: Box::new(Operation::TokenOperation(InnerTokenOperation { token })),
+                }));
+            }
+
+            TokenType::Assignment => {
+                // `name type value =` takes everything before it back to the last statement
+                let mut arguments = pop_arguments(&mut stack, 3)?;
+
+                if !stack.iter().all(is_statement) {
+                    return None;
+                }
+
+                let expression_3 = arguments.pop().unwrap();
+                let expression_2 = arguments.pop().unwrap();
+                let expression_1 = arguments.pop().unwrap();
+
+                stack.push(Expression::Assignment(InnerAssignment {
+                    expression_1: Box::new(expression_1),
+                    expression_2: Box::new(expression_2),
+                    expression_3: Box::new(expression_3),
+                    operation: Box::new(Operation::TokenOperation(InnerTokenOperation { token })),
+                }));
+            }
+
+            // `name value +=` changes a variable that is already declared
+            TokenType::AddAssignment
+            | TokenType::SubtractAssignment
+            | TokenType::MultiplyAssignment
+            | TokenType::DivideAssignment => {
+                let mut arguments = pop_arguments(&mut stack, 2)?;
+
+                if !stack.iter().all(is_statement) {
+                    return None;
+                }
+
+                let value = arguments.pop().unwrap();
+                let name = match arguments.pop().unwrap() {
+                    Expression::TokenExpression(t)
+                        if t.token.token_type == TokenType::Identifier =>
+                    {
+                        t.token
+                    }
+                    _ => return None,
+                };
+
+                stack.push(Expression::CompoundAssignment(InnerCompoundAssignment {
+                    name,
+                    value: Box::new(value),
+                    operation: Box::new(Operation::TokenOperation(InnerTokenOperation { token })),
+                }));
+            }
+
+            TokenType::Dot => {
+                let arguments = pop_arguments(&mut stack, 1)?;
+
+                stack.push(Expression::Call(InnerCall {
+                    function: token,
+                    arguments,
+                }));
+            }
+
+            TokenType::TypeSizeKeyword
+            | TokenType::TypeBoolKeyword
+            | TokenType::TypeOptionKeyword
+            | TokenType::TypeTupleKeyword
+            | TokenType::TypeSetKeyword
+            | TokenType::TypeSampleKeyword
+            | TokenType::TypePopulationKeyword
+                if is_type_call(tokens, index - 1) =>
+            {
+                let arguments = pop_arguments(&mut stack, 1)?;
+
+                stack.push(Expression::Call(InnerCall {
+                    function: token,
+                    arguments,
+                }));
+            }
+
+            // A builtin named by a declaration like `range int 5 =` is not called, so the
+            // assignment can say that it cannot be assigned to
+            TokenType::Identifier
+                if builtin_arity(&token.value).is_some()
+                    && stack.iter().all(is_statement)
+                    && tokens
+                        .get(index)
+                        .is_some_and(|t| is_type_keyword(&t.token_type)) =>
+            {
+                stack.push(Expression::from_token(token))
+            }
+
+            TokenType::Identifier if token.value == "if" => {
+                let mut arguments = pop_arguments(&mut stack, 3)?;
+
+                let otherwise = arguments.pop().unwrap();
+                let then = arguments.pop().unwrap();
+                let condition = arguments.pop().unwrap();
+
+                stack.push(Expression::If(InnerIf {
+                    condition: Box::new(condition),
+                    then: Box::new(then),
+                    otherwise: Box::new(otherwise),
+                }));
+            }
+
+            // A loop starts a new statement and runs to the end of its body
+            TokenType::Identifier if matches!(token.value.as_str(), "for" | "while") => {
+                if !stack.iter().all(is_statement) {
+                    return None;
+                }
+
+                let (exp, next) = parse_loop(tokens, index - 1, arity)?;
+
+                index = next;
+                stack.push(exp);
+            }
+
+            TokenType::Identifier if token.value == "break" => stack.push(Expression::Break),
+            TokenType::Identifier if token.value == "continue" => stack.push(Expression::Continue),
+
+            // `x match (nil: 0, 0..10: 1, _: 2)` takes the value on its left and the arms on
+            // its right
+            TokenType::Identifier if token.value == "match" => {
+                let subject = stack.pop()?;
+
+                let end = match tokens.get(index) {
+                    Some(t) if t.token_type == TokenType::LeftParen => {
+                        group_end(tokens, index + 1, TokenType::RightParen)
+                    }
+                    _ => None,
+                };
+
+                let end = end?;
+
+                let arms = parse_arms(&tokens[index + 1..end], arity)?;
+
+                index = end + 1;
+                stack.push(Expression::Match(InnerMatch {
+                    subject: Box::new(subject),
+                    arms,
+                }));
+            }
+
+            TokenType::Identifier if is_function_argument(tokens, index - 1, arity) => {
+                stack.push(function_value(token))
+            }
+
+            // A user function without its arguments is used as a value, like `f` in
+            // `f 0 1 integrate`
+            TokenType::Identifier
+                if builtin_arity(&token.value).is_none()
+                    && arity(&token.value).is_some_and(|count| stack.len() < count) =>
+            {
+                stack.push(Expression::from_token(token))
+            }
+
+            TokenType::Identifier if arity(&token.value).is_some() => {
+                let count = arity(&token.value).unwrap();
+                let arguments = pop_arguments(&mut stack, count)?;
+
+                stack.push(Expression::Call(InnerCall {
+                    function: token,
+                    arguments,
+                }));
+            }
+
+            _ => stack.push(Expression::from_token(token)),
+        }
     }
 
-    return Expression::None;
+    Some(stack)
+}
+
+/// Whether an expression is complete on its own, so another can follow it in a sequence. A
+/// conditional counts, so a loop body can `break` or `continue` part way through.
+fn is_statement(exp: &Expression) -> bool {
+    matches!(
+        exp,
+        Expression::Assignment(_)
+            | Expression::CompoundAssignment(_)
+            | Expression::For(_)
+            | Expression::While(_)
+            | Expression::If(_)
+            | Expression::Match(_)
+            | Expression::Break
+            | Expression::Continue
+    )
 }
 
+/// Evaluate an expression and give back the result as an expression
 pub fn parse(exp: Expression) -> Expression {
-    match exp {
-        Expression::ExpExpOp(i) => {
-            return run_operation(i.expression_1, i.expression_2, i.operation)
-        }
-        Expression::Assignment(a) => {
-            return create_assignment(a.expression_1, a.expression_2, a.expression_3, a.operation)
-        }
-        _ => return Expression::None,
+    match eval(&exp) {
+        Ok(value) => value.to_expression(),
+        Err(_) => Expression::None,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexer::{Lex, Lexer};
+
+    /// Evaluate a line in the environment and give back the result as it would be printed
+    fn run(env: &mut Environment, line: &str) -> Result<String, BasisError> {
+        let mut lex: Lexer = Lexer::new(vec![line.to_string()]);
+        let mut tokens: Vec<Token> = vec![];
+
+        let mut tok = lex.next();
+        while tok.token_type != TokenType::EndToken {
+            tokens.push(tok.clone());
+            tok = lex.next();
+        }
+
+        check_nesting(&tokens)?;
+        let expr = tokens_to_expr_in(tokens, env);
+        eval_in(&expr, env).map(|value| value.to_string())
+    }
 
     #[test]
     fn run_operation_test() {
@@ -272,7 +2876,7 @@ mod tests {
                 assert_eq!(d.token.value, String::from("3"));
             }
 
-            _ => assert!(false),
+            _ => unreachable!(),
         }
 
         let c = Expression::ExpExpOp({
@@ -312,7 +2916,7 @@ mod tests {
                 assert_eq!(e.token.value, String::from("80"));
             }
 
-            _ => assert!(false),
+            _ => unreachable!(),
         }
     }
 
@@ -360,10 +2964,60 @@ mod tests {
                 assert_eq!(d.token.value, String::from("101"));
             }
 
-            _ => assert!(false),
+            _ => unreachable!(),
         }
     }
 
+    #[test]
+    fn cast_test() {
+        let mut env = Environment::new();
+        assert_eq!(run(&mut env, "a ratio 3 ="), Ok("ratio: 3".to_string()));
+
+        // A value that cannot be the declared type is an error rather than kept as it is
+        assert_eq!(
+            run(&mut env, "a int \"x\" =").unwrap_err().message,
+            "Cannot cast string to int"
+        );
+        assert_eq!(
+            run(&mut env, "a vector 5 =").unwrap_err().message,
+            "Cannot cast int to vector"
+        );
+        assert_eq!(run(&mut env, "a"), Ok("ratio: 3".to_string()));
+
+        // Literals can be any number type, and names that are not types are not checked
+        assert_eq!(run(&mut env, "b dec pi ="), Ok("literal: pi".to_string()));
+        assert_eq!(run(&mut env, "c float 5 ="), Ok("int: 5".to_string()));
+    }
+
+    #[test]
+    fn nesting_test() {
+        let stack_size = Environment::new().stack_size().unwrap();
+
+        // The groups are parsed on the stack the interpreter runs on
+        let test = move || {
+            let mut env = Environment::new();
+            let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), " 1 +)".repeat(depth));
+
+            assert_eq!(
+                run(&mut env, &nested(MAX_NESTING)),
+                Ok("int: 257".to_string())
+            );
+
+            // Deeper groups are an error rather than a stack overflow
+            assert_eq!(
+                run(&mut env, &nested(MAX_NESTING + 1)).unwrap_err().message,
+                "Groups can only be nested 256 deep"
+            );
+        };
+
+        std::thread::Builder::new()
+            .stack_size(stack_size)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
     #[test]
     fn parser_one_test() {
         let mut lex: Lexer = Lexer::new(vec!["a int 3 =".to_string()]);
@@ -385,7 +3039,7 @@ mod tests {
                 assert_eq!(d.token.value, String::from("3"));
             }
 
-            _ => assert!(false),
+            _ => unreachable!(),
         }
     }
 
@@ -410,7 +3064,704 @@ mod tests {
                 assert_eq!(d.token.value, String::from("39400"));
             }
 
-            _ => assert!(false),
+            _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn literal_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "pi 2 * pi +"),
+            Ok("literal: 3 pi".to_string())
+        );
+
+        // Folds all the way down to an int
+        assert_eq!(run(&mut env, "pi pi / e 0 ^ +"), Ok("int: 2".to_string()));
+
+        assert_eq!(
+            run(&mut env, "pi 2 * unwrap"),
+            Ok(Value::dec(std::f64::consts::PI * 2.0).to_string())
+        );
+
+        // Dividing a literal by an exact zero is an error rather than a literal like `pi / 0`
+        assert_eq!(
+            run(&mut env, "pi 0 /").unwrap_err().message,
+            "Division by zero"
+        );
+        assert!(run(&mut env, "pi pi pi - /").is_err());
+    }
+
+    #[test]
+    fn derivative_test() {
+        let mut env = Environment::new();
+        assert_eq!(run(&mut env, "x 2 ^ x d"), Ok("literal: 2 x".to_string()));
+        assert_eq!(run(&mut env, "x pi * x d"), Ok("literal: pi".to_string()));
+
+        // Only the variable given to `d` is a symbol, and it shadows a value of the same name
+        assert!(run(&mut env, "x y * x d").is_err());
+        assert!(run(&mut env, "x 1 +").is_err());
+        run(&mut env, "x int 3 =").unwrap();
+        assert_eq!(run(&mut env, "x 3 ^ x d"), Ok("literal: 3 x^2".to_string()));
+        assert_eq!(run(&mut env, "x"), Ok("int: 3".to_string()));
+
+        assert_eq!(run(&mut env, "5 x d"), Ok("int: 0".to_string()));
+        assert_eq!(run(&mut env, "1.5 x d"), Ok("int: 0".to_string()));
+        assert!(run(&mut env, "\"abc\" x d").is_err());
+        assert!(run(&mut env, "true x d").is_err());
+        assert!(run(&mut env, "[1 2] x d").is_err());
+
+        // The derivative of a function shows its body, and can be named and called
+        run(&mut env, "h (t dec) =: t 2 ^ 3 t * +").unwrap();
+        assert_eq!(
+            run(&mut env, "h t d"),
+            Ok("function: h' (dec) -> dec = 2 t + 3".to_string())
+        );
+        run(&mut env, "hp function h t d =").unwrap();
+        assert_eq!(run(&mut env, "1 hp"), Ok("dec: 5".to_string()));
+        assert!(run(&mut env, "hq int h t d =").is_err());
+        assert!(run(&mut env, "h y d").is_err());
+
+        // A literal to solve has its free variable as a symbol
+        assert_eq!(
+            run(&mut env, "u 2 ^ 0 3 integrate"),
+            Ok("dec: 9 (error: 0)".to_string())
+        );
+        assert!(run(&mut env, "u v * 0 1 integrate").is_err());
+    }
+
+    #[test]
+    fn complex_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "2 3i + 1 2i - *"),
+            Ok("complex: 8 - 1i".to_string())
+        );
+        assert_eq!(run(&mut env, "0 4 - sqrt"), Ok("imaginary: 2i".to_string()));
+    }
+
+    #[test]
+    fn size_test() {
+        let mut env = Environment::new();
+        assert_eq!(run(&mut env, "\"hello\" size"), Ok("size: 5".to_string()));
+
+        // `size` is the type in an assignment rather than a function
+        assert_eq!(run(&mut env, "a size 4 ="), Ok("size: 4".to_string()));
+    }
+
+    #[test]
+    fn precision_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "1 20 prec 3 /"),
+            Ok("real: 0.33333333333333333333".to_string())
+        );
+        assert!(run(&mut env, "1 10000 prec 3 /").is_ok());
+        assert!(run(&mut env, "2 10000 prec sqrt").is_ok());
+
+        // A precision past the most a real keeps is an error rather than a long wait
+        assert_eq!(
+            run(&mut env, "2 1000000000 prec sqrt").unwrap_err().message,
+            "The precision is too large, since a real keeps at most 10000 digits"
+        );
+        assert!(run(&mut env, "1 0 prec").is_err());
+    }
+
+    #[test]
+    fn comparison_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "(3 2 /) 1.5 == 1 2 > or"),
+            Ok("bool: true".to_string())
+        );
+        assert_eq!(run(&mut env, "0 bool not"), Ok("bool: true".to_string()));
+    }
+
+    #[test]
+    fn option_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "3 option<ratio>"),
+            Ok("option: some(ratio: 3)".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "3 option<ratio> unwrap"),
+            Ok("ratio: 3".to_string())
+        );
+
+        assert_eq!(
+            run(&mut env, "nil option bool"),
+            Ok("bool: false".to_string())
+        );
+        assert!(run(&mut env, "nil option unwrap").is_err());
+    }
+
+    #[test]
+    fn function_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "f (x int, y dec) =: x y * 1 +"),
+            Ok("function: f (int, dec) -> dec".to_string())
+        );
+        assert_eq!(run(&mut env, "2 3 f"), Ok("dec: 7".to_string()));
+
+        // The arguments are cast to the parameter types
+        assert!(run(&mut env, "2.5 3 f").is_err());
+    }
+
+    #[test]
+    fn generic_test() {
+        let mut env = Environment::new();
+        let output = run(&mut env, "f (x <number>) x <1> +").unwrap();
+        assert_eq!(output.to_string(), "function: f (<number>) -> <number>");
+
+        // The generic literal is cast to the type of the argument
+        assert_eq!(run(&mut env, "2 f"), Ok("int: 3".to_string()));
+        assert_eq!(run(&mut env, "2.0 f"), Ok("dec: 3".to_string()));
+        assert!(run(&mut env, "\"a\" f").is_err());
+
+        // Every argument of a generic is promoted to one type
+        run(&mut env, "g (x <number>, y <number>) =: x y +").unwrap();
+        assert_eq!(run(&mut env, "1 2.5 g"), Ok("dec: 3.5".to_string()));
+    }
+
+    #[test]
+    fn closure_test() {
+        let mut env = Environment::new();
+        let output = run(&mut env, ": (1 1 +)").unwrap();
+        assert_eq!(output.to_string(), "closure: 1 1 +");
+        assert_eq!(run(&mut env, ": (1 1 +) unwrap"), Ok("int: 2".to_string()));
+
+        let output = run(&mut env, "{ 1 1 + }").unwrap();
+        assert_eq!(output.to_string(), "closure: 1 1 +");
+        assert_eq!(run(&mut env, "{ 1 1 + } unwrap"), Ok("int: 2".to_string()));
+
+        // A closure keeps the arguments of the call it was made in
+        run(&mut env, "f (x int) =: : (x 2 *)").unwrap();
+        assert_eq!(run(&mut env, "3 f unwrap"), Ok("int: 6".to_string()));
+    }
+
+    #[test]
+    fn scope_test() {
+        let mut env = Environment::new();
+        assert_eq!(run(&mut env, "a int 5 ="), Ok("int: 5".to_string()));
+        assert_eq!(run(&mut env, "a 1 +"), Ok("int: 6".to_string()));
+
+        // Parameters shadow globals, and functions see globals as they are when called
+        run(&mut env, "f (x int) =: x a +").unwrap();
+        run(&mut env, "g (a int) =: a 1 +").unwrap();
+        assert_eq!(run(&mut env, "7 g"), Ok("int: 8".to_string()));
+        run(&mut env, "a int 10 =").unwrap();
+        assert_eq!(run(&mut env, "2 f"), Ok("int: 12".to_string()));
+
+        // Declarations in a block do not leak out of it
+        assert_eq!(
+            run(&mut env, "{ a int 1 = b int 2 = a b + } unwrap"),
+            Ok("int: 3".to_string())
+        );
+        assert_eq!(run(&mut env, "a"), Ok("int: 10".to_string()));
+        assert!(run(&mut env, "b").is_err());
+
+        // Captured variables outlive their scope
+        run(&mut env, "k (x int) =: { y int 2 = : (x y *) } unwrap").unwrap();
+        assert_eq!(run(&mut env, "4 k unwrap"), Ok("int: 8".to_string()));
+        assert_eq!(
+            run(&mut env, "{ c int 3 = : (c) } unwrap unwrap"),
+            Ok("int: 3".to_string())
+        );
+    }
+
+    #[test]
+    fn recursion_test() {
+        let stack_size = Environment::new().stack_size().unwrap();
+
+        // Deep recursion needs the stack the interpreter runs on
+        let test = move || {
+            let mut env = Environment::new();
+
+            run(&mut env, "tail (n int) =: n 0 == : (0) : (n 1 - tail) if").unwrap();
+            run(
+                &mut env,
+                "nested (n int) =: n 0 == : (0) : (n 1 - nested 1 +) if",
+            )
+            .unwrap();
+            assert_eq!(run(&mut env, "10 nested"), Ok("int: 10".to_string()));
+
+            // The stack has room for every call up to the limit
+            assert_eq!(run(&mut env, "999 nested"), Ok("int: 999".to_string()));
+            assert!(run(&mut env, "1000 nested").is_err());
+
+            // Recursion past the limit is an error rather than a stack overflow
+            let output = run(&mut env, "5000 nested").unwrap_err();
+            assert_eq!(
+                output.message,
+                "`nested` went past the recursion limit of 1000 calls"
+            );
+
+            // Tail calls run in constant stack, so they go past the limit
+            assert_eq!(run(&mut env, "5000 tail"), Ok("int: 0".to_string()));
+        };
+
+        std::thread::Builder::new()
+            .stack_size(stack_size)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn conditional_test() {
+        let mut env = Environment::new();
+        assert_eq!(run(&mut env, "1 2 < 3 4 if"), Ok("int: 3".to_string()));
+        assert_eq!(
+            run(&mut env, "0 : (1 0 /) : (2) if"),
+            Ok("int: 2".to_string())
+        );
+
+        // Only the chosen branch is evaluated
+        run(&mut env, "f (x dec) =: x 0 < : (0 x -) : (x) if").unwrap();
+        assert_eq!(run(&mut env, "0 2.5 - f"), Ok("dec: 2.5".to_string()));
+
+        run(&mut env, "h (x dec) =: x match (..0: 0, _: 1)").unwrap();
+        assert_eq!(run(&mut env, "0 2 - h"), Ok("int: 0".to_string()));
+        assert_eq!(run(&mut env, "3 h"), Ok("int: 1".to_string()));
+
+        run(
+            &mut env,
+            "g (x option<int>) =: x match (some v: v 1 +, none: 0)",
+        )
+        .unwrap();
+        assert_eq!(run(&mut env, "5 g"), Ok("int: 6".to_string()));
+        assert_eq!(run(&mut env, "nil g"), Ok("int: 0".to_string()));
+        assert!(run(&mut env, "5 match (0..3: 1)").is_err());
+
+        // A tail call in a branch runs in constant stack
+        run(
+            &mut env,
+            "sum (n int, acc int) =: n 0 == : (acc) : (n 1 - n acc + sum) if",
+        )
+        .unwrap();
+        assert_eq!(
+            run(&mut env, "100000 0 sum"),
+            Ok("int: 5000050000".to_string())
+        );
+    }
+
+    #[test]
+    fn loop_test() {
+        let mut env = Environment::new();
+        run(&mut env, "total int 0 =").unwrap();
+        run(&mut env, "for i in 0..5 { total i += }").unwrap();
+        assert_eq!(run(&mut env, "total"), Ok("int: 10".to_string()));
+
+        // The loop variable is cast to its type, and the result of `+=` keeps the promoted type
+        run(&mut env, "half int 0 =").unwrap();
+        run(&mut env, "for i: ratio in 1..3 { half i 2 / += }").unwrap();
+        assert_eq!(run(&mut env, "half"), Ok("ratio: 3 / 2".to_string()));
+
+        run(&mut env, "n int 1 =").unwrap();
+        run(&mut env, "while n 100 < { n 2 *= }").unwrap();
+        assert_eq!(run(&mut env, "n"), Ok("int: 128".to_string()));
+        assert_eq!(run(&mut env, "n 28 -="), Ok("int: 100".to_string()));
+
+        // `break` leaves the loop and `continue` skips the rest of the body
+        run(&mut env, "vowels int 0 =").unwrap();
+        run(&mut env, "for c in \"basis!\" { c \"!\" == : (break) : (nil) if c \"s\" == : (continue) : (nil) if vowels 1 += }").unwrap();
+        assert_eq!(run(&mut env, "vowels"), Ok("int: 3".to_string()));
+        run(&mut env, "i int 0 =").unwrap();
+        run(
+            &mut env,
+            "while true { i 1 += i 7 == : (break) : (nil) if }",
+        )
+        .unwrap();
+        assert_eq!(run(&mut env, "i"), Ok("int: 7".to_string()));
+
+        // Declarations in the body are gone after each pass
+        run(&mut env, "for i in 0..2 { inner int i = }").unwrap();
+        assert!(run(&mut env, "inner").is_err());
+
+        // A function can loop, and `break` does not leave a loop in its caller
+        run(
+            &mut env,
+            "triangle (n int) =: k int 0 = for i in 0..n 1 + { k i += } k",
+        )
+        .unwrap();
+        assert_eq!(run(&mut env, "4 triangle"), Ok("int: 10".to_string()));
+        run(&mut env, "stop (x int) =: break").unwrap();
+        assert!(run(&mut env, "for i in 0..3 { i stop }").is_err());
+
+        assert!(run(&mut env, "break").is_err());
+        assert!(run(&mut env, "missing 1 +=").is_err());
+        assert!(run(&mut env, "for i in 0..1.5 { }").is_err());
+    }
+
+    #[test]
+    fn vector_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "[1 2 3]"),
+            Ok("vector[int]: [1 2 3] -> [size: 1x3]".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "[1 2] [2 3] -"),
+            Ok("vector[int]: [-1 -1] -> [size: 1x2]".to_string())
+        );
+
+        // The dot product is a row times a column
+        assert_eq!(
+            run(&mut env, "[1 2 3] [2 3 4] transpose *"),
+            Ok("vector[int]: [20] -> [size: 1x1]".to_string())
+        );
+
+        // A number is spread across every element
+        assert_eq!(
+            run(&mut env, "[100, 40, 10] 8 /"),
+            Ok("vector[ratio]: [25/2 5 5/4] -> [size: 1x3]".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "2 [1 2.5] *"),
+            Ok("vector[dec]: [2 5] -> [size: 1x2]".to_string())
+        );
+
+        assert_eq!(
+            run(&mut env, "[1 2] [1 2 3] +"),
+            Err(BasisError::new(
+                "Cannot combine a 1x2 vector with a 1x3 vector"
+            ))
+        );
+        assert!(run(&mut env, "[1 2] [3 4] *").is_err());
+        assert!(run(&mut env, "[1 \"a\"]").is_err());
+
+        assert_eq!(
+            run(&mut env, "a vector[dec] [1 2] ="),
+            Ok("vector[dec]: [1 2] -> [size: 1x2]".to_string())
+        );
+        assert_eq!(run(&mut env, "a size"), Ok("size: 1x2".to_string()));
+
+        run(&mut env, "f (v vector[int]) =: v 8 *").unwrap();
+        assert_eq!(
+            run(&mut env, "[1 2] f"),
+            Ok("vector[int]: [8 16] -> [size: 1x2]".to_string())
+        );
+        assert!(run(&mut env, "2 f").is_err());
+
+        // `;` starts a new row, and `*` between vectors is the matrix product
+        assert_eq!(
+            run(&mut env, "[1 2 3; 2 3 4; 3 4 5]"),
+            Ok("vector[int]: [[1 2 3]  [2 3 4]  [3 4 5]] -> [size: 3x3]".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "[1 2; 3 4] [5; 6] *"),
+            Ok("vector[int]: [[17]  [39]] -> [size: 2x1]".to_string())
+        );
+        assert_eq!(run(&mut env, "[2 1; 1 1] det"), Ok("int: 1".to_string()));
+        assert_eq!(
+            run(&mut env, "[2 0; 0 4] inverse"),
+            Ok("vector[ratio]: [[1/2 0]  [0 1/4]] -> [size: 2x2]".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "[1 1; 1 2] [3 4] solve"),
+            Ok("vector[ratio]: [2 1] -> [size: 1x2]".to_string())
+        );
+        assert_eq!(run(&mut env, "[1 2; 2 4] rank"), Ok("int: 1".to_string()));
+        assert_eq!(
+            run(&mut env, "[0 1; 1 0] lu"),
+            Ok(
+                "tuple[vector]: ([[0 1]  [1 0]] [[1 0]  [0 1]] [[1 0]  [0 1]]) -> (size: 3)"
+                    .to_string()
+            )
+        );
+        assert!(run(&mut env, "[1 2; 3] det").is_err());
+        assert!(run(&mut env, "5 det").is_err());
+
+        // The numerical functions give decs
+        assert_eq!(
+            run(&mut env, "[0 1; 1 0] eigvals"),
+            Ok("vector[dec]: [-1 1] -> [size: 1x2]".to_string())
+        );
+        assert_eq!(run(&mut env, "[3 4] norm"), Ok("dec: 5".to_string()));
+        assert_eq!(
+            run(&mut env, "[1 2; 3 4] 1 pnorm"),
+            Ok("dec: 6".to_string())
+        );
+        assert!(run(&mut env, "[1 2; 3 4] 3 pnorm").is_err());
+        assert!(run(&mut env, "[1 0; 0 2] svd").is_ok());
+        assert!(run(&mut env, "[1 1; 1 2; 1 3] [1 2 2] lstsq").is_ok());
+    }
+
+    #[test]
+    fn container_test() {
+        let mut env = Environment::new();
+        assert_eq!(
+            run(&mut env, "(2 3 4 5 5)"),
+            Ok("tuple[int]: (2 3 4 5 5) -> (size: 5)".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "{2 3 4 5 5}"),
+            Ok("set[int]: {2 3 4 5} -> {size: 4}".to_string())
+        );
+
+        // A single expression is still a group or a closure
+        assert_eq!(run(&mut env, "(1 1 +) 2 *"), Ok("int: 4".to_string()));
+        assert_eq!(run(&mut env, "{ 1 1 + } unwrap"), Ok("int: 2".to_string()));
+
+        // Commas split the elements into whole expressions
+        assert_eq!(
+            run(&mut env, "(1 2 +, 4)"),
+            Ok("tuple[int]: (3 4) -> (size: 2)".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "{5,}"),
+            Ok("set[int]: {5} -> {size: 1}".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2.5)"),
+            Ok("tuple[number]: (1 2.5) -> (size: 2)".to_string())
+        );
+
+        // Numbers of different types are the same element when they are equal, and the first
+        // one is kept
+        assert_eq!(
+            run(&mut env, "{1 1.0 2}"),
+            Ok("set[int]: {1 2} -> {size: 2}".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "{1 2 3} {3 4} union"),
+            Ok("set[int]: {1 2 3 4} -> {size: 4}".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "{1 2 3} {3 4} intersection"),
+            Ok("set[int]: {3} -> {size: 1}".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "{1 2 3} {3 4} difference"),
+            Ok("set[int]: {1 2} -> {size: 2}".to_string())
+        );
+        assert_eq!(run(&mut env, "2 {1 2 3} in"), Ok("bool: true".to_string()));
+        assert_eq!(run(&mut env, "5 (1 2 3) in"), Ok("bool: false".to_string()));
+        assert_eq!(
+            run(&mut env, "{1 2} {2 1} =="),
+            Ok("bool: true".to_string())
+        );
+        assert!(run(&mut env, "{1 2} (2 3) union").is_err());
+
+        // Any container can be cast to a tuple or set
+        assert_eq!(
+            run(&mut env, "[1 2 2] set"),
+            Ok("set[int]: {1 2} -> {size: 2}".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "s set[dec] (1 2 1) ="),
+            Ok("set[dec]: {1 2} -> {size: 2}".to_string())
+        );
+
+        run(&mut env, "total int 0 =").unwrap();
+        run(&mut env, "for x in {1 2 2 3} { total x += }").unwrap();
+        assert_eq!(run(&mut env, "total"), Ok("int: 6".to_string()));
+
+        // Every container has a length, an index and an order
+        assert_eq!(run(&mut env, "{1 2 2} len"), Ok("int: 2".to_string()));
+        assert_eq!(run(&mut env, "[4 5 6] 1 at"), Ok("int: 5".to_string()));
+        assert!(run(&mut env, "(4 5 6) 3 at").is_err());
+        assert_eq!(
+            run(&mut env, "(3 1 2) sort"),
+            Ok("tuple[int]: (1 2 3) -> (size: 3)".to_string())
+        );
+        assert!(run(&mut env, "5 len").is_err());
+
+        // A `container` parameter takes any container and casts its elements
+        run(&mut env, "average (c container[ratio]) =: total ratio 0 = for x in c { total x += } total c len /")
+            .unwrap();
+        assert_eq!(run(&mut env, "[1 2 3] average"), Ok("ratio: 2".to_string()));
+        assert_eq!(
+            run(&mut env, "(1 2 4) average"),
+            Ok("ratio: 7 / 3".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "{1 2 2} average"),
+            Ok("ratio: 3 / 2".to_string())
+        );
+        assert!(run(&mut env, "5 average").is_err());
+    }
+
+    #[test]
+    fn higher_order_test() {
+        let mut env = Environment::new();
+        run(&mut env, "f (x int) =: x 1 +").unwrap();
+
+        assert_eq!(
+            run(&mut env, "[1 2 3] f map"),
+            Ok("vector[int]: [2 3 4] -> [size: 1x3]".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3 4) : (x 2 >) filter"),
+            Err(BasisError::new(
+                "The closure `x 2 >` takes 0 arguments but got 1"
+            ))
+        );
+        assert_eq!(
+            run(&mut env, "{1 2 3 4} (2 >) filter"),
+            Ok("set[int]: {3 4} -> {size: 2}".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3) 0 add fold"),
+            Ok("int: 6".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "[1 2 3] 1 mul fold"),
+            Ok("int: 6".to_string())
+        );
+        assert_eq!(run(&mut env, "3 4 sub"), Ok("int: -1".to_string()));
+
+        // A group missing arguments on its left is a partial application
+        assert_eq!(run(&mut env, "(2 *)"), Ok("closure: (2 *)".to_string()));
+        assert_eq!(
+            run(&mut env, "[1 2 3] (2 *) map"),
+            Ok("vector[int]: [2 4 6] -> [size: 1x3]".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 4 9) (sqrt) map"),
+            Ok("tuple[dec]: (1 2 3) -> (size: 3)".to_string())
+        );
+        assert_eq!(run(&mut env, "(3 5) 0 (+) fold"), Ok("int: 8".to_string()));
+
+        // `f g compose` runs `f` and then `g`
+        assert_eq!(
+            run(&mut env, "f (2 *) compose"),
+            Ok("closure: f (2 *) compose".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "[1 2 3] f (2 *) compose map"),
+            Ok("vector[int]: [4 6 8] -> [size: 1x3]".to_string())
+        );
+        run(&mut env, "g closure f f compose =").unwrap();
+        assert_eq!(run(&mut env, "1 g"), Ok("int: 3".to_string()));
+        assert_eq!(
+            run(&mut env, "(1 +) (2 *) compose"),
+            Ok("closure: (1 +) (2 *) compose".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "f f compose (2 *) compose"),
+            Ok("closure: (f f compose) (2 *) compose".to_string())
+        );
+        assert_eq!(run(&mut env, "g"), Ok("closure: f f compose".to_string()));
+
+        assert!(run(&mut env, "1 f map").is_err());
+        assert!(run(&mut env, "[1 2] add map").is_err());
+    }
+
+    #[test]
+    fn statistics_test() {
+        let mut env = Environment::new();
+        assert_eq!(run(&mut env, "[1 2 3] mean"), Ok("ratio: 2".to_string()));
+        assert_eq!(
+            run(&mut env, "(1 2 4) mean"),
+            Ok("ratio: 7 / 3".to_string())
+        );
+        assert_eq!(run(&mut env, "(1.5 2.5) mean"), Ok("dec: 2".to_string()));
+        assert_eq!(
+            run(&mut env, "{4 1 3 2} median"),
+            Ok("ratio: 5 / 2".to_string())
+        );
+        assert_eq!(run(&mut env, "(1 2 2 3) mode"), Ok("int: 2".to_string()));
+
+        // A mean written as a function needs a name that is not a builtin
+        run(
+            &mut env,
+            "average (c container[ratio]) =: { total ratio 0 = count int 0 = for x: ratio in c { total x += count 1 += } total count / } unwrap",
+        )
+        .unwrap();
+        assert_eq!(
+            run(&mut env, "(1 2 4) average"),
+            Ok("ratio: 7 / 3".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "range int 5 =").unwrap_err().message,
+            "Cannot assign to builtin `range`"
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3 4) variance"),
+            Ok("ratio: 5 / 4".to_string())
+        );
+        assert_eq!(run(&mut env, "(1 3) stdev"), Ok("ratio: 1".to_string()));
+        assert_eq!(run(&mut env, "[5 1 9] range"), Ok("int: 8".to_string()));
+        assert_eq!(
+            run(&mut env, "(1 2 3 4 5) 1 4 / quantile"),
+            Ok("int: 2".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3) (2 4 7) covariance"),
+            Ok("ratio: 5 / 3".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "[1 2 3] [3 2 1] correlation"),
+            Ok("ratio: -1".to_string())
+        );
+
+        assert!(run(&mut env, "(1 true) mean").is_err());
+        assert!(run(&mut env, "5 mean").is_err());
+        assert!(run(&mut env, "(1 2) 3 quantile").is_err());
+
+        // A sample divides by `n - 1` where a population divides by `n`
+        assert_eq!(
+            run(&mut env, "(1 2 3) sample"),
+            Ok("sample: (1 2 3) -> (size: 3)".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "[1 2 4] population"),
+            Ok("population: (1 2 4) -> (size: 3)".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3 4) sample variance"),
+            Ok("ratio: 5 / 3".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3 4) population variance"),
+            Ok("ratio: 5 / 4".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 3) sample stdev"),
+            Ok("dec: 1.4142135623730951".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3) sample (1 2 4) sample covariance"),
+            Ok("ratio: 3 / 2".to_string())
+        );
+
+        // A plain container takes the kind of the other, whichever side it is on
+        assert_eq!(
+            run(&mut env, "(1 2 3) (1 5 7) sample covariance"),
+            Ok("ratio: 3".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3) sample (1 5 7) covariance"),
+            Ok("ratio: 3".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3) population (1 5 7) covariance"),
+            Ok("ratio: 2".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3) sample (1 5 7) population covariance")
+                .unwrap_err()
+                .message,
+            "Cannot take the covariance of a sample and a population"
+        );
+        assert!(run(&mut env, "(1 2 3) population (1 5 7) sample correlation").is_err());
+        assert_eq!(
+            run(&mut env, "(4 1 2) sample sort"),
+            Ok("sample: (1 2 4) -> (size: 3)".to_string())
+        );
+        assert_eq!(
+            run(&mut env, "(1 2 3) sample len"),
+            Ok("int: 3".to_string())
+        );
+
+        run(&mut env, "a sample (1 2 3) =").unwrap();
+        assert_eq!(run(&mut env, "a mean"), Ok("ratio: 2".to_string()));
+
+        assert!(run(&mut env, "(1 true) sample").is_err());
+        assert!(run(&mut env, "(1,) sample variance").is_err());
+    }
 }
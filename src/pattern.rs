@@ -0,0 +1,209 @@
+use crate::lexer::{Token, TokenType};
+use crate::number::Number;
+use crate::parser::Expression;
+use crate::value::Value;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The left side of an arm in `x match (nil: 0, 0..10: 1, _: 2)`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    // `_` matches anything
+    Wildcard,
+
+    // `nil` and `none` match nil and empty options
+    Nil,
+
+    // `some` matches an option with a value, and `some v` names the value `v`
+    Some(Option<String>),
+
+    // `2`, `"a"` or `true` match an equal value, where numbers of any type are compared by value
+    Value(Value),
+
+    // `0..10` matches numbers from the start up to but not including the end, where either end
+    // can be left out like `10..`
+    Range {
+        start: Option<Number>,
+        end: Option<Number>,
+    },
+}
+
+/// A number written in a pattern, which can start with a `-` like `-5`
+fn number_of(tokens: &[Token]) -> Option<Number> {
+    let (negative, token) = match tokens {
+        [token] => (false, token),
+        [sign, token] if sign.token_type == TokenType::Subtraction => (true, token),
+        _ => return None,
+    };
+
+    let number = match Value::from_expression(Expression::from_token(token.clone())) {
+        Value::Number(number) => number,
+        _ => return None,
+    };
+
+    if negative {
+        return number
+            .operate(Number::Int(-1), &TokenType::Multiplication)
+            .ok();
+    }
+
+    Some(number)
+}
+
+impl Pattern {
+    pub fn parse(tokens: &[Token]) -> Option<Pattern> {
+        let range = tokens
+            .windows(2)
+            .position(|w| w.iter().all(|t| t.token_type == TokenType::Dot));
+
+        if let Some(split) = range {
+            let (start, end) = (&tokens[..split], &tokens[split + 2..]);
+            if start.is_empty() && end.is_empty() {
+                return None;
+            }
+
+            let bound = |side: &[Token]| match side {
+                [] => Some(None),
+                side => number_of(side).map(Some),
+            };
+
+            return Some(Pattern::Range {
+                start: bound(start)?,
+                end: bound(end)?,
+            });
+        }
+
+        match tokens {
+            [token] if token.value == "_" => Some(Pattern::Wildcard),
+            [token] if token.token_type == TokenType::NilLiteral || token.value == "none" => {
+                Some(Pattern::Nil)
+            }
+            [token] if token.value == "some" => Some(Pattern::Some(None)),
+            [token, name] if token.value == "some" && name.token_type == TokenType::Identifier => {
+                Some(Pattern::Some(Some(name.value.clone())))
+            }
+            [token] if token.token_type == TokenType::StringLiteral => {
+                Some(Pattern::Value(Value::String(token.value.clone())))
+            }
+            [token] if token.token_type == TokenType::BoolLiteral => {
+                Some(Pattern::Value(Value::Bool(token.value == "true")))
+            }
+            tokens => number_of(tokens).map(|n| Pattern::Value(Value::Number(n))),
+        }
+    }
+
+    pub fn matches(&self, value: &Value) -> bool {
+        let number = match value.clone().without_estimate() {
+            Value::Number(number) => Some(number),
+            Value::Literal(_) => value.as_dec().ok().map(Number::Dec),
+            _ => None,
+        };
+
+        match (self, value) {
+            (Pattern::Wildcard, _) => true,
+            (Pattern::Nil, Value::Nil | Value::Option(None)) => true,
+            (Pattern::Nil, _) => false,
+            (Pattern::Some(_), value) => matches!(value, Value::Option(Some(_))),
+            (Pattern::Value(Value::Number(expected)), _) => {
+                number.is_some_and(|n| n.compare(expected.clone()) == Some(Ordering::Equal))
+            }
+            (Pattern::Value(expected), value) => expected == value,
+            (Pattern::Range { start, end }, _) => {
+                let Some(number) = number else {
+                    return false;
+                };
+
+                let after_start = start.as_ref().is_none_or(|s| {
+                    matches!(
+                        number.clone().compare(s.clone()),
+                        Some(Ordering::Greater | Ordering::Equal)
+                    )
+                });
+
+                let before_end = end
+                    .as_ref()
+                    .is_none_or(|e| number.clone().compare(e.clone()) == Some(Ordering::Less));
+
+                after_start && before_end
+            }
+        }
+    }
+
+    /// The name a pattern gives to part of the value, like `v` in `some v`
+    pub fn binding(&self, value: &Value) -> Option<(String, Value)> {
+        match (self, value) {
+            (Pattern::Some(Some(name)), Value::Option(Some(inner))) => {
+                Some((name.clone(), inner.as_ref().clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Numbers are written as they are typed, like `3` rather than `int: 3`
+        let bound = |n: &Option<Number>| match n {
+            Some(n) => Value::Number(n.clone()).to_expression().source(),
+            None => String::new(),
+        };
+
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Nil => write!(f, "nil"),
+            Pattern::Some(None) => write!(f, "some"),
+            Pattern::Some(Some(name)) => write!(f, "some {}", name),
+            Pattern::Value(value) => write!(f, "{}", value.to_expression().source()),
+            Pattern::Range { start, end } => write!(f, "{}..{}", bound(start), bound(end)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{Lex, Lexer};
+
+    fn pattern(text: &str) -> Option<Pattern> {
+        let mut lex = Lexer::new(vec![text.to_string()]);
+        let mut tokens = Vec::<Token>::new();
+        loop {
+            let token = lex.next();
+            if token.token_type == TokenType::EndToken {
+                break;
+            }
+            tokens.push(token);
+        }
+
+        Pattern::parse(&tokens)
+    }
+
+    #[test]
+    fn pattern_test() {
+        let small = pattern("0..10").unwrap();
+        assert!(small.matches(&Value::int(0)));
+        assert!(small.matches(&Value::dec(9.5)));
+        assert!(!small.matches(&Value::int(10)));
+        assert!(!small.matches(&Value::String("a".to_string())));
+
+        let negative = pattern("..0").unwrap();
+        assert!(negative.matches(&Value::int(-3)));
+        assert!(pattern("-5..").unwrap().matches(&Value::int(-5)));
+
+        assert!(pattern("2").unwrap().matches(&Value::dec(2.0)));
+        assert!(pattern("_").unwrap().matches(&Value::Nil));
+        assert!(pattern("none").unwrap().matches(&Value::Option(None)));
+        assert!(pattern("nil").unwrap().matches(&Value::Nil));
+
+        let some = pattern("some v").unwrap();
+        let option = Value::int(5).into_option();
+        assert!(some.matches(&option));
+        assert_eq!(
+            some.binding(&option),
+            Some(("v".to_string(), Value::int(5)))
+        );
+
+        assert_eq!(pattern("1..2..3"), None);
+        assert_eq!(pattern("-5..10").unwrap().to_string(), "-5..10");
+    }
+}
@@ -0,0 +1,411 @@
+use crate::error::BasisError;
+use crate::number::Ratio;
+use num_bigint::BigInt;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The number of significant digits a real keeps unless given another precision with `prec`
+pub const DEFAULT_PRECISION: u32 = 50;
+
+/// The largest power of ten a real can be scaled by either way, which keeps the digits that
+/// line up two reals for `+` from taking more memory than there is
+pub const MAX_EXPONENT: i64 = 100_000;
+
+/// The most significant digits a real can keep. A quotient like `1 3 /` has an exponent as
+/// large as its precision, so this stays well inside `MAX_EXPONENT`.
+pub const MAX_PRECISION: u32 = 10_000;
+
+fn ten_to(power: u32) -> BigInt {
+    BigInt::from(10u8).pow(power)
+}
+
+fn digit_count(value: &BigInt) -> u32 {
+    if *value == BigInt::from(0u8) {
+        return 0;
+    }
+
+    value.magnitude().to_str_radix(10).len() as u32
+}
+
+/// An arbitrary precision decimal `mantissa * 10^exponent`, rounded to `precision` significant
+/// digits after every operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Real {
+    pub mantissa: BigInt,
+    pub exponent: i64,
+    pub precision: u32,
+}
+
+impl Real {
+    /// Round to the precision and drop trailing zeros so every value has one representation
+    pub fn new(mantissa: BigInt, exponent: i64, precision: u32) -> Self {
+        let precision = precision.max(1);
+        let zero = BigInt::from(0u8);
+        let mut mantissa = mantissa;
+        let mut exponent = exponent;
+
+        let digits = digit_count(&mantissa);
+        if digits > precision {
+            let drop = digits - precision;
+            let divisor = ten_to(drop);
+            let remainder = &mantissa % &divisor;
+            mantissa /= &divisor;
+
+            // Round half away from zero
+            if remainder.magnitude() * 2u8 >= *divisor.magnitude() {
+                if remainder < zero {
+                    mantissa -= 1;
+                } else {
+                    mantissa += 1;
+                }
+            }
+
+            exponent += drop as i64;
+        }
+
+        if mantissa == zero {
+            return Real {
+                mantissa,
+                exponent: 0,
+                precision,
+            };
+        }
+
+        let ten = BigInt::from(10u8);
+        while &mantissa % &ten == zero {
+            mantissa /= &ten;
+            exponent += 1;
+        }
+
+        Real {
+            mantissa,
+            exponent,
+            precision,
+        }
+    }
+
+    pub fn int(value: i64, precision: u32) -> Self {
+        Real::new(BigInt::from(value), 0, precision)
+    }
+
+    pub fn from_ratio(value: &Ratio, precision: u32) -> Self {
+        Real::int(value.numerator, precision)
+            .div(&Real::int(value.denominator, precision))
+            .expect("A ratio never has a zero denominator")
+    }
+
+    /// The shortest real that reads back as the same dec, so `0.1` stays `0.1`
+    pub fn from_dec(value: f64, precision: u32) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        Real::parse(&format!("{}", value), precision)
+    }
+
+    /// Read a decimal like `-12.5`
+    pub fn parse(text: &str, precision: u32) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty()
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+
+        let mut mantissa = format!("{}{}", whole, fraction).parse::<BigInt>().ok()?;
+        if negative {
+            mantissa = -mantissa;
+        }
+
+        Some(Real::new(mantissa, -(fraction.len() as i64), precision))
+    }
+
+    /// Give a result the larger precision of the two values
+    fn with(&self, other: &Real, mantissa: BigInt, exponent: i64) -> Real {
+        Real::new(mantissa, exponent, self.precision.max(other.precision))
+    }
+
+    /// Give back an error for a result whose exponent is past the cap
+    fn capped(self) -> Result<Real, BasisError> {
+        match self.exponent.abs() > MAX_EXPONENT {
+            true => Err(BasisError::new(&format!(
+                "A real must be between 10^-{} and 10^{}",
+                MAX_EXPONENT, MAX_EXPONENT
+            ))),
+            false => Ok(self),
+        }
+    }
+
+    /// Both mantissas scaled to the smaller exponent
+    fn aligned(&self, other: &Real) -> (BigInt, BigInt, i64) {
+        let exponent = self.exponent.min(other.exponent);
+        let scale = |e: i64| {
+            ten_to(u32::try_from(e - exponent).expect("Exponents are capped, so the gap fits"))
+        };
+
+        (
+            &self.mantissa * scale(self.exponent),
+            &other.mantissa * scale(other.exponent),
+            exponent,
+        )
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == BigInt::from(0u8)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa < BigInt::from(0u8)
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.exponent >= 0
+    }
+
+    pub fn add(&self, other: &Real) -> Real {
+        let (a, b, exponent) = self.aligned(other);
+        self.with(other, a + b, exponent)
+    }
+
+    pub fn sub(&self, other: &Real) -> Real {
+        let (a, b, exponent) = self.aligned(other);
+        self.with(other, a - b, exponent)
+    }
+
+    pub fn mul(&self, other: &Real) -> Result<Real, BasisError> {
+        self.with(
+            other,
+            &self.mantissa * &other.mantissa,
+            self.exponent + other.exponent,
+        )
+        .capped()
+    }
+
+    pub fn div(&self, other: &Real) -> Result<Real, BasisError> {
+        if other.is_zero() {
+            return Err(BasisError::new("Division by zero"));
+        }
+
+        // Scale up so the quotient has a couple of digits more than the precision to round
+        let precision = self.precision.max(other.precision);
+        let shift = precision + digit_count(&other.mantissa) + 2;
+        let mantissa = &self.mantissa * ten_to(shift) / &other.mantissa;
+
+        self.with(
+            other,
+            mantissa,
+            self.exponent - other.exponent - shift as i64,
+        )
+        .capped()
+    }
+
+    pub fn neg(&self) -> Real {
+        Real::new(-&self.mantissa, self.exponent, self.precision)
+    }
+
+    pub fn abs(&self) -> Real {
+        Real::new(
+            BigInt::from(self.mantissa.magnitude().clone()),
+            self.exponent,
+            self.precision,
+        )
+    }
+
+    /// Gives back an error for zero to a negative power, or when the result is past the cap
+    pub fn pow(&self, exponent: i64) -> Result<Real, BasisError> {
+        let mut out = Real::int(1, self.precision);
+        let mut base = self.clone();
+        let mut n = exponent.unsigned_abs();
+
+        while n > 0 {
+            if n & 1 == 1 {
+                out = out.mul(&base)?;
+            }
+
+            // The base is only squared when it is used again, so it cannot pass the cap early
+            n >>= 1;
+            if n > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+
+        if exponent < 0 {
+            return Real::int(1, self.precision).div(&out);
+        }
+
+        Ok(out)
+    }
+
+    /// Gives back `None` for a negative number
+    pub fn sqrt(&self) -> Option<Real> {
+        if self.is_negative() {
+            return None;
+        }
+
+        // Scale up to twice the precision with an even exponent, so the integer square root
+        // has every digit needed
+        let mut shift = (2 * self.precision + 2).saturating_sub(digit_count(&self.mantissa));
+        if (self.exponent - shift as i64) % 2 != 0 {
+            shift += 1;
+        }
+
+        let mantissa = (&self.mantissa * ten_to(shift)).sqrt();
+        Some(Real::new(
+            mantissa,
+            (self.exponent - shift as i64) / 2,
+            self.precision,
+        ))
+    }
+
+    /// A whole real as an int, if it fits
+    pub fn to_int(&self) -> Option<i64> {
+        if !self.is_whole() {
+            return None;
+        }
+
+        i64::try_from(&self.mantissa * ten_to(u32::try_from(self.exponent).ok()?)).ok()
+    }
+
+    /// The exact ratio, if the numerator and denominator fit
+    pub fn to_ratio(&self) -> Option<Ratio> {
+        if self.is_whole() {
+            return Some(Ratio::whole(self.to_int()?));
+        }
+
+        let denominator = i64::try_from(ten_to(u32::try_from(-self.exponent).ok()?)).ok()?;
+        Ratio::new(i64::try_from(&self.mantissa).ok()?, denominator)
+    }
+
+    pub fn to_dec(&self) -> f64 {
+        self.to_string().parse::<f64>().unwrap_or(f64::NAN)
+    }
+
+    /// Compare the values, ignoring precision
+    pub fn compare(&self, other: &Real) -> Ordering {
+        let (a, b, _) = self.aligned(other);
+        a.cmp(&b)
+    }
+}
+
+impl fmt::Display for Real {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let digits = self.mantissa.magnitude().to_str_radix(10);
+
+        // Past the precision the zeros are not significant, so they are written as a power of
+        // ten like `1.5e400` rather than spelled out
+        let zeros = match self.exponent >= 0 {
+            true => self.exponent,
+            false => -self.exponent - digits.len() as i64,
+        };
+
+        if zeros > i64::from(self.precision) {
+            let (first, rest) = digits.split_at(1);
+            let power = self.exponent + digits.len() as i64 - 1;
+            return match rest.is_empty() {
+                true => write!(f, "{}{}e{}", sign, first, power),
+                false => write!(f, "{}{}.{}e{}", sign, first, rest, power),
+            };
+        }
+
+        if self.exponent >= 0 {
+            return write!(
+                f,
+                "{}{}{}",
+                sign,
+                digits,
+                "0".repeat(self.exponent as usize)
+            );
+        }
+
+        let places = (-self.exponent) as usize;
+        if digits.len() > places {
+            let (whole, fraction) = digits.split_at(digits.len() - places);
+            write!(f, "{}{}.{}", sign, whole, fraction)
+        } else {
+            write!(
+                f,
+                "{}0.{}{}",
+                sign,
+                "0".repeat(places - digits.len()),
+                digits
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn real(text: &str) -> Real {
+        Real::parse(text, DEFAULT_PRECISION).unwrap()
+    }
+
+    #[test]
+    fn real_arithmetic_test() {
+        assert_eq!(real("0.1").add(&real("0.2")), real("0.3"));
+        assert_eq!(real("1.5").sub(&real("2")).to_string(), "-0.5");
+        assert_eq!(real("1.25").mul(&real("4")).unwrap().to_string(), "5");
+        assert_eq!(real("2").pow(-2).unwrap().to_string(), "0.25");
+        assert!(real("0").div(&real("0")).is_err());
+
+        // Results past the cap are errors rather than running out of memory
+        assert!(real("2").pow(1_000_000_000_000).is_err());
+        assert!(real("0.5").pow(1_000_000_000_000).is_err());
+
+        // Division rounds to the precision
+        let third = Real::int(1, 20).div(&Real::int(3, 20)).unwrap();
+        assert_eq!(third.to_string(), "0.33333333333333333333");
+
+        let two_thirds = Real::int(2, 5).div(&Real::int(3, 5)).unwrap();
+        assert_eq!(two_thirds.to_string(), "0.66667");
+    }
+
+    #[test]
+    fn real_sqrt_test() {
+        let root = Real::int(2, 30).sqrt().unwrap();
+        assert_eq!(root.to_string(), "1.41421356237309504880168872421");
+
+        assert_eq!(real("0.0004").sqrt().unwrap().to_string(), "0.02");
+        assert_eq!(real("-1").sqrt(), None);
+    }
+
+    #[test]
+    fn real_conversion_test() {
+        assert_eq!(Real::from_dec(0.1, DEFAULT_PRECISION), Some(real("0.1")));
+        assert_eq!(real("1200").to_string(), "1200");
+
+        // Zeros past the precision are written as a power of ten
+        assert_eq!(Real::int(10, 5).pow(60).unwrap().neg().to_string(), "-1e60");
+        assert_eq!(
+            real("1.5")
+                .mul(&real("10").pow(60).unwrap())
+                .unwrap()
+                .to_string(),
+            "1.5e60"
+        );
+        assert_eq!(
+            Real::int(1, 5)
+                .div(&Real::int(10, 5).pow(20).unwrap())
+                .unwrap()
+                .to_string(),
+            "1e-20"
+        );
+        assert_eq!(real("1200").to_int(), Some(1200));
+        assert_eq!(real("0.75").to_ratio(), Ratio::new(3, 4));
+        assert_eq!(real("0.5").to_int(), None);
+        assert_eq!(
+            Real::from_ratio(&Ratio::new(1, 8).unwrap(), DEFAULT_PRECISION),
+            real("0.125")
+        );
+    }
+}
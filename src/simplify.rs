@@ -0,0 +1,601 @@
+use crate::lexer::TokenType;
+use crate::number::Ratio;
+use crate::parser::{Expression, InnerCall, Operation};
+
+/// A base raised to an exponent, where the exponent is kept as a simplified sum so that
+/// `pi x ^ pi y ^ *` can collect into `pi^(x + y)`
+#[derive(Debug, Clone, PartialEq)]
+struct Factor {
+    base: Expression,
+    exponent: Vec<Term>,
+}
+
+/// A coefficient times a product of factors, like `2 pi^2`
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    coefficient: Ratio,
+    factors: Vec<Factor>,
+}
+
+impl Term {
+    fn constant(coefficient: Ratio) -> Self {
+        Term {
+            coefficient,
+            factors: vec![],
+        }
+    }
+
+    fn atom(base: Expression) -> Self {
+        Term {
+            coefficient: Ratio::whole(1),
+            factors: vec![Factor {
+                base,
+                exponent: vec![Term::constant(Ratio::whole(1))],
+            }],
+        }
+    }
+
+    fn normalize(mut self) -> Self {
+        self.factors.retain(|f| !f.exponent.is_empty());
+        self.factors.sort_by_key(|f| factor_order(&f.base));
+        self
+    }
+
+    fn mul(&self, other: &Term) -> Option<Term> {
+        let mut factors = self.factors.clone();
+
+        for factor in &other.factors {
+            match factors.iter_mut().find(|f| f.base == factor.base) {
+                Some(existing) => {
+                    let mut exponent = existing.exponent.clone();
+                    exponent.extend(factor.exponent.clone());
+                    existing.exponent = collect(exponent)?;
+                }
+                None => factors.push(factor.clone()),
+            }
+        }
+
+        let term = Term {
+            coefficient: self.coefficient.mul(&other.coefficient)?,
+            factors,
+        };
+
+        Some(term.normalize())
+    }
+
+    /// Raise to a power, giving back `None` when the result can't stay a single term
+    fn pow(&self, exponent: &[Term]) -> Option<Term> {
+        let coefficient = match constant_of(exponent) {
+            Some(k) if k.is_whole() => self.coefficient.pow(k.numerator)?,
+            _ if self.coefficient.is_one() => self.coefficient,
+            _ => return None,
+        };
+
+        let mut factors = Vec::<Factor>::new();
+        for factor in &self.factors {
+            factors.push(Factor {
+                base: factor.base.clone(),
+                exponent: multiply(&factor.exponent, exponent)?,
+            });
+        }
+
+        Some(
+            Term {
+                coefficient,
+                factors,
+            }
+            .normalize(),
+        )
+    }
+
+    fn negate(&self) -> Option<Term> {
+        self.mul(&Term::constant(Ratio::whole(-1)))
+    }
+
+    fn degree(&self) -> i64 {
+        self.factors
+            .iter()
+            .map(|f| match constant_of(&f.exponent) {
+                Some(k) => k.numerator / k.denominator,
+                None => 1,
+            })
+            .sum()
+    }
+}
+
+/// Sort plain names before compound factors and function calls, so `sin(x) x 2 *` reads as
+/// `2 x sin(x)`
+fn factor_order(base: &Expression) -> (u8, String) {
+    let rank = match base {
+        Expression::TokenExpression(_) => 0,
+        Expression::Call(_) => 2,
+        _ => 1,
+    };
+
+    (rank, math_notation(base))
+}
+
+/// The value of a sum if it is a plain number
+fn constant_of(sum: &[Term]) -> Option<Ratio> {
+    match sum {
+        [] => Some(Ratio::whole(0)),
+        [term] if term.factors.is_empty() => Some(term.coefficient),
+        _ => None,
+    }
+}
+
+/// Combine like terms and drop the ones that cancel out
+fn collect(terms: Vec<Term>) -> Option<Vec<Term>> {
+    let mut out = Vec::<Term>::new();
+
+    for term in terms {
+        match out.iter_mut().find(|t| t.factors == term.factors) {
+            Some(existing) => {
+                existing.coefficient = existing.coefficient.add(&term.coefficient)?;
+            }
+            None => out.push(term),
+        }
+    }
+
+    out.retain(|t| !t.coefficient.is_zero());
+    Some(out)
+}
+
+/// View a sum as a single term, wrapping it up as an atom if it has more than one
+fn as_term(sum: &[Term]) -> Term {
+    match sum {
+        [] => Term::constant(Ratio::whole(0)),
+        [term] => term.clone(),
+        _ => Term::atom(rebuild(sum)),
+    }
+}
+
+fn multiply(a: &[Term], b: &[Term]) -> Option<Vec<Term>> {
+    if a.is_empty() || b.is_empty() {
+        return Some(vec![]);
+    }
+
+    // A plain number scales every term of the other side
+    if let Some(k) = constant_of(a) {
+        return collect(
+            b.iter()
+                .map(|t| t.mul(&Term::constant(k)))
+                .collect::<Option<Vec<Term>>>()?,
+        );
+    }
+
+    if constant_of(b).is_some() {
+        return multiply(b, a);
+    }
+
+    collect(vec![as_term(a).mul(&as_term(b))?])
+}
+
+fn divide(a: &[Term], b: &[Term]) -> Option<Vec<Term>> {
+    // Division by an exact zero is reported before a literal is simplified, so it is only
+    // left alone here
+    if b.is_empty() {
+        return None;
+    }
+
+    let divisor = as_term(b);
+    let inverse = divisor.pow(&[Term::constant(Ratio::whole(-1))])?;
+
+    multiply(a, &[inverse])
+}
+
+fn power(a: &[Term], b: &[Term]) -> Option<Vec<Term>> {
+    if let Some(k) = constant_of(b) {
+        // x^0 is 1
+        if k.is_zero() {
+            return Some(vec![Term::constant(Ratio::whole(1))]);
+        }
+
+        // 0^x is 0 for positive x
+        if a.is_empty() {
+            return if k.numerator > 0 { Some(vec![]) } else { None };
+        }
+    }
+
+    if let Some(term) = as_term(a).pow(b) {
+        return collect(vec![term]);
+    }
+
+    Some(vec![Term::atom(Expression::binary(
+        rebuild(a),
+        rebuild(b),
+        TokenType::Power,
+    ))])
+}
+
+/// The exact value of a function where it is known, like `0 sin` or `e log`
+fn exact_call(name: &str, argument: &Expression) -> Option<Expression> {
+    let zero = Expression::int(0);
+    let one = Expression::int(1);
+
+    match (name, argument) {
+        ("sin", a) | ("tan", a) if *a == zero => Some(zero),
+        ("cos", a) | ("exp", a) if *a == zero => Some(one),
+        ("log", a) if *a == one => Some(zero),
+        ("log", a) if *a == Expression::identifier("e") => Some(one),
+
+        // exp and log undo each other
+        ("exp", Expression::Call(inner)) | ("log", Expression::Call(inner))
+            if inner.function.value != name
+                && matches!(inner.function.value.as_str(), "exp" | "log")
+                && inner.arguments.len() == 1 =>
+        {
+            Some(inner.arguments[0].clone())
+        }
+
+        _ => None,
+    }
+}
+
+fn to_sum(exp: &Expression) -> Option<Vec<Term>> {
+    match exp {
+        Expression::TokenExpression(t) if t.token.token_type == TokenType::NumericIntLiteral => {
+            let value = t.token.value.parse::<i64>().ok()?;
+            collect(vec![Term::constant(Ratio::whole(value))])
+        }
+
+        Expression::ExpExpOp(i) => {
+            let a = to_sum(&i.expression_1)?;
+            let b = to_sum(&i.expression_2)?;
+
+            let Operation::TokenOperation(c) = i.operation.as_ref();
+            match c.token.token_type {
+                TokenType::Addition => collect(a.into_iter().chain(b).collect()),
+                TokenType::Subtraction => {
+                    let negated = b
+                        .iter()
+                        .map(|t| t.negate())
+                        .collect::<Option<Vec<Term>>>()?;
+                    collect(a.into_iter().chain(negated).collect())
+                }
+                TokenType::Multiplication => multiply(&a, &b),
+                TokenType::Division => divide(&a, &b),
+                TokenType::Power => power(&a, &b),
+                _ => None,
+            }
+        }
+
+        Expression::Call(c) => {
+            let arguments: Vec<Expression> = c.arguments.iter().map(simplify).collect();
+
+            if let [argument] = arguments.as_slice() {
+                if let Some(exact) = exact_call(&c.function.value, argument) {
+                    return to_sum(&exact);
+                }
+            }
+
+            Some(vec![Term::atom(Expression::Call(InnerCall {
+                function: c.function.clone(),
+                arguments,
+            }))])
+        }
+
+        _ => Some(vec![Term::atom(exp.clone())]),
+    }
+}
+
+fn product(parts: Vec<Expression>) -> Option<Expression> {
+    parts
+        .into_iter()
+        .reduce(|a, b| Expression::binary(a, b, TokenType::Multiplication))
+}
+
+fn factor_expression(base: &Expression, exponent: &[Term]) -> Expression {
+    match constant_of(exponent) {
+        Some(k) if k.is_one() => base.clone(),
+        _ => Expression::binary(base.clone(), rebuild(exponent), TokenType::Power),
+    }
+}
+
+/// Whether a factor belongs under the fraction bar, like the `pi` in `pi -1 ^`
+fn is_denominator(factor: &Factor) -> bool {
+    match factor.exponent.as_slice() {
+        [term] => term.coefficient.numerator < 0,
+        _ => false,
+    }
+}
+
+fn rebuild_term(term: &Term) -> Expression {
+    let mut numerator = Vec::<Expression>::new();
+    let mut denominator = Vec::<Expression>::new();
+
+    let has_numerator = term.factors.iter().any(|f| !is_denominator(f));
+    if term.coefficient.numerator != 1 || !has_numerator {
+        numerator.push(Expression::int(term.coefficient.numerator));
+    }
+
+    if term.coefficient.denominator != 1 {
+        denominator.push(Expression::int(term.coefficient.denominator));
+    }
+
+    for factor in &term.factors {
+        if is_denominator(factor) {
+            let flipped = factor
+                .exponent
+                .iter()
+                .map(|t| t.negate())
+                .collect::<Option<Vec<Term>>>()
+                .unwrap_or_default();
+            denominator.push(factor_expression(&factor.base, &flipped));
+        } else {
+            numerator.push(factor_expression(&factor.base, &factor.exponent));
+        }
+    }
+
+    let numerator = product(numerator).unwrap_or(Expression::int(1));
+
+    match product(denominator) {
+        Some(denominator) => Expression::binary(numerator, denominator, TokenType::Division),
+        None => numerator,
+    }
+}
+
+fn rebuild(sum: &[Term]) -> Expression {
+    let mut terms = sum.to_vec();
+
+    // Highest degree first, with plain numbers at the end
+    terms.sort_by(|a, b| {
+        (
+            a.factors.is_empty(),
+            -a.degree(),
+            math_notation(&rebuild_term(a)),
+        )
+            .cmp(&(
+                b.factors.is_empty(),
+                -b.degree(),
+                math_notation(&rebuild_term(b)),
+            ))
+    });
+
+    let mut out: Option<Expression> = None;
+
+    for term in terms {
+        out = Some(match out {
+            None => rebuild_term(&term),
+            Some(acc) => {
+                if term.coefficient.numerator < 0 {
+                    let positive = term.negate().unwrap_or(term);
+                    Expression::binary(acc, rebuild_term(&positive), TokenType::Subtraction)
+                } else {
+                    Expression::binary(acc, rebuild_term(&term), TokenType::Addition)
+                }
+            }
+        });
+    }
+
+    out.unwrap_or(Expression::int(0))
+}
+
+/// Simplify an expression by folding exact numbers, removing identities like `x 0 +` and
+/// `x 1 *`, collecting like terms and combining powers of the same base. Anything that can't be
+/// simplified exactly, like division by zero, is left as it was.
+pub fn simplify(exp: &Expression) -> Expression {
+    match to_sum(exp) {
+        Some(sum) => rebuild(&sum),
+        None => exp.clone(),
+    }
+}
+
+fn precedence(exp: &Expression) -> u8 {
+    match exp.operation_type() {
+        Some(TokenType::Addition) | Some(TokenType::Subtraction) => 1,
+        Some(TokenType::Multiplication) | Some(TokenType::Division) => 2,
+        Some(TokenType::Power) => 3,
+        _ => 4,
+    }
+}
+
+fn is_number(exp: &Expression) -> bool {
+    match exp {
+        Expression::TokenExpression(t) => matches!(
+            t.token.token_type,
+            TokenType::NumericIntLiteral | TokenType::NumericDecLiteral
+        ),
+        _ => false,
+    }
+}
+
+fn is_negative_number(exp: &Expression) -> bool {
+    match exp {
+        Expression::TokenExpression(t) => is_number(exp) && t.token.value.starts_with('-'),
+        _ => false,
+    }
+}
+
+/// Wrap the notation in parentheses when the expression binds looser than `min`
+fn wrapped(exp: &Expression, min: u8) -> String {
+    if precedence(exp) < min || (min > 2 && is_negative_number(exp)) {
+        format!("({})", math_notation(exp))
+    } else {
+        math_notation(exp)
+    }
+}
+
+/// Write an expression in the usual infix math notation, like `2 pi` or `pi^2 / 2`
+pub fn math_notation(exp: &Expression) -> String {
+    match exp {
+        Expression::TokenExpression(t) => t.token.value.clone(),
+
+        Expression::ExpExpOp(i) => {
+            let a = &i.expression_1;
+            let b = &i.expression_2;
+
+            let Operation::TokenOperation(c) = i.operation.as_ref();
+            match c.token.token_type {
+                TokenType::Addition => format!("{} + {}", wrapped(a, 1), wrapped(b, 1)),
+                TokenType::Subtraction => format!("{} - {}", wrapped(a, 1), wrapped(b, 2)),
+                TokenType::Multiplication => {
+                    if is_number(b) {
+                        format!("{} * {}", wrapped(a, 2), wrapped(b, 3))
+                    } else if **a == Expression::int(-1) {
+                        format!("-{}", wrapped(b, 2))
+                    } else {
+                        format!("{} {}", wrapped(a, 2), wrapped(b, 2))
+                    }
+                }
+                TokenType::Division => format!("{} / {}", wrapped(a, 2), wrapped(b, 3)),
+                TokenType::Power => format!("{}^{}", wrapped(a, 4), wrapped(b, 4)),
+                _ => format!(
+                    "{} {} {}",
+                    math_notation(a),
+                    math_notation(b),
+                    c.token.value
+                ),
+            }
+        }
+
+        Expression::Call(c) => format!(
+            "{}({})",
+            c.function.value,
+            c.arguments
+                .iter()
+                .map(math_notation)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+
+        Expression::Vector(v) => format!(
+            "[{}]",
+            v.rows
+                .iter()
+                .map(|row| row
+                    .iter()
+                    .map(math_notation)
+                    .collect::<Vec<String>>()
+                    .join(" "))
+                .collect::<Vec<String>>()
+                .join("; ")
+        ),
+        Expression::Tuple(t) => format!(
+            "({})",
+            t.elements
+                .iter()
+                .map(math_notation)
+                .collect::<Vec<String>>()
+                .join(" ")
+        ),
+        Expression::Set(s) => format!(
+            "{{{}}}",
+            s.elements
+                .iter()
+                .map(math_notation)
+                .collect::<Vec<String>>()
+                .join(" ")
+        ),
+
+        Expression::Function(f) => f.name.value.clone(),
+        Expression::Closure(_) => exp.source(),
+        Expression::Sequence(s) => s.expressions.last().map(math_notation).unwrap_or_default(),
+        Expression::If(_)
+        | Expression::Match(_)
+        | Expression::For(_)
+        | Expression::While(_)
+        | Expression::CompoundAssignment(_)
+        | Expression::Break
+        | Expression::Continue => exp.source(),
+        Expression::Assignment(_) | Expression::None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pi() -> Expression {
+        Expression::identifier("pi")
+    }
+
+    fn e() -> Expression {
+        Expression::identifier("e")
+    }
+
+    #[test]
+    fn simplify_constant_folding_test() {
+        let exp = Expression::binary(Expression::int(2), Expression::int(3), TokenType::Addition);
+        assert_eq!(simplify(&exp), Expression::int(5));
+
+        // 6 / 4 stays exact
+        let exp = Expression::binary(Expression::int(6), Expression::int(4), TokenType::Division);
+        assert_eq!(math_notation(&simplify(&exp)), "3 / 2");
+    }
+
+    #[test]
+    fn simplify_identity_test() {
+        // "pi 2 *" is "2 pi"
+        let exp = Expression::binary(pi(), Expression::int(2), TokenType::Multiplication);
+        assert_eq!(math_notation(&simplify(&exp)), "2 pi");
+
+        // "pi pi /" is "1"
+        let exp = Expression::binary(pi(), pi(), TokenType::Division);
+        assert_eq!(simplify(&exp), Expression::int(1));
+
+        // "e 0 ^" is "1"
+        let exp = Expression::binary(e(), Expression::int(0), TokenType::Power);
+        assert_eq!(simplify(&exp), Expression::int(1));
+
+        // "pi 0 *" is "0" and "pi 0 +" is "pi"
+        let exp = Expression::binary(pi(), Expression::int(0), TokenType::Multiplication);
+        assert_eq!(simplify(&exp), Expression::int(0));
+        let exp = Expression::binary(pi(), Expression::int(0), TokenType::Addition);
+        assert_eq!(simplify(&exp), pi());
+    }
+
+    #[test]
+    fn simplify_like_terms_test() {
+        // "pi pi + e +" is "2 pi + e"
+        let exp = Expression::binary(
+            Expression::binary(pi(), pi(), TokenType::Addition),
+            e(),
+            TokenType::Addition,
+        );
+        assert_eq!(math_notation(&simplify(&exp)), "2 pi + e");
+
+        // "pi e + pi -" is "e"
+        let exp = Expression::binary(
+            Expression::binary(pi(), e(), TokenType::Addition),
+            pi(),
+            TokenType::Subtraction,
+        );
+        assert_eq!(simplify(&exp), e());
+    }
+
+    #[test]
+    fn simplify_power_test() {
+        // "pi 2 ^ pi *" is "pi^3"
+        let exp = Expression::binary(
+            Expression::binary(pi(), Expression::int(2), TokenType::Power),
+            pi(),
+            TokenType::Multiplication,
+        );
+        assert_eq!(math_notation(&simplify(&exp)), "pi^3");
+
+        // "pi 2 ^ 3 ^" is "pi^6"
+        let exp = Expression::binary(
+            Expression::binary(pi(), Expression::int(2), TokenType::Power),
+            Expression::int(3),
+            TokenType::Power,
+        );
+        assert_eq!(math_notation(&simplify(&exp)), "pi^6");
+
+        // "2 pi 2 ^ /" is "2 / pi^2"
+        let exp = Expression::binary(
+            Expression::int(2),
+            Expression::binary(pi(), Expression::int(2), TokenType::Power),
+            TokenType::Division,
+        );
+        assert_eq!(math_notation(&simplify(&exp)), "2 / pi^2");
+
+        // "pi 2 / e *" is "e pi / 2"
+        let exp = Expression::binary(
+            Expression::binary(pi(), Expression::int(2), TokenType::Division),
+            e(),
+            TokenType::Multiplication,
+        );
+        assert_eq!(math_notation(&simplify(&exp)), "e pi / 2");
+    }
+}
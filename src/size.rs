@@ -0,0 +1,63 @@
+use crate::error::BasisError;
+use crate::number::Number;
+use std::fmt;
+
+/// The size of another value, like `5` for a string or `3x3` for a matrix. A size made from a
+/// single number like `4 size` has one dimension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Size {
+    pub dimensions: Vec<usize>,
+}
+
+impl Size {
+    pub fn new(dimensions: Vec<usize>) -> Self {
+        Size { dimensions }
+    }
+
+    /// A size from a whole number that is not negative, like the `4` in `4 size`
+    pub fn from_number(number: &Number) -> Result<Self, BasisError> {
+        let invalid = || {
+            BasisError::new(&format!(
+                "A size must be a whole number that is not negative, but got {}",
+                number
+            ))
+        };
+
+        let length = match number.clone().cast("int") {
+            Ok(Number::Int(value)) => usize::try_from(value).map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Size::new(vec![length]))
+    }
+
+    /// The number of elements, which is the product of the dimensions
+    pub fn count(&self) -> usize {
+        self.dimensions.iter().product()
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dimensions: Vec<String> = self.dimensions.iter().map(|d| d.to_string()).collect();
+        write!(f, "size: {}", dimensions.join("x"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_test() {
+        assert_eq!(Size::from_number(&Number::Int(4)), Ok(Size::new(vec![4])));
+        assert_eq!(Size::from_number(&Number::Dec(3.0)), Ok(Size::new(vec![3])));
+        assert!(Size::from_number(&Number::Int(-1)).is_err());
+        assert!(Size::from_number(&Number::Dec(2.5)).is_err());
+
+        let size = Size::new(vec![3, 3]);
+        assert_eq!(size.to_string(), "size: 3x3");
+        assert_eq!(size.count(), 9);
+        assert_eq!(Size::new(vec![4]).to_string(), "size: 4");
+    }
+}
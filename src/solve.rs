@@ -0,0 +1,353 @@
+use crate::deriv::derive;
+use crate::error::BasisError;
+use crate::lexer::{Token, TokenType};
+use crate::parser::Expression;
+use crate::value::{approximate, constant};
+
+/// The most times integration will split an interval in half
+const MAX_DEPTH: u32 = 40;
+
+/// The most steps root finding will take before giving up
+const MAX_ITERATIONS: u32 = 200;
+
+const TOLERANCE: f64 = 1e-9;
+
+/// A literal expression in a single free variable, used as a function of that variable
+pub struct LiteralFunction {
+    pub body: Expression,
+    pub variable: String,
+}
+
+/// The names in an expression that are not provided constants, like `x` and `y` in `x y *`
+pub fn free_variables(exp: &Expression, out: &mut Vec<String>) {
+    match exp {
+        Expression::TokenExpression(t)
+            if t.token.token_type == TokenType::Identifier
+                && constant(&t.token.value).is_none()
+                && !out.contains(&t.token.value) =>
+        {
+            out.push(t.token.value.clone());
+        }
+        Expression::ExpExpOp(i) => {
+            free_variables(&i.expression_1, out);
+            free_variables(&i.expression_2, out);
+        }
+        Expression::Call(c) => {
+            for argument in &c.arguments {
+                free_variables(argument, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl LiteralFunction {
+    /// Use a literal as a function of its only free variable, like `x` in `x 2 ^`
+    pub fn new(body: Expression) -> Result<Self, BasisError> {
+        let mut variables = Vec::<String>::new();
+        free_variables(&body, &mut variables);
+
+        match variables.len() {
+            0 => Ok(LiteralFunction {
+                body,
+                variable: "x".to_string(),
+            }),
+            1 => Ok(LiteralFunction {
+                body,
+                variable: variables.remove(0),
+            }),
+            _ => Err(BasisError::new(&format!(
+                "Expected a function of one variable but found {}",
+                variables.join(", ")
+            ))),
+        }
+    }
+
+    pub fn at(&self, x: f64) -> Result<f64, BasisError> {
+        let value = Expression::from_token(Token {
+            token_type: TokenType::NumericDecLiteral,
+            value: format!("{}", x),
+        });
+
+        let y = approximate(&self.body.substitute(&self.variable, &value))?;
+        if !y.is_finite() {
+            return Err(BasisError::new(&format!(
+                "The function is not finite at {} = {}",
+                self.variable, x
+            )));
+        }
+
+        Ok(y)
+    }
+
+    /// The symbolic derivative if one exists, like `2 x` for `x 2 ^`
+    pub fn derivative(&self) -> Option<LiteralFunction> {
+        match derive(&self.body, &self.variable) {
+            Ok(body) => Some(LiteralFunction {
+                body,
+                variable: self.variable.clone(),
+            }),
+            Err(_) => None,
+        }
+    }
+}
+
+fn simpson(a: f64, b: f64, fa: f64, fm: f64, fb: f64) -> f64 {
+    (b - a) / 6.0 * (fa + 4.0 * fm + fb)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn adaptive_simpson(
+    f: &LiteralFunction,
+    a: f64,
+    b: f64,
+    fa: f64,
+    fm: f64,
+    fb: f64,
+    whole: f64,
+    tolerance: f64,
+    depth: u32,
+) -> Result<(f64, f64), BasisError> {
+    let m = (a + b) / 2.0;
+    let left_m = (a + m) / 2.0;
+    let right_m = (m + b) / 2.0;
+
+    let f_left_m = f.at(left_m)?;
+    let f_right_m = f.at(right_m)?;
+
+    let left = simpson(a, m, fa, f_left_m, fm);
+    let right = simpson(m, b, fm, f_right_m, fb);
+    let delta = left + right - whole;
+
+    // Richardson extrapolation gives both a better value and the error estimate
+    if depth == 0 || delta.abs() <= 15.0 * tolerance {
+        return Ok((left + right + delta / 15.0, delta.abs() / 15.0));
+    }
+
+    let (left, left_error) =
+        adaptive_simpson(f, a, m, fa, f_left_m, fm, left, tolerance / 2.0, depth - 1)?;
+    let (right, right_error) = adaptive_simpson(
+        f,
+        m,
+        b,
+        fm,
+        f_right_m,
+        fb,
+        right,
+        tolerance / 2.0,
+        depth - 1,
+    )?;
+
+    Ok((left + right, left_error + right_error))
+}
+
+/// Integrate from `a` to `b` with adaptive Simpson's rule, giving back the value and an
+/// estimate of its error
+pub fn integrate(f: &LiteralFunction, a: f64, b: f64) -> Result<(f64, f64), BasisError> {
+    let m = (a + b) / 2.0;
+    let fa = f.at(a)?;
+    let fm = f.at(m)?;
+    let fb = f.at(b)?;
+
+    let whole = simpson(a, b, fa, fm, fb);
+    adaptive_simpson(f, a, b, fa, fm, fb, whole, TOLERANCE, MAX_DEPTH)
+}
+
+/// Find a root between `x1` and `x2` with Brent's method, giving back the root and the width
+/// of the final bracket around it
+pub fn root(f: &LiteralFunction, x1: f64, x2: f64) -> Result<(f64, f64), BasisError> {
+    let (mut a, mut b) = (x1, x2);
+    let (mut fa, mut fb) = (f.at(a)?, f.at(b)?);
+
+    if fa == 0.0 {
+        return Ok((a, 0.0));
+    }
+
+    if fb == 0.0 {
+        return Ok((b, 0.0));
+    }
+
+    if fa.signum() == fb.signum() {
+        return Err(BasisError::new(&format!(
+            "The function has the same sign at {} and {}, so there is no bracketed root",
+            x1, x2
+        )));
+    }
+
+    let (mut c, mut fc) = (b, fb);
+    let mut d = b - a;
+    let mut e = d;
+
+    for _ in 0..MAX_ITERATIONS {
+        // Keep the root between b and c
+        if fb.signum() == fc.signum() {
+            c = a;
+            fc = fa;
+            d = b - a;
+            e = d;
+        }
+
+        // Keep b as the best guess
+        if fc.abs() < fb.abs() {
+            a = b;
+            b = c;
+            c = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+
+        let tolerance = 2.0 * f64::EPSILON * b.abs() + 0.5 * TOLERANCE;
+        let half = 0.5 * (c - b);
+
+        if half.abs() <= tolerance || fb == 0.0 {
+            return Ok((b, half.abs()));
+        }
+
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            // Try inverse quadratic interpolation, or the secant method with two points
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * half * s, 1.0 - s)
+            } else {
+                let q = fa / fc;
+                let r = fb / fc;
+                (
+                    s * (2.0 * half * q * (q - r) - (b - a) * (r - 1.0)),
+                    (q - 1.0) * (r - 1.0) * (s - 1.0),
+                )
+            };
+
+            if p > 0.0 {
+                q = -q;
+            }
+            p = p.abs();
+
+            let min_1 = 3.0 * half * q - (tolerance * q).abs();
+            let min_2 = (e * q).abs();
+
+            if 2.0 * p < min_1.min(min_2) {
+                e = d;
+                d = p / q;
+            } else {
+                // Interpolation failed, so bisect
+                d = half;
+                e = d;
+            }
+        } else {
+            d = half;
+            e = d;
+        }
+
+        a = b;
+        fa = fb;
+
+        if d.abs() > tolerance {
+            b += d;
+        } else {
+            b += tolerance.copysign(half);
+        }
+
+        fb = f.at(b)?;
+    }
+
+    Err(BasisError::new("Root finding did not converge"))
+}
+
+/// Find a root near `x0` with Newton's method, using the symbolic derivative when there is one
+/// and a central difference otherwise. Gives back the root and the size of the last step.
+pub fn newton(f: &LiteralFunction, x0: f64) -> Result<(f64, f64), BasisError> {
+    let derivative = f.derivative();
+    let mut x = x0;
+
+    for _ in 0..MAX_ITERATIONS {
+        let fx = f.at(x)?;
+
+        let dfx = match &derivative {
+            Some(df) => df.at(x)?,
+            None => {
+                let h = f64::EPSILON.cbrt() * x.abs().max(1.0);
+                (f.at(x + h)? - f.at(x - h)?) / (2.0 * h)
+            }
+        };
+
+        if dfx == 0.0 {
+            return Err(BasisError::new(&format!(
+                "The derivative is zero at {} = {}",
+                f.variable, x
+            )));
+        }
+
+        let step = fx / dfx;
+        x -= step;
+
+        if step.abs() <= TOLERANCE * x.abs().max(1.0) {
+            return Ok((x, step.abs()));
+        }
+    }
+
+    Err(BasisError::new("Newton's method did not converge"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn x() -> Expression {
+        Expression::identifier("x")
+    }
+
+    #[test]
+    fn integrate_test() {
+        // "x 2 ^" from 0 to 1 is 1 / 3
+        let f = LiteralFunction::new(Expression::binary(
+            x(),
+            Expression::int(2),
+            TokenType::Power,
+        ))
+        .unwrap();
+
+        let (value, error) = integrate(&f, 0.0, 1.0).unwrap();
+        assert!((value - 1.0 / 3.0).abs() < 1e-5);
+        assert!(error < 1e-5);
+
+        // "x sin" from 0 to pi is 2
+        let f = LiteralFunction::new(Expression::call("sin", vec![x()])).unwrap();
+        let (value, _) = integrate(&f, 0.0, std::f64::consts::PI).unwrap();
+        assert!((value - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn root_test() {
+        // "x 2 ^ 2 -" has a root at the square root of 2
+        let f = LiteralFunction::new(Expression::binary(
+            Expression::binary(x(), Expression::int(2), TokenType::Power),
+            Expression::int(2),
+            TokenType::Subtraction,
+        ))
+        .unwrap();
+
+        let (value, _) = root(&f, 1.0, 2.0).unwrap();
+        assert!((value - std::f64::consts::SQRT_2).abs() < 1e-5);
+
+        let (value, _) = newton(&f, 1.0).unwrap();
+        assert!((value - std::f64::consts::SQRT_2).abs() < 1e-5);
+
+        // There is no sign change between 2 and 3
+        assert!(root(&f, 2.0, 3.0).is_err());
+    }
+
+    #[test]
+    fn literal_function_test() {
+        let f = LiteralFunction::new(Expression::binary(
+            Expression::identifier("pi"),
+            Expression::identifier("t"),
+            TokenType::Multiplication,
+        ))
+        .unwrap();
+        assert_eq!(f.variable, "t");
+
+        let exp = Expression::binary(x(), Expression::identifier("y"), TokenType::Addition);
+        assert!(LiteralFunction::new(exp).is_err());
+    }
+}
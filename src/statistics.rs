@@ -0,0 +1,388 @@
+use crate::container::Container;
+use crate::error::BasisError;
+use crate::lexer::TokenType;
+use crate::number::{Number, Ratio};
+use crate::value::Value;
+use std::cmp::Ordering;
+
+/// The numbers of a container that statistics are taken of, like `(1 2 3)` in `(1 2 3) mean`.
+///
+/// Every statistic is worked out with the arithmetic of the numbers themselves, so ints and
+/// ratios give exact ratios, and only square roots that are not whole, like in `stdev`, give
+/// back a dec. The data is treated as the whole population, so `variance` divides by `n`,
+/// unless it is a `sample`, where `variance` divides by `n - 1` and the other statistics that
+/// depend on the spread are corrected in the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Data {
+    pub values: Vec<Number>,
+    pub sample: bool,
+}
+
+fn operate(a: &Number, b: &Number, operation: TokenType) -> Result<Number, BasisError> {
+    a.clone().operate(b.clone(), &operation)
+}
+
+fn add(a: &Number, b: &Number) -> Result<Number, BasisError> {
+    operate(a, b, TokenType::Addition)
+}
+
+fn sub(a: &Number, b: &Number) -> Result<Number, BasisError> {
+    operate(a, b, TokenType::Subtraction)
+}
+
+fn mul(a: &Number, b: &Number) -> Result<Number, BasisError> {
+    operate(a, b, TokenType::Multiplication)
+}
+
+fn div(a: &Number, b: &Number) -> Result<Number, BasisError> {
+    operate(a, b, TokenType::Division)
+}
+
+fn sum(values: &[Number]) -> Result<Number, BasisError> {
+    values
+        .iter()
+        .try_fold(Number::Int(0), |total, v| add(&total, v))
+}
+
+/// The whole square root of an int, if it has one
+fn whole_root(value: i64) -> Option<i64> {
+    if value < 0 {
+        return None;
+    }
+
+    let root = (value as f64).sqrt().round() as i64;
+    (root.checked_mul(root) == Some(value)).then_some(root)
+}
+
+/// The square root, which stays exact when both parts of an int or ratio are squares like
+/// `9/4`
+fn root(number: Number) -> Result<Number, BasisError> {
+    match number {
+        Number::Int(value) => match whole_root(value) {
+            Some(root) => Ok(Number::Int(root)),
+            None => Number::Int(value).apply("sqrt"),
+        },
+        Number::Ratio(value) => {
+            match (whole_root(value.numerator), whole_root(value.denominator)) {
+                (Some(n), Some(d)) => Ok(Number::Ratio(Ratio::new(n, d).unwrap())),
+                _ => Number::Ratio(value).apply("sqrt"),
+            }
+        }
+        other => other.apply("sqrt"),
+    }
+}
+
+/// The largest whole number no larger than a number, like `1` for `3/2`
+fn floor(number: &Number) -> usize {
+    match number {
+        Number::Int(value) => *value as usize,
+        Number::Ratio(value) => value.numerator.div_euclid(value.denominator) as usize,
+        other => other.to_dec().floor() as usize,
+    }
+}
+
+impl Data {
+    pub fn from_container(container: &Container) -> Result<Data, BasisError> {
+        let mut values = Vec::<Number>::new();
+        for value in container.values() {
+            match value.without_estimate() {
+                Value::Number(number) => values.push(number),
+                other => {
+                    return Err(BasisError::new(&format!(
+                        "Statistics need numbers, but got {}",
+                        other.type_name()
+                    )))
+                }
+            }
+        }
+
+        if values.is_empty() {
+            return Err(BasisError::new("Statistics need at least one value"));
+        }
+
+        Ok(Data {
+            values,
+            sample: matches!(container, Container::Sample(_)),
+        })
+    }
+
+    fn count(&self) -> Number {
+        Number::Int(self.values.len() as i64)
+    }
+
+    /// Check that a sample has enough values for a statistic to be corrected
+    fn needs(&self, count: usize, statistic: &str) -> Result<(), BasisError> {
+        match self.sample && self.values.len() < count {
+            true => Err(BasisError::new(&format!(
+                "The {} of a sample needs at least {} values",
+                statistic, count
+            ))),
+            false => Ok(()),
+        }
+    }
+
+    /// What the sums of squared deviations are divided by, which is `n - 1` for a sample
+    fn degrees(&self, statistic: &str) -> Result<Number, BasisError> {
+        self.needs(2, statistic)?;
+        match self.sample {
+            true => sub(&self.count(), &Number::Int(1)),
+            false => Ok(self.count()),
+        }
+    }
+
+    /// The values from smallest to largest, which complex numbers have no order for
+    fn sorted(&self) -> Result<Vec<Number>, BasisError> {
+        let mut values = self.values.clone();
+        let mut unordered = false;
+
+        values.sort_by(|a, b| {
+            a.clone().compare(b.clone()).unwrap_or_else(|| {
+                unordered = true;
+                Ordering::Equal
+            })
+        });
+
+        match unordered {
+            true => Err(BasisError::new("Cannot order complex numbers")),
+            false => Ok(values),
+        }
+    }
+
+    /// Each value less the mean
+    fn deviations(&self) -> Result<Vec<Number>, BasisError> {
+        let mean = self.mean()?;
+        self.values.iter().map(|v| sub(v, &mean)).collect()
+    }
+
+    /// The mean of the deviations to a power, like the variance for `2`
+    fn moment(&self, power: i64) -> Result<Number, BasisError> {
+        let mut powers = Vec::<Number>::new();
+        for deviation in self.deviations()? {
+            powers.push(operate(&deviation, &Number::Int(power), TokenType::Power)?);
+        }
+
+        div(&sum(&powers)?, &self.count())
+    }
+
+    /// The variance, which the shape statistics like `skewness` divide by
+    fn spread(&self) -> Result<Number, BasisError> {
+        let variance = self.moment(2)?;
+        match variance.is_zero() {
+            true => Err(BasisError::new(
+                "Statistics of shape need values that are not all the same",
+            )),
+            false => Ok(variance),
+        }
+    }
+
+    pub fn mean(&self) -> Result<Number, BasisError> {
+        div(&sum(&self.values)?, &self.count())
+    }
+
+    pub fn median(&self) -> Result<Number, BasisError> {
+        self.quantile(&Number::Ratio(Ratio::new(1, 2).unwrap()))
+    }
+
+    /// The value that comes up the most, where a tie goes to the smallest
+    pub fn mode(&self) -> Result<Number, BasisError> {
+        let sorted = self.sorted()?;
+
+        let mut best = (&sorted[0], 0);
+        for run in sorted.chunk_by(|a, b| a.clone().compare(b.clone()) == Some(Ordering::Equal)) {
+            if run.len() > best.1 {
+                best = (&run[0], run.len());
+            }
+        }
+
+        Ok(best.0.clone())
+    }
+
+    pub fn variance(&self) -> Result<Number, BasisError> {
+        div(&self.co_deviation(self)?, &self.degrees("variance")?)
+    }
+
+    pub fn stdev(&self) -> Result<Number, BasisError> {
+        root(self.variance()?)
+    }
+
+    /// The value a fraction `q` of the way through the sorted values, going in a straight line
+    /// between the two values on either side, so `1/2` gives the median
+    pub fn quantile(&self, q: &Number) -> Result<Number, BasisError> {
+        // NaN and complex numbers have no order, so they are not between 0 and 1 either
+        let above = |bound: i64| q.clone().compare(Number::Int(bound));
+        let within = q.is_real()
+            && matches!(above(0), Some(Ordering::Greater | Ordering::Equal))
+            && matches!(above(1), Some(Ordering::Less | Ordering::Equal));
+
+        if !within {
+            return Err(BasisError::new("A quantile must be between 0 and 1"));
+        }
+
+        let sorted = self.sorted()?;
+        let position = mul(&Number::Int(sorted.len() as i64 - 1), q)?;
+        let below = floor(&position).min(sorted.len() - 1);
+
+        let fraction = sub(&position, &Number::Int(below as i64))?;
+        if fraction.is_zero() || below + 1 >= sorted.len() {
+            return Ok(sorted[below].clone());
+        }
+
+        let step = sub(&sorted[below + 1], &sorted[below])?;
+        add(&sorted[below], &mul(&fraction, &step)?)
+    }
+
+    pub fn min(&self) -> Result<Number, BasisError> {
+        Ok(self.sorted()?.swap_remove(0))
+    }
+
+    pub fn max(&self) -> Result<Number, BasisError> {
+        Ok(self.sorted()?.pop().unwrap())
+    }
+
+    pub fn range(&self) -> Result<Number, BasisError> {
+        sub(&self.max()?, &self.min()?)
+    }
+
+    /// How lopsided the values are around the mean, which is `0` when they are symmetric
+    pub fn skewness(&self) -> Result<Number, BasisError> {
+        self.needs(3, "skewness")?;
+        let variance = self.spread()?;
+        let skewness = div(&self.moment(3)?, &mul(&variance, &root(variance.clone())?)?)?;
+        if !self.sample {
+            return Ok(skewness);
+        }
+
+        // The adjusted Fisher-Pearson skewness, `g1 sqrt(n (n - 1)) / (n - 2)`
+        let n = self.count();
+        let scale = root(mul(&n, &sub(&n, &Number::Int(1))?)?)?;
+        div(&mul(&skewness, &scale)?, &sub(&n, &Number::Int(2))?)
+    }
+
+    /// The excess kurtosis, which is `0` for a normal distribution and larger when more of the
+    /// values are far from the mean
+    pub fn kurtosis(&self) -> Result<Number, BasisError> {
+        self.needs(4, "kurtosis")?;
+        let variance = self.spread()?;
+        let ratio = div(&self.moment(4)?, &mul(&variance, &variance)?)?;
+        let kurtosis = sub(&ratio, &Number::Int(3))?;
+        if !self.sample {
+            return Ok(kurtosis);
+        }
+
+        // `((n + 1) g2 + 6) (n - 1) / ((n - 2) (n - 3))`, which is exact like the rest
+        let n = |offset: i64| add(&self.count(), &Number::Int(offset));
+        let top = mul(&add(&mul(&n(1)?, &kurtosis)?, &Number::Int(6))?, &n(-1)?)?;
+        div(&top, &mul(&n(-2)?, &n(-3)?)?)
+    }
+
+    /// The sum of the products of the deviations of two sets of data of the same size
+    fn co_deviation(&self, other: &Data) -> Result<Number, BasisError> {
+        if self.values.len() != other.values.len() {
+            return Err(BasisError::new(&format!(
+                "Cannot pair {} values with {} values",
+                self.values.len(),
+                other.values.len()
+            )));
+        }
+
+        let mut products = Vec::<Number>::new();
+        for (a, b) in self.deviations()?.iter().zip(other.deviations()?) {
+            products.push(mul(a, &b)?);
+        }
+
+        sum(&products)
+    }
+
+    pub fn covariance(&self, other: &Data) -> Result<Number, BasisError> {
+        div(&self.co_deviation(other)?, &self.degrees("covariance")?)
+    }
+
+    /// The Pearson correlation, from `-1` when one goes down as the other goes up to `1` when
+    /// they go up together
+    pub fn correlation(&self, other: &Data) -> Result<Number, BasisError> {
+        let spread = mul(&self.co_deviation(self)?, &other.co_deviation(other)?)?;
+        if spread.is_zero() {
+            return Err(BasisError::new(
+                "Correlation needs values that are not all the same",
+            ));
+        }
+
+        div(&self.co_deviation(other)?, &root(spread)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(values: &[i64]) -> Data {
+        Data {
+            values: values.iter().map(|v| Number::Int(*v)).collect(),
+            sample: false,
+        }
+    }
+
+    fn sample(values: &[i64]) -> Data {
+        Data {
+            sample: true,
+            ..data(values)
+        }
+    }
+
+    fn ratio(numerator: i64, denominator: i64) -> Number {
+        Number::Ratio(Ratio::new(numerator, denominator).unwrap())
+    }
+
+    #[test]
+    fn statistics_test() {
+        let d = data(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(d.mean(), Ok(ratio(5, 1)));
+        assert_eq!(d.variance(), Ok(ratio(4, 1)));
+        assert_eq!(d.stdev(), Ok(ratio(2, 1)));
+        assert_eq!(d.median(), Ok(ratio(9, 2)));
+        assert_eq!(d.mode(), Ok(Number::Int(4)));
+        assert_eq!(d.range(), Ok(Number::Int(7)));
+
+        // An odd number of values has a middle one
+        assert_eq!(data(&[3, 1, 2]).median(), Ok(Number::Int(2)));
+        assert_eq!(data(&[1, 2, 3, 4]).quantile(&ratio(1, 4)), Ok(ratio(7, 4)));
+        assert!(data(&[1, 2]).quantile(&Number::Int(2)).is_err());
+        assert!(data(&[5]).quantile(&Number::Dec(f64::NAN)).is_err());
+        assert!(data(&[5]).quantile(&Number::Imaginary(1.0)).is_err());
+        assert_eq!(data(&[5]).quantile(&Number::Dec(0.5)), Ok(Number::Int(5)));
+
+        // A variance that is not a square of a ratio gives a dec
+        assert_eq!(data(&[1, 2]).stdev(), Ok(ratio(1, 2)));
+        assert_eq!(
+            data(&[1, 2, 3]).stdev(),
+            Ok(Number::Dec((2.0f64 / 3.0).sqrt()))
+        );
+
+        assert_eq!(data(&[1, 2, 3]).skewness(), Ok(Number::Dec(0.0)));
+        assert_eq!(data(&[1, 2, 3, 4]).kurtosis(), Ok(ratio(-34, 25)));
+        assert!(data(&[1, 1]).skewness().is_err());
+
+        let x = data(&[1, 2, 3]);
+        assert_eq!(x.covariance(&data(&[2, 4, 6])), Ok(ratio(4, 3)));
+        assert_eq!(x.correlation(&data(&[6, 4, 2])), Ok(ratio(-1, 1)));
+        assert!(x.covariance(&data(&[1, 2])).is_err());
+
+        let decs = Data {
+            values: vec![Number::Dec(1.5), Number::Dec(2.5)],
+            sample: false,
+        };
+        assert_eq!(decs.mean(), Ok(Number::Dec(2.0)));
+
+        // A sample divides by `n - 1` and corrects the shape statistics
+        let s = sample(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(s.mean(), Ok(ratio(5, 1)));
+        assert_eq!(s.variance(), Ok(ratio(32, 7)));
+        assert_eq!(sample(&[1, 2, 3, 4]).kurtosis(), Ok(ratio(-6, 5)));
+        assert_eq!(
+            sample(&[1, 2, 3]).covariance(&sample(&[2, 4, 6])),
+            Ok(ratio(2, 1))
+        );
+        assert!(sample(&[1]).variance().is_err());
+        assert!(sample(&[1, 2, 3]).kurtosis().is_err());
+    }
+}
@@ -0,0 +1,540 @@
+use crate::closure::Closure;
+use crate::container::Container;
+use crate::error::BasisError;
+use crate::function::Function;
+use crate::lexer::{Token, TokenTrait, TokenType};
+use crate::number::{Complex, Number, Ratio};
+use crate::parser::{
+    Expression, InnerCall, InnerClosure, InnerSet, InnerTuple, InnerVector, Operation,
+};
+use crate::simplify::math_notation;
+use crate::size::Size;
+use crate::vector::{element, Vector};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The numeric value of each provided constant. Constants are literals, so this is only used
+/// once they are unwrapped.
+pub fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(std::f64::consts::PI),
+        "e" => Some(std::f64::consts::E),
+        // Gravity of Earth in m/s^2
+        "g" => Some(9.80665),
+        // Planck constant in J s
+        "h" => Some(6.626_07e-34),
+        _ => None,
+    }
+}
+
+/// The multiple of i written by an imaginary literal like `3i`
+pub fn imaginary_part(literal: &str) -> Option<f64> {
+    literal.strip_suffix('i')?.parse::<f64>().ok()
+}
+
+/// Numerically evaluate one of the builtin math functions
+pub fn function_value(name: &str, x: f64) -> Result<f64, BasisError> {
+    match name {
+        "abs" => Ok(x.abs()),
+        "sqrt" => Ok(x.sqrt()),
+        "sin" => Ok(x.sin()),
+        "cos" => Ok(x.cos()),
+        "tan" => Ok(x.tan()),
+        "exp" => Ok(x.exp()),
+        "log" => Ok(x.ln()),
+        _ => Err(BasisError::new(&format!("Unknown function `{}`", name))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(Number),
+
+    // A symbolic expression like `2 pi` that is only evaluated when unwrapped
+    Literal(Expression),
+
+    // A dec from a numeric method like `integrate`, along with an estimate of its error
+    Estimate { value: f64, error: f64 },
+
+    String(String),
+    Size(Size),
+    Vector(Vector),
+
+    // Several values together in order, like `(1 2 3)` or the `P`, `L` and `U` from `lu`
+    Tuple(Vec<Value>),
+
+    // Values without repeats, kept in the order they were first given, like `{1 2 3}`
+    Set(Vec<Value>),
+
+    // Tuples of numbers that statistics treat as a sample or as the whole population, like
+    // `(1 2 3) sample`
+    Sample(Vec<Value>),
+    Population(Vec<Value>),
+    Bool(bool),
+    Nil,
+
+    // A value that may be missing, where `None` is an empty option
+    Option(Option<Box<Value>>),
+
+    Function(Function),
+    Closure(Closure),
+}
+
+/// Numerically evaluate a literal expression, replacing constants with their values
+pub fn approximate(exp: &Expression) -> Result<f64, BasisError> {
+    match exp {
+        Expression::TokenExpression(t) => match t.token.token_type {
+            TokenType::NumericIntLiteral | TokenType::NumericDecLiteral => {
+                match t.token.value.parse::<f64>() {
+                    Ok(value) => Ok(value),
+                    Err(_) => Err(BasisError::new(&format!(
+                        "Invalid number `{}`",
+                        t.token.value
+                    ))),
+                }
+            }
+            TokenType::Identifier => match constant(&t.token.value) {
+                Some(value) => Ok(value),
+                None => Err(BasisError::new(&format!(
+                    "Cannot unwrap unknown literal `{}`",
+                    t.token.value
+                ))),
+            },
+            _ => Err(BasisError::new(&format!(
+                "Cannot unwrap `{}`",
+                t.token.value
+            ))),
+        },
+
+        Expression::ExpExpOp(i) => {
+            let a = approximate(&i.expression_1)?;
+            let b = approximate(&i.expression_2)?;
+
+            let Operation::TokenOperation(c) = i.operation.as_ref();
+            match c.token.token_type {
+                TokenType::Addition => Ok(a + b),
+                TokenType::Subtraction => Ok(a - b),
+                TokenType::Multiplication => Ok(a * b),
+                TokenType::Division => Ok(a / b),
+                TokenType::Power => Ok(a.powf(b)),
+                _ => Err(BasisError::new(&format!(
+                    "Cannot unwrap operation `{}`",
+                    c.token.value
+                ))),
+            }
+        }
+
+        Expression::Call(c) => match c.arguments.as_slice() {
+            [argument] => function_value(&c.function.value, approximate(argument)?),
+            _ => Err(BasisError::new(&format!(
+                "Cannot unwrap function `{}`",
+                c.function.value
+            ))),
+        },
+
+        _ => Err(BasisError::new("Cannot unwrap this expression")),
+    }
+}
+
+impl Value {
+    pub fn int(value: i64) -> Self {
+        Value::Number(Number::Int(value))
+    }
+
+    pub fn dec(value: f64) -> Self {
+        Value::Number(Number::Dec(value))
+    }
+
+    /// Turn a simplified expression back into a value, keeping it a literal unless it folded
+    /// down to a single number
+    pub fn from_expression(exp: Expression) -> Self {
+        match Value::number_of(&exp) {
+            Some(number) => Value::Number(number),
+            None => Value::Literal(exp),
+        }
+    }
+
+    /// The number written by an expression, where a ratio is written as `2 3 /`
+    fn number_of(exp: &Expression) -> Option<Number> {
+        match exp {
+            Expression::TokenExpression(t) => match t.token.token_type {
+                TokenType::NumericIntLiteral => t.token.value.parse::<i64>().ok().map(Number::Int),
+                TokenType::NumericDecLiteral => t.token.value.parse::<f64>().ok().map(Number::Dec),
+                TokenType::NumericImaginaryLiteral => {
+                    imaginary_part(&t.token.value).map(Number::Imaginary)
+                }
+                _ => None,
+            },
+            Expression::ExpExpOp(i) if exp.operation_type() == Some(TokenType::Division) => {
+                match (
+                    Value::number_of(&i.expression_1)?,
+                    Value::number_of(&i.expression_2)?,
+                ) {
+                    (Number::Int(a), Number::Int(b)) => Ratio::new(a, b).map(Number::Ratio),
+                    _ => None,
+                }
+            }
+            Expression::ExpExpOp(i) if exp.operation_type() == Some(TokenType::Addition) => {
+                match (
+                    Value::number_of(&i.expression_1)?,
+                    Value::number_of(&i.expression_2)?,
+                ) {
+                    (Number::Dec(re), Number::Imaginary(im)) => {
+                        Some(Number::Complex(Complex::new(re, im)))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_expression(&self) -> Expression {
+        match self {
+            Value::Number(Number::Int(value)) => Expression::int(*value),
+            Value::Number(Number::Ratio(value)) => Expression::binary(
+                Expression::int(value.numerator),
+                Expression::int(value.denominator),
+                TokenType::Division,
+            ),
+            Value::Number(Number::Dec(value)) => Expression::from_token(Token {
+                token_type: TokenType::NumericDecLiteral,
+                value: format!("{}", value),
+            }),
+            Value::Number(Number::Real(value)) => Expression::from_token(Token {
+                token_type: TokenType::NumericDecLiteral,
+                value: value.to_string(),
+            }),
+            Value::Number(Number::Imaginary(value)) => Expression::from_token(Token {
+                token_type: TokenType::NumericImaginaryLiteral,
+                value: format!("{}i", value),
+            }),
+            // Written as `re im +`, like `2 3i +`
+            Value::Number(Number::Complex(value)) => Expression::binary(
+                Value::dec(value.re).to_expression(),
+                Value::Number(Number::Imaginary(value.im)).to_expression(),
+                TokenType::Addition,
+            ),
+            Value::Literal(exp) => exp.clone(),
+            Value::Estimate { value, .. } => Value::dec(*value).to_expression(),
+            Value::String(value) => Expression::from_token(Token {
+                token_type: TokenType::StringLiteral,
+                value: value.clone(),
+            }),
+            Value::Bool(value) => Expression::from_token(Token {
+                token_type: TokenType::BoolLiteral,
+                value: value.to_string(),
+            }),
+            Value::Nil => Expression::from_token(Token {
+                token_type: TokenType::NilLiteral,
+                value: "nil".to_string(),
+            }),
+            // Written as the inner value passed to `option`, like `5 option`
+            Value::Option(inner) => Expression::Call(InnerCall {
+                function: Token {
+                    token_type: TokenType::TypeOptionKeyword,
+                    value: "option".to_string(),
+                },
+                arguments: vec![match inner {
+                    Some(value) => value.to_expression(),
+                    None => Value::Nil.to_expression(),
+                }],
+            }),
+            // A function is written as its name
+            Value::Function(function) => Expression::identifier(&function.name),
+            Value::Closure(closure) => Expression::Closure(InnerClosure {
+                parameters: closure.parameters.clone(),
+                body: Box::new(closure.body.clone()),
+            }),
+            Value::Vector(vector) => Expression::Vector(InnerVector {
+                rows: vector
+                    .to_rows()
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|n| Value::Number(n.clone()).to_expression())
+                            .collect()
+                    })
+                    .collect(),
+            }),
+            Value::Tuple(values) => Expression::Tuple(InnerTuple {
+                elements: values.iter().map(Value::to_expression).collect(),
+            }),
+            Value::Set(values) => Expression::Set(InnerSet {
+                elements: values.iter().map(Value::to_expression).collect(),
+            }),
+            // Written as the tuple passed to `sample` or `population`, like `(1 2 3) sample`
+            Value::Sample(values) | Value::Population(values) => Expression::Call(InnerCall {
+                function: Token::tokenize(self.type_name().to_string()),
+                arguments: vec![Value::Tuple(values.clone()).to_expression()],
+            }),
+            // Written as the dimensions passed to `size`, like `4 size`
+            Value::Size(size) => Expression::Call(InnerCall {
+                function: Token {
+                    token_type: TokenType::TypeSizeKeyword,
+                    value: "size".to_string(),
+                },
+                arguments: size
+                    .dimensions
+                    .iter()
+                    .map(|d| Expression::int(*d as i64))
+                    .collect(),
+            }),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(number) => number.type_name(),
+            Value::Estimate { .. } => "dec",
+            Value::Literal(_) => "literal",
+            Value::String(_) => "string",
+            Value::Size(_) => "size",
+            Value::Vector(_) => "vector",
+            Value::Tuple(_) => "tuple",
+            Value::Set(_) => "set",
+            Value::Sample(_) => "sample",
+            Value::Population(_) => "population",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::Option(_) => "option",
+            Value::Function(_) => "function",
+            Value::Closure(_) => "closure",
+        }
+    }
+
+    /// Unwrap a wrapper type into its inner value, evaluating literals
+    pub fn unwrap(self) -> Result<Value, BasisError> {
+        match self {
+            Value::Literal(exp) => Ok(Value::dec(approximate(&exp)?)),
+            Value::Option(Some(value)) => Ok(*value),
+            Value::Option(None) => Err(BasisError::new("Cannot unwrap an empty option")),
+            Value::Nil => Err(BasisError::new("Cannot unwrap nil")),
+            other => Ok(other.without_estimate()),
+        }
+    }
+
+    /// Drop the error estimate, since arithmetic treats an estimate as a plain dec
+    pub fn without_estimate(self) -> Value {
+        match self {
+            Value::Estimate { value, .. } => Value::dec(value),
+            other => other,
+        }
+    }
+
+    /// The numeric value as a float, like the bounds given to `integrate`
+    pub fn as_dec(&self) -> Result<f64, BasisError> {
+        match self {
+            Value::Number(number) => Ok(number.to_dec()),
+            Value::Estimate { value, .. } => Ok(*value),
+            Value::Literal(exp) => approximate(exp),
+            other => Err(BasisError::new(&format!(
+                "Expected a number but got {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// The size of a value with the `size` function, like `5` for `"hello"`
+    pub fn size(&self) -> Result<Size, BasisError> {
+        match self {
+            Value::Number(number) => Size::from_number(number),
+            Value::Estimate { value, .. } => Size::from_number(&Number::Dec(*value)),
+            Value::String(value) => Ok(Size::new(vec![value.chars().count()])),
+            Value::Size(size) => Ok(size.clone()),
+            Value::Vector(vector) => Ok(vector.size()),
+            Value::Tuple(values)
+            | Value::Set(values)
+            | Value::Sample(values)
+            | Value::Population(values) => Ok(Size::new(vec![values.len()])),
+            other => Err(BasisError::new(&format!(
+                "A {} has no size",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Cast into a bool, where zero of any number type is `false`
+    pub fn truthy(&self) -> Result<bool, BasisError> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            Value::Number(number) => Ok(!number.is_zero()),
+            Value::Estimate { value, .. } => Ok(*value != 0.0),
+            Value::String(value) => Ok(!value.is_empty()),
+            Value::Size(size) => Ok(size.count() != 0),
+            Value::Vector(vector) => Ok(!vector.elements.is_empty()),
+            Value::Tuple(values)
+            | Value::Set(values)
+            | Value::Sample(values)
+            | Value::Population(values) => Ok(!values.is_empty()),
+            Value::Literal(exp) => Ok(approximate(exp)? != 0.0),
+            Value::Nil => Ok(false),
+            Value::Option(_) => Ok(self.exists()),
+            Value::Function(_) | Value::Closure(_) => Ok(true),
+        }
+    }
+
+    /// Whether there is a value, which is `false` for `nil` and empty options
+    pub fn exists(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Option(None))
+    }
+
+    /// Wrap a value in an option, where `nil` becomes an empty option
+    pub fn into_option(self) -> Value {
+        match self {
+            Value::Nil => Value::Option(None),
+            option @ Value::Option(_) => option,
+            value => Value::Option(Some(Box::new(value))),
+        }
+    }
+
+    /// The `value` attribute of an option, which is `nil` for an empty option
+    pub fn option_value(self) -> Value {
+        match self {
+            Value::Option(Some(value)) => *value,
+            Value::Option(None) => Value::Nil,
+            value => value,
+        }
+    }
+
+    /// The values a `for` loop goes through, like each character of a string, each element of
+    /// a vector or the value in an option
+    pub fn elements(&self) -> Result<Vec<Value>, BasisError> {
+        match self {
+            Value::String(value) => Ok(value.chars().map(|c| Value::String(c.into())).collect()),
+            container if Container::is_container(container) => {
+                Ok(Container::from_value(container.clone())?.values())
+            }
+            Value::Option(Some(value)) => Ok(vec![value.as_ref().clone()]),
+            Value::Option(None) | Value::Nil => Ok(Vec::new()),
+            other => Err(BasisError::new(&format!(
+                "Cannot loop over a {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// A set of values, where a value equal to one before it is left out
+    pub fn set(values: Vec<Value>) -> Value {
+        Value::Set(unique(values))
+    }
+
+    /// Whether two values are equal, where numbers of different types like `1` and `1.0` are
+    /// compared by their value and sets do not depend on their order
+    pub fn same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => {
+                a.clone().compare(b.clone()) == Some(Ordering::Equal)
+            }
+            (Value::Tuple(a), Value::Tuple(b))
+            | (Value::Sample(a), Value::Sample(b))
+            | (Value::Population(a), Value::Population(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.same(y))
+            }
+            (Value::Set(a), Value::Set(b)) => {
+                a.len() == b.len() && a.iter().all(|x| b.iter().any(|y| x.same(y)))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Whether a value is one of the elements of a container, like `2 {1 2 3} in`
+    pub fn contains(&self, value: &Value) -> Result<bool, BasisError> {
+        Ok(self.elements()?.iter().any(|element| element.same(value)))
+    }
+
+    /// The value written without its type, like `3/4` or `[1 2]`, for inside another value
+    fn entry(&self) -> String {
+        match self {
+            Value::Number(number) => element(number),
+            Value::Vector(vector) => vector.entries(),
+            other => other.to_expression().source(),
+        }
+    }
+
+    /// Cast into the dec type with the `.` function
+    pub fn to_dec(self) -> Result<Value, BasisError> {
+        Ok(Value::dec(self.as_dec()?))
+    }
+}
+
+/// The values without the ones equal to a value before them
+pub fn unique(values: Vec<Value>) -> Vec<Value> {
+    let mut out = Vec::<Value>::new();
+    for value in values {
+        if !out.iter().any(|v| v.same(&value)) {
+            out.push(value);
+        }
+    }
+
+    out
+}
+
+/// The type of every value in a container, like the `vector` of `tuple[vector]`. Numbers of
+/// different types are a `number`, and other values of different types are a `type`.
+pub fn element_type(values: &[Value]) -> &'static str {
+    match values.first().map(Value::type_name) {
+        Some(first) if values.iter().all(|v| v.type_name() == first) => first,
+        Some(_) if values.iter().all(|v| matches!(v, Value::Number(_))) => "number",
+        Some(_) => "type",
+        None => "nil",
+    }
+}
+
+fn entries(values: &[Value]) -> String {
+    let entries: Vec<String> = values.iter().map(Value::entry).collect();
+    entries.join(" ")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Literal(exp) => write!(f, "literal: {}", math_notation(exp)),
+            Value::Estimate { value, error } => {
+                if *error == 0.0 {
+                    write!(f, "dec: {} (error: 0)", value)
+                } else {
+                    write!(f, "dec: {} (error: {:.1e})", value, error)
+                }
+            }
+            // The size is in the same quotes as the string
+            Value::String(value) => write!(
+                f,
+                "string: \"{}\" -> \"size: {}\"",
+                value,
+                value.chars().count()
+            ),
+            Value::Size(size) => write!(f, "{}", size),
+            Value::Vector(vector) => write!(f, "{}", vector),
+            // The size is in the same brackets as the values, like for a vector
+            Value::Tuple(values) => write!(
+                f,
+                "tuple[{}]: ({}) -> (size: {})",
+                element_type(values),
+                entries(values),
+                values.len()
+            ),
+            Value::Set(values) => write!(
+                f,
+                "set[{}]: {{{}}} -> {{size: {}}}",
+                element_type(values),
+                entries(values),
+                values.len()
+            ),
+            Value::Sample(values) | Value::Population(values) => write!(
+                f,
+                "{}: ({}) -> (size: {})",
+                self.type_name(),
+                entries(values),
+                values.len()
+            ),
+            Value::Bool(value) => write!(f, "bool: {}", value),
+            Value::Nil => write!(f, "nil"),
+            Value::Option(Some(value)) => write!(f, "option: some({})", value),
+            Value::Option(None) => write!(f, "option: none"),
+            Value::Function(function) => write!(f, "function: {}", function),
+            Value::Closure(closure) => write!(f, "closure: {}", closure),
+        }
+    }
+}
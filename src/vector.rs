@@ -0,0 +1,247 @@
+use crate::error::BasisError;
+use crate::lexer::TokenType;
+use crate::number::Number;
+use crate::size::Size;
+use std::fmt;
+
+/// A vector of numbers like `[1 2 3]`, kept in rows so a plain vector is a single row. Every
+/// element has the same type, which is the type all of them promote to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector {
+    pub elements: Vec<Number>,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+impl Vector {
+    /// A vector with one row, like `[1 2.5]`, where the `1` is promoted to a dec
+    pub fn row(elements: Vec<Number>) -> Result<Self, BasisError> {
+        Vector::new(vec![elements])
+    }
+
+    /// A vector from its rows, like `[1 2; 3 4]`, where every row needs the same length
+    pub fn new(rows: Vec<Vec<Number>>) -> Result<Self, BasisError> {
+        let columns = rows.first().map_or(0, Vec::len);
+        if rows.iter().any(|row| row.len() != columns) {
+            return Err(BasisError::new(
+                "Every row of a vector needs the same number of columns",
+            ));
+        }
+
+        Ok(Vector {
+            rows: rows.len(),
+            columns,
+            elements: promote(rows.into_iter().flatten().collect())?,
+        })
+    }
+
+    pub fn to_rows(&self) -> Vec<Vec<Number>> {
+        match self.columns {
+            0 => vec![Vec::new(); self.rows],
+            columns => self
+                .elements
+                .chunks(columns)
+                .map(|row| row.to_vec())
+                .collect(),
+        }
+    }
+
+    /// The type of the elements, which is `int` for an empty vector
+    pub fn element_type(&self) -> &'static str {
+        self.elements.first().map_or("int", Number::type_name)
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(vec![self.rows, self.columns])
+    }
+
+    /// Cast every element to a number type, like `vector[dec]`
+    pub fn cast(self, type_name: &str) -> Result<Self, BasisError> {
+        let mut elements = Vec::<Number>::new();
+        for element in self.elements {
+            elements.push(element.cast(type_name)?);
+        }
+
+        Ok(Vector { elements, ..self })
+    }
+
+    fn get(&self, row: usize, column: usize) -> &Number {
+        &self.elements[row * self.columns + column]
+    }
+
+    /// Apply an operation to each pair of elements. A side with a single row or column is
+    /// spread across the other, so a `1x3` vector can be added to each row of a `2x3` one.
+    pub fn broadcast(&self, other: &Vector, operation: &TokenType) -> Result<Vector, BasisError> {
+        let spread = |a: usize, b: usize| match (a, b) {
+            (a, b) if a == b => Some(a),
+            (1, b) => Some(b),
+            (a, 1) => Some(a),
+            _ => None,
+        };
+
+        let (Some(rows), Some(columns)) = (
+            spread(self.rows, other.rows),
+            spread(self.columns, other.columns),
+        ) else {
+            return Err(BasisError::new(&format!(
+                "Cannot combine a {}x{} vector with a {}x{} vector",
+                self.rows, self.columns, other.rows, other.columns
+            )));
+        };
+
+        let mut elements = Vec::<Number>::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let a = self.get(row.min(self.rows - 1), column.min(self.columns - 1));
+                let b = other.get(row.min(other.rows - 1), column.min(other.columns - 1));
+                elements.push(a.clone().operate(b.clone(), operation)?);
+            }
+        }
+
+        Ok(Vector {
+            elements: promote(elements)?,
+            rows,
+            columns,
+        })
+    }
+
+    /// Apply an operation between each element and a number, where `reversed` puts the number
+    /// on the left like `1 [1 2] -`
+    pub fn scalar(
+        &self,
+        number: &Number,
+        operation: &TokenType,
+        reversed: bool,
+    ) -> Result<Vector, BasisError> {
+        let mut elements = Vec::<Number>::new();
+        for element in &self.elements {
+            let (a, b) = match reversed {
+                true => (number.clone(), element.clone()),
+                false => (element.clone(), number.clone()),
+            };
+
+            elements.push(a.operate(b, operation)?);
+        }
+
+        Ok(Vector {
+            elements: promote(elements)?,
+            ..self.clone()
+        })
+    }
+}
+
+/// Cast every number to the type they all promote to
+fn promote(elements: Vec<Number>) -> Result<Vec<Number>, BasisError> {
+    let Some(mut widest) = elements.first().cloned() else {
+        return Ok(elements);
+    };
+
+    for element in &elements[1..] {
+        let common = widest.common_type(element);
+        widest = widest.cast(common)?;
+    }
+
+    let common = widest.type_name();
+    elements.into_iter().map(|e| e.cast(common)).collect()
+}
+
+/// An element written without its type, like `3/4` for a ratio
+pub fn element(number: &Number) -> String {
+    match number {
+        Number::Int(value) => value.to_string(),
+        Number::Dec(value) => value.to_string(),
+        Number::Imaginary(value) => format!("{}i", value),
+        other => {
+            let text = other.to_string();
+            let (_, value) = text.split_once(": ").unwrap_or(("", &text));
+            value.replace(' ', "")
+        }
+    }
+}
+
+impl Vector {
+    /// The elements without the type, like `[1 2]`, where a vector with more than one row is
+    /// written as its rows like `[[1 2]  [3 4]]`
+    pub fn entries(&self) -> String {
+        let rows: Vec<String> = self
+            .to_rows()
+            .iter()
+            .map(|row| {
+                let elements: Vec<String> = row.iter().map(element).collect();
+                format!("[{}]", elements.join(" "))
+            })
+            .collect();
+
+        match self.rows {
+            1 => rows.join(""),
+            _ => format!("[{}]", rows.join("  ")),
+        }
+    }
+}
+
+impl fmt::Display for Vector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "vector[{}]: {} -> [{}]",
+            self.element_type(),
+            self.entries(),
+            self.size()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::number::Ratio;
+
+    fn ints(values: &[i64]) -> Vector {
+        Vector::row(values.iter().map(|v| Number::Int(*v)).collect()).unwrap()
+    }
+
+    #[test]
+    fn vector_test() {
+        assert_eq!(
+            ints(&[1, 2, 3]).to_string(),
+            "vector[int]: [1 2 3] -> [size: 1x3]"
+        );
+
+        // Every element is promoted to the same type
+        let mixed = Vector::row(vec![Number::Int(1), Number::Dec(2.5)]).unwrap();
+        assert_eq!(mixed.to_string(), "vector[dec]: [1 2.5] -> [size: 1x2]");
+
+        let sum = ints(&[1, 2]).broadcast(&ints(&[2, 3]), &TokenType::Addition);
+        assert_eq!(sum, Ok(ints(&[3, 5])));
+        assert!(ints(&[1, 2])
+            .broadcast(&ints(&[1, 2, 3]), &TokenType::Addition)
+            .is_err());
+
+        // A single element is spread across the other vector
+        let spread = ints(&[1, 2, 3]).broadcast(&ints(&[10]), &TokenType::Subtraction);
+        assert_eq!(spread, Ok(ints(&[-9, -8, -7])));
+
+        let half = ints(&[1, 2])
+            .scalar(&Number::Int(2), &TokenType::Division, false)
+            .unwrap();
+        assert_eq!(
+            half.elements,
+            vec![
+                Number::Ratio(Ratio::new(1, 2).unwrap()),
+                Number::Ratio(Ratio::whole(1))
+            ]
+        );
+        assert_eq!(half.to_string(), "vector[ratio]: [1/2 1] -> [size: 1x2]");
+
+        let matrix = Vector::new(vec![
+            vec![Number::Int(1), Number::Int(2)],
+            vec![Number::Int(3), Number::Int(4)],
+        ])
+        .unwrap();
+        assert_eq!(
+            matrix.to_string(),
+            "vector[int]: [[1 2]  [3 4]] -> [size: 2x2]"
+        );
+        assert!(Vector::new(vec![vec![Number::Int(1)], vec![]]).is_err());
+    }
+}